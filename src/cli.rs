@@ -0,0 +1,423 @@
+use crate::ddc_manager::{DdcError, DdcManager, Feature, WriteOutcome};
+use crate::schedule;
+use crate::settings::AppSettings;
+use serde_json::json;
+
+const USAGE: &str = "Usage: brightless [--monitor N] [--status] \
+[--get-brightness|--get-contrast|--get-volume|--get-brightness-raw] \
+[--set-brightness P|--set-contrast P|--set-volume P|--set-brightness-raw V] \
+[--apply-profile NAME] [--apply-saved-state] [--nudge DELTA] \
+[--export FILE] [--import FILE] [--diagnose]";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Command {
+    Status,
+    Get(Feature),
+    Set(Feature, u8),
+    GetBrightnessRaw,
+    SetBrightnessRaw(u16),
+    ApplyProfile(String),
+    ApplySavedState,
+    Nudge(i8),
+    Export(String),
+    Import(String),
+    Diagnose,
+}
+
+#[derive(Debug)]
+struct Invocation {
+    monitor: Option<usize>,
+    commands: Vec<Command>,
+}
+
+fn parse_percentage(flag: &str, value: Option<&String>) -> Result<u8, String> {
+    value
+        .and_then(|v| v.parse::<u8>().ok())
+        .filter(|v| *v <= 100)
+        .ok_or_else(|| format!("{} expects a percentage between 0 and 100", flag))
+}
+
+fn parse_args(args: &[String]) -> Result<Invocation, String> {
+    let mut invocation = Invocation {
+        monitor: None,
+        commands: Vec::new(),
+    };
+    // Arguments left for GTK when no command is given, which it reports
+    // itself if it doesn't know them either.
+    let mut unknown = Vec::new();
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        let command = match arg.as_str() {
+            "--status" => Command::Status,
+            "--get-brightness" => Command::Get(Feature::Brightness),
+            "--get-contrast" => Command::Get(Feature::Contrast),
+            "--get-volume" => Command::Get(Feature::Volume),
+            "--set-brightness" => {
+                Command::Set(Feature::Brightness, parse_percentage(arg, iter.next())?)
+            }
+            "--set-contrast" => {
+                Command::Set(Feature::Contrast, parse_percentage(arg, iter.next())?)
+            }
+            "--set-volume" => Command::Set(Feature::Volume, parse_percentage(arg, iter.next())?),
+            "--get-brightness-raw" => Command::GetBrightnessRaw,
+            "--set-brightness-raw" => Command::SetBrightnessRaw(
+                iter.next()
+                    .and_then(|v| v.parse::<u16>().ok())
+                    .ok_or_else(|| format!("{} expects a raw value between 0 and 65535", arg))?,
+            ),
+            "--apply-profile" | "apply-profile" => Command::ApplyProfile(
+                iter.next()
+                    .cloned()
+                    .ok_or_else(|| format!("{} expects a profile name", arg))?,
+            ),
+            "--apply-saved-state" => Command::ApplySavedState,
+            "--diagnose" => Command::Diagnose,
+            "--nudge" | "nudge" => Command::Nudge(
+                iter.next()
+                    .and_then(|v| v.parse::<i8>().ok())
+                    .filter(|v| (-100..=100).contains(v))
+                    .ok_or_else(|| format!("{} expects a change between -100 and 100", arg))?,
+            ),
+            "--export" | "--import" => {
+                let path = iter
+                    .next()
+                    .cloned()
+                    .ok_or_else(|| format!("{} expects a file path", arg))?;
+                if arg == "--export" {
+                    Command::Export(path)
+                } else {
+                    Command::Import(path)
+                }
+            }
+            "--monitor" => {
+                let index = iter
+                    .next()
+                    .and_then(|v| v.parse::<usize>().ok())
+                    .ok_or_else(|| "--monitor expects a monitor index".to_string())?;
+                invocation.monitor = Some(index);
+                continue;
+            }
+            _ => {
+                unknown.push(arg);
+                continue;
+            }
+        };
+        invocation.commands.push(command);
+    }
+    match unknown.first() {
+        Some(arg) if !invocation.commands.is_empty() => Err(format!("Unknown argument {}", arg)),
+        _ => Ok(invocation),
+    }
+}
+
+fn status_json(ddc: &mut DdcManager, indices: &[usize]) -> serde_json::Value {
+    let monitors: Vec<serde_json::Value> = indices
+        .iter()
+        .map(|&i| {
+            let feature_json = |value: Option<Result<u8, DdcError>>| match value {
+                None => json!({ "supported": false }),
+                Some(Ok(v)) => json!({ "supported": true, "percentage": v }),
+                Some(Err(e)) => json!({ "supported": true, "error": e.to_string() }),
+            };
+            let brightness = Some(ddc.get_brightness_percentage(i));
+            let contrast = ddc
+                .supports_contrast(i)
+                .then(|| ddc.get_contrast_percentage(i));
+            let volume = ddc.supports_volume(i).then(|| ddc.get_volume_percentage(i));
+            json!({
+                "index": i,
+                "name": ddc.monitors[i].name,
+                "connector": ddc.monitors[i].connector,
+                "mccs_version": ddc.monitors[i].mccs_version.map(|v| v.to_string()),
+                "brightness": feature_json(brightness),
+                "contrast": feature_json(contrast),
+                "volume": feature_json(volume),
+            })
+        })
+        .collect();
+    json!({ "monitors": monitors })
+}
+
+/// Runs a headless command if `args` contains one, returning the process
+/// exit code. Returns `None` when the GUI should start instead.
+pub fn run(args: &[String]) -> Option<i32> {
+    let invocation = match parse_args(args) {
+        Ok(invocation) if invocation.commands.is_empty() => return None,
+        Ok(invocation) => invocation,
+        Err(e) => {
+            eprintln!("{}\n{}", e, USAGE);
+            return Some(2);
+        }
+    };
+
+    let mut settings = AppSettings::load();
+
+    // Moving settings between machines needs no monitor, and diagnostics
+    // have to work when none can be used.
+    let mut code = 0;
+    for command in &invocation.commands {
+        match command {
+            Command::Export(path) => {
+                let result = serde_json::to_string_pretty(&settings)
+                    .map_err(|e| e.to_string())
+                    .and_then(|json| std::fs::write(path, json).map_err(|e| e.to_string()));
+                match result {
+                    Ok(()) => println!("Exported settings to {}", path),
+                    Err(e) => {
+                        eprintln!("Error: failed to export to {}: {}", path, e);
+                        code = 1;
+                    }
+                }
+            }
+            Command::Import(path) => {
+                let result = std::fs::read_to_string(path)
+                    .map_err(|e| e.to_string())
+                    .and_then(|contents| settings.merge_import(&contents));
+                match result {
+                    Ok(imported) => {
+                        settings = imported;
+                        match settings.save() {
+                            Ok(()) => println!("Imported settings from {}", path),
+                            Err(e) => {
+                                eprintln!("Error: failed to save settings: {}", e);
+                                code = 1;
+                            }
+                        }
+                    }
+                    Err(e) => {
+                        eprintln!("Error: failed to import {}: {}", path, e);
+                        code = 1;
+                    }
+                }
+            }
+            Command::Diagnose => print!("{}", DdcManager::diagnose(&settings.ddc_options())),
+            _ => {}
+        }
+    }
+    if invocation.commands.iter().all(|c| {
+        matches!(
+            c,
+            Command::Export(_) | Command::Import(_) | Command::Diagnose
+        )
+    }) {
+        return Some(code);
+    }
+
+    for command in &invocation.commands {
+        if let Command::ApplyProfile(name) = command {
+            if !settings.profiles.contains_key(name) {
+                eprintln!("Error: no profile named \"{}\"", name);
+                return Some(1);
+            }
+        }
+    }
+
+    let mut ddc = match DdcManager::with_options(&settings.ddc_options()) {
+        Ok(ddc) => ddc,
+        Err(DdcError::NoneDiscovered(report)) => {
+            eprintln!("Error: no DDC monitors found\n{}", report);
+            return Some(1);
+        }
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            return Some(1);
+        }
+    };
+    let keys: Vec<(&str, &str)> = ddc
+        .monitors
+        .iter()
+        .map(|m| (m.name.as_str(), m.settings_key()))
+        .collect();
+    if settings.adopt_monitor_keys(&keys) {
+        let _ = settings.save();
+    }
+    settings.apply_brightness_floors(&mut ddc);
+    ddc.set_brightness_ceiling(
+        schedule::local_minute_of_day().and_then(|m| settings.night_cap.ceiling_at(m)),
+    );
+
+    let indices: Vec<usize> = match invocation.monitor {
+        Some(index) if index < ddc.monitors.len() => vec![index],
+        Some(index) => {
+            eprintln!("Error: no monitor with index {}", index);
+            return Some(1);
+        }
+        None => (0..ddc.monitors.len()).collect(),
+    };
+
+    for command in &invocation.commands {
+        match command {
+            Command::Export(_) | Command::Import(_) | Command::Diagnose => {}
+            Command::Status => {
+                println!("{:#}", status_json(&mut ddc, &indices));
+            }
+            Command::Get(feature) => {
+                for &i in &indices {
+                    match ddc.get_percentage(i, *feature) {
+                        Ok(v) => println!("{}: {}: {}%", i, ddc.monitors[i].name, v),
+                        Err(e) => {
+                            eprintln!("{}: {}: {}", i, ddc.monitors[i].name, e);
+                            code = 1;
+                        }
+                    }
+                }
+            }
+            Command::Set(feature, percentage) => {
+                for &i in &indices {
+                    match ddc.set_percentage(i, *feature, *percentage) {
+                        Ok(WriteOutcome::Clamped { achieved, .. }) => eprintln!(
+                            "{}: {}: monitor set {} to {}% instead of {}%",
+                            i,
+                            ddc.monitors[i].name,
+                            feature.name(),
+                            achieved,
+                            percentage
+                        ),
+                        Ok(_) => {}
+                        Err(e) => {
+                            eprintln!(
+                                "{}: {}: failed to set {}: {}",
+                                i,
+                                ddc.monitors[i].name,
+                                feature.name(),
+                                e
+                            );
+                            code = 1;
+                        }
+                    }
+                }
+            }
+            Command::GetBrightnessRaw => {
+                for &i in &indices {
+                    match ddc.get_brightness_raw(i) {
+                        Ok((value, max)) => {
+                            println!("{}: {}: {} / {}", i, ddc.monitors[i].name, value, max)
+                        }
+                        Err(e) => {
+                            eprintln!("{}: {}: {}", i, ddc.monitors[i].name, e);
+                            code = 1;
+                        }
+                    }
+                }
+            }
+            Command::SetBrightnessRaw(value) => {
+                for &i in &indices {
+                    match ddc.set_brightness_raw(i, *value) {
+                        Ok(raw) if raw != *value => eprintln!(
+                            "{}: {}: monitor set brightness to {} instead of {}",
+                            i, ddc.monitors[i].name, raw, value
+                        ),
+                        Ok(_) => {}
+                        Err(e) => {
+                            eprintln!(
+                                "{}: {}: failed to set brightness: {}",
+                                i, ddc.monitors[i].name, e
+                            );
+                            code = 1;
+                        }
+                    }
+                }
+            }
+            Command::ApplyProfile(name) => {
+                let summary = settings.profiles[name].apply(&mut ddc);
+                for monitor in &summary.applied {
+                    println!("{}: applied \"{}\"", monitor, name);
+                }
+                for monitor in &summary.failed {
+                    eprintln!("{}: failed to apply \"{}\"", monitor, name);
+                    code = 1;
+                }
+                for monitor in &summary.missing {
+                    println!("{}: not connected, skipped", monitor);
+                }
+            }
+            Command::Nudge(delta) => {
+                let default = invocation
+                    .monitor
+                    .is_none()
+                    .then_some(settings.default_monitor.as_ref())
+                    .flatten()
+                    .and_then(|key| ddc.monitors.iter().position(|m| m.settings_key() == key));
+                let targets = default.map_or_else(|| indices.clone(), |i| vec![i]);
+                for i in targets {
+                    match ddc.adjust_brightness_percentage(i, *delta) {
+                        Ok(v) => println!("{}: {}: {}%", i, ddc.monitors[i].name, v),
+                        Err(e) => {
+                            eprintln!(
+                                "{}: {}: failed to adjust brightness: {}",
+                                i, ddc.monitors[i].name, e
+                            );
+                            code = 1;
+                        }
+                    }
+                }
+            }
+            Command::ApplySavedState => {
+                for &i in &indices {
+                    let name = ddc.monitors[i].name.clone();
+                    let Some(state) = settings.monitor_states.get(ddc.monitors[i].settings_key())
+                    else {
+                        println!("{}: {}: no saved state", i, name);
+                        continue;
+                    };
+                    match ddc.apply_saved_state(i, state) {
+                        Ok(changes) if changes.is_empty() => {
+                            println!("{}: {}: already up to date", i, name)
+                        }
+                        Ok(changes) => {
+                            for change in changes {
+                                println!(
+                                    "{}: {}: {} {}% -> {}%",
+                                    i,
+                                    name,
+                                    change.feature.name(),
+                                    change.from,
+                                    change.to
+                                );
+                            }
+                        }
+                        Err(e) => {
+                            eprintln!("{}: {}: {}", i, name, e);
+                            code = 1;
+                        }
+                    }
+                }
+            }
+        }
+    }
+    Some(code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|a| a.to_string()).collect()
+    }
+
+    #[test]
+    fn nudges_take_a_signed_change_of_at_most_100() {
+        let invocation = parse_args(&args(&["nudge", "-10"])).unwrap();
+        assert_eq!(invocation.commands, [Command::Nudge(-10)]);
+        let invocation = parse_args(&args(&["--nudge", "+10"])).unwrap();
+        assert_eq!(invocation.commands, [Command::Nudge(10)]);
+        assert!(parse_args(&args(&["--nudge", "101"])).is_err());
+        assert!(parse_args(&args(&["--nudge"])).is_err());
+    }
+
+    #[test]
+    fn unknown_arguments_are_rejected_next_to_a_command() {
+        let invocation = parse_args(&args(&["--monitor", "1", "--set-brightness", "40"])).unwrap();
+        assert_eq!(invocation.monitor, Some(1));
+        assert_eq!(invocation.commands, [Command::Set(Feature::Brightness, 40)]);
+
+        let error = parse_args(&args(&["--set-brightness", "40", "--monitr", "1"])).unwrap_err();
+        assert_eq!(error, "Unknown argument --monitr");
+        // Without a command they are left for GTK.
+        assert!(parse_args(&args(&["--gapplication-service"]))
+            .unwrap()
+            .commands
+            .is_empty());
+    }
+}
@@ -1,23 +1,69 @@
-use ddc::Ddc;
+use ddc::{Ddc, Edid};
 use ddc_i2c::I2cDdc;
-use i2c_linux::I2c;
-use std::collections::HashMap;
+use i2c_linux::{Functionality, I2c, ReadWrite};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt::Write as _;
 use std::fs;
 use std::fs::File;
 use std::io::Read;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
 use thiserror::Error;
 
+const WARM_UP_DELAY_MS: u64 = 50;
+
+/// Pause before retrying a failed DDC command, the minimum gap DDC/CI
+/// expects between commands.
+const RETRY_DELAY_MS: u64 = 50;
+
+/// 7-bit I2C address monitors answer DDC/CI on.
+const DDC_CI_ADDRESS: u16 = 0x37;
+
+/// How long `DdcManager::identify` holds the pulse before restoring.
+pub const IDENTIFY_PULSE_MS: u64 = 600;
+
+/// VCP features `DdcManager::diagnose` reads from every monitor it reaches.
+const DIAGNOSTIC_FEATURES: &[(u8, &str)] = &[
+    (0x10, "Brightness"),
+    (0x12, "Contrast"),
+    (0x14, "Color preset"),
+    (0x16, "Red gain"),
+    (0x18, "Green gain"),
+    (0x1A, "Blue gain"),
+    (0x60, "Input source"),
+    (0x62, "Volume"),
+    (0x6C, "Red black level"),
+    (0x6E, "Green black level"),
+    (0x70, "Blue black level"),
+    (0xB6, "Display technology"),
+    (0xCA, "OSD"),
+    (0xD6, "Power mode"),
+    (0xDF, "VCP version"),
+];
+
 #[derive(Error, Debug)]
 pub enum DdcError {
     #[error("Failed to open I2C device: {0}")]
     OpenError(String),
     #[error("DDC communication error: {0}")]
     CommError(String),
+    #[error("{0} is not supported by this monitor")]
+    Unsupported(&'static str),
     #[error("No DDC monitors found")]
     NoMonitors,
+    #[error("Rolled back after another monitor failed")]
+    RolledBack,
     #[error("Permission denied: {0}")]
     PermissionDenied(String),
+    #[error("Timed out: {0}")]
+    Timeout(String),
+    #[error("No DDC monitors found")]
+    NoneDiscovered(DiscoveryReport),
+    #[error("Unknown input source \"{0}\"")]
+    UnknownInputSource(String),
     #[error("IO error: {0}")]
     IoError(#[from] std::io::Error),
 }
@@ -37,6 +83,25 @@ pub enum InputSource {
 }
 
 impl InputSource {
+    /// Every named input, in the order the UI lists them.
+    pub const ALL: [InputSource; 9] = [
+        InputSource::Vga1,
+        InputSource::Dvi1,
+        InputSource::DisplayPort1,
+        InputSource::DisplayPort2,
+        InputSource::Hdmi1,
+        InputSource::Hdmi2,
+        InputSource::Hdmi3,
+        InputSource::Hdmi4,
+        InputSource::UsbC,
+    ];
+
+    /// Decodes a VCP 0x60 reading. MCCS 2.2 and later only define the low
+    /// byte; several monitors leave vendor data in the high byte.
+    pub fn from_vcp_value(value: u16) -> Self {
+        Self::from_code((value & 0xff) as u8)
+    }
+
     pub fn from_code(code: u8) -> Self {
         match code {
             0x01 => InputSource::Vga1,
@@ -67,6 +132,35 @@ impl InputSource {
         }
     }
 
+    /// Inverse of `name`, ignoring case, spaces, dashes and underscores.
+    /// Also accepts short aliases such as `"dp1"`, `"hdmi"` and `"usbc"`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        let normalized: String = name
+            .chars()
+            .filter(|c| !matches!(c, ' ' | '-' | '_'))
+            .flat_map(char::to_lowercase)
+            .collect();
+        let alias = match normalized.as_str() {
+            "vga1" => Some(InputSource::Vga1),
+            "dvi1" => Some(InputSource::Dvi1),
+            "dp" | "dp1" | "displayport" => Some(InputSource::DisplayPort1),
+            "dp2" => Some(InputSource::DisplayPort2),
+            "hdmi" => Some(InputSource::Hdmi1),
+            "typec" => Some(InputSource::UsbC),
+            _ => None,
+        };
+        alias.or_else(|| {
+            Self::ALL.into_iter().find(|source| {
+                source
+                    .name()
+                    .chars()
+                    .filter(|c| !matches!(c, ' ' | '-'))
+                    .flat_map(char::to_lowercase)
+                    .eq(normalized.chars())
+            })
+        })
+    }
+
     pub fn name(&self) -> &str {
         match self {
             InputSource::Vga1 => "VGA",
@@ -83,6 +177,50 @@ impl InputSource {
     }
 }
 
+/// Panel type, as reported on VCP 0xB6.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DisplayTechnology {
+    Crt,
+    Lcd,
+    Lcos,
+    Plasma,
+    Oled,
+    Electroluminescent,
+    Mems,
+    /// Not reported, or a value MCCS doesn't define.
+    #[default]
+    Unknown,
+}
+
+impl DisplayTechnology {
+    /// Decodes a VCP 0xB6 reading; the type is the low byte.
+    pub fn from_vcp_value(value: u16) -> Self {
+        match value & 0xff {
+            0x01 | 0x02 => DisplayTechnology::Crt,
+            0x03 => DisplayTechnology::Lcd,
+            0x04 => DisplayTechnology::Lcos,
+            0x05 => DisplayTechnology::Plasma,
+            0x06 => DisplayTechnology::Oled,
+            0x07 => DisplayTechnology::Electroluminescent,
+            0x08 | 0x09 => DisplayTechnology::Mems,
+            _ => DisplayTechnology::Unknown,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            DisplayTechnology::Crt => "CRT",
+            DisplayTechnology::Lcd => "LCD",
+            DisplayTechnology::Lcos => "LCoS",
+            DisplayTechnology::Plasma => "Plasma",
+            DisplayTechnology::Oled => "OLED",
+            DisplayTechnology::Electroluminescent => "EL",
+            DisplayTechnology::Mems => "MEMS",
+            DisplayTechnology::Unknown => "Unknown",
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum PowerMode {
     On,
@@ -94,6 +232,21 @@ pub enum PowerMode {
 }
 
 impl PowerMode {
+    /// Every named mode, in the order the UI lists them.
+    pub const ALL: [PowerMode; 5] = [
+        PowerMode::On,
+        PowerMode::Standby,
+        PowerMode::Suspend,
+        PowerMode::Off,
+        PowerMode::Normal,
+    ];
+
+    /// Decodes a VCP 0xD6 reading. The mode is the low byte; some monitors
+    /// report status flags in the high byte.
+    pub fn from_vcp_value(value: u16) -> Self {
+        Self::from_code((value & 0xff) as u8)
+    }
+
     pub fn from_code(code: u8) -> Self {
         match code {
             0x01 => PowerMode::On,
@@ -126,515 +279,3979 @@ impl PowerMode {
             PowerMode::Unknown(_) => "Unknown",
         }
     }
-}
 
-pub struct Monitor {
-    pub handle: I2cDdc<I2c<File>>,
-    pub name: String,
-    pub connector: String,
-    pub min_brightness: u16,
-    pub max_brightness: u16,
-    pub min_contrast: u16,
-    pub max_contrast: u16,
-    pub min_volume: u16,
-    pub max_volume: u16,
-    pub supports_input_source: bool,
-    pub supports_power_mode: bool,
+    /// Whether the monitor is in a power-saving state, where it may not
+    /// answer reads of its other features.
+    pub fn is_asleep(&self) -> bool {
+        matches!(
+            self,
+            PowerMode::Standby | PowerMode::Suspend | PowerMode::Off
+        )
+    }
 }
 
-pub struct DdcManager {
-    pub monitors: Vec<Monitor>,
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorChannel {
+    Red,
+    Green,
+    Blue,
 }
 
-impl DdcManager {
-    pub fn new() -> Result<Self, DdcError> {
-        let monitors = Self::discover_monitors()?;
-        Ok(Self { monitors })
-    }
-
-    fn get_connected_connectors() -> Vec<String> {
-        let mut connectors = Vec::new();
+impl ColorChannel {
+    pub const ALL: [ColorChannel; 3] = [ColorChannel::Red, ColorChannel::Green, ColorChannel::Blue];
 
-        if let Ok(entries) = fs::read_dir("/sys/class/drm") {
-            for entry in entries.flatten() {
-                let path = entry.path();
-                if path.is_dir() {
-                    if let Some(name) = path.file_name().and_then(|n| n.to_str()) {
-                        if !name.starts_with("card") || name.contains("-") {
-                            let status_path = path.join("status");
-                            if let Ok(status) = fs::read_to_string(&status_path) {
-                                if status.trim() == "connected" {
-                                    connectors.push(name.to_string());
-                                }
-                            }
-                        }
-                    }
-                }
-            }
+    pub fn name(&self) -> &str {
+        match self {
+            ColorChannel::Red => "Red",
+            ColorChannel::Green => "Green",
+            ColorChannel::Blue => "Blue",
         }
-
-        connectors
     }
 
-    fn read_edid(connector: &str) -> Option<Vec<u8>> {
-        let edid_path = format!("/sys/class/drm/{}/edid", connector);
-        let path = Path::new(&edid_path);
-
-        if path.exists() {
-            let mut file = match File::open(path) {
-                Ok(f) => f,
-                Err(_) => return None,
-            };
-
-            let mut data = Vec::new();
-            if file.read_to_end(&mut data).is_ok() && !data.is_empty() && data.len() >= 128 {
-                return Some(data);
-            }
+    /// VCP code of the channel's video gain (drive).
+    pub fn gain_code(&self) -> u8 {
+        match self {
+            ColorChannel::Red => 0x16,
+            ColorChannel::Green => 0x18,
+            ColorChannel::Blue => 0x1a,
         }
-        None
     }
 
-    fn parse_edid_name(edid: &[u8]) -> Option<String> {
-        if edid.len() < 128 {
-            return None;
+    /// VCP code of the channel's black level (bias).
+    pub fn bias_code(&self) -> u8 {
+        match self {
+            ColorChannel::Red => 0x6c,
+            ColorChannel::Green => 0x6e,
+            ColorChannel::Blue => 0x70,
         }
+    }
 
-        if edid[0] != 0x00
-            || edid[1] != 0xFF
-            || edid[2] != 0xFF
-            || edid[3] != 0xFF
-            || edid[4] != 0xFF
-            || edid[5] != 0xFF
-            || edid[6] != 0xFF
-            || edid[7] != 0x00
-        {
-            return None;
+    fn position(&self) -> usize {
+        match self {
+            ColorChannel::Red => 0,
+            ColorChannel::Green => 1,
+            ColorChannel::Blue => 2,
         }
+    }
+}
 
-        let mfg_id =
-            ((edid[8].saturating_sub(64) as usize) * 64) + (edid[9].saturating_sub(64) as usize);
-        let mfg_chars = [
-            ((mfg_id >> 10) & 0x1F) as u8,
-            ((mfg_id >> 5) & 0x1F) as u8,
-            (mfg_id & 0x1F) as u8,
-        ];
-
-        let manufacturer: String = mfg_chars
-            .iter()
-            .filter_map(|&c| {
-                if c == 0 || c > 26 {
-                    None
-                } else {
-                    Some((c + 64) as char)
-                }
-            })
-            .collect();
-
-        let product_code = u16::from_be_bytes([edid[10], edid[11]]);
-
-        let mut name = String::new();
-        for i in 0..14 {
-            let offset = 0x36 + (i * 18);
-            if offset + 18 > edid.len() {
-                break;
-            }
-            if edid[offset] == 0x00 && edid[offset + 1] == 0x00 && edid[offset + 2] == 0x00 {
-                if edid[offset + 3] == 0xFC {
-                    for j in 0..13 {
-                        let c = edid[offset + 5 + j];
-                        if c == 0x0A {
-                            break;
-                        }
-                        if c >= 0x20 && c < 0x7F {
-                            name.push(c as char);
-                        }
-                    }
-                    break;
-                }
-            }
-        }
+/// MCCS version reported through VCP 0xDF.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct MccsVersion {
+    pub major: u8,
+    pub minor: u8,
+}
 
-        if name.is_empty() {
-            Some(format!("{} {:04x}", manufacturer, product_code))
-        } else {
-            Some(name)
-        }
+impl MccsVersion {
+    pub fn at_least(&self, major: u8, minor: u8) -> bool {
+        (self.major, self.minor) >= (major, minor)
     }
+}
 
-    fn get_brightness_range(ddc: &mut I2cDdc<I2c<File>>) -> Option<(u16, u16)> {
-        match ddc.get_vcp_feature(0x10) {
-            Ok(vcp) => Some((0, vcp.maximum())),
-            Err(_) => None,
-        }
+impl std::fmt::Display for MccsVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}", self.major, self.minor)
     }
+}
 
-    fn get_contrast_range(ddc: &mut I2cDdc<I2c<File>>) -> Option<(u16, u16)> {
-        match ddc.get_vcp_feature(0x12) {
-            Ok(vcp) => Some((0, vcp.maximum())),
-            Err(_) => None,
-        }
+/// Maps a raw VCP value onto 0-100 within `min..=max`.
+pub fn raw_to_percentage(raw: u16, min: u16, max: u16) -> u8 {
+    if max <= min {
+        return 0;
     }
+    let raw = raw.clamp(min, max);
+    (((raw - min) as u32 * 100) / (max - min) as u32) as u8
+}
 
-    fn get_volume_range(ddc: &mut I2cDdc<I2c<File>>) -> Option<(u16, u16)> {
-        match ddc.get_vcp_feature(0x62) {
-            Ok(vcp) => Some((0, vcp.maximum())),
-            Err(_) => None,
-        }
+/// Maps a 0-100 percentage onto a raw VCP value within `min..=max`.
+pub fn percentage_to_raw(percentage: u8, min: u16, max: u16) -> u16 {
+    if max <= min {
+        return min;
     }
+    let percentage = percentage.min(100) as u32;
+    min + ((percentage * (max - min) as u32) / 100) as u16
+}
 
-    fn check_input_source_support(ddc: &mut I2cDdc<I2c<File>>) -> bool {
-        match ddc.get_vcp_feature(0x60) {
-            Ok(vcp) => {
-                let value = vcp.value();
-                value >= 1 && value <= 27
-            }
-            Err(_) => false,
+/// Whether `code` is listed as a feature in the `vcp(...)` section of a
+/// monitor's capabilities string. Value lists nested in parentheses after a
+/// feature are skipped.
+pub fn capabilities_list_vcp(caps: &str, code: u8) -> bool {
+    let caps = caps.to_ascii_lowercase();
+    let Some(start) = caps.find("vcp(") else {
+        return false;
+    };
+    let mut depth = 1;
+    let mut token = String::new();
+    for c in caps[start + 4..].chars() {
+        if c.is_ascii_alphanumeric() {
+            token.push(c);
+            continue;
         }
-    }
-
-    fn check_power_mode_support(ddc: &mut I2cDdc<I2c<File>>) -> bool {
-        match ddc.get_vcp_feature(0xd6) {
-            Ok(vcp) => {
-                let value = vcp.value();
-                value >= 1 && value <= 5
-            }
-            Err(_) => false,
+        if depth == 1 && u8::from_str_radix(&token, 16) == Ok(code) {
+            return true;
+        }
+        token.clear();
+        match c {
+            '(' => depth += 1,
+            ')' if depth == 1 => return false,
+            ')' => depth -= 1,
+            _ => {}
         }
     }
+    false
+}
 
-    fn test_ddc_connection(
-        path: &str,
-    ) -> Option<(I2cDdc<I2c<File>>, u16, u16, u16, u16, u16, u16, bool, bool)> {
-        match I2c::from_path(path) {
-            Ok(i2c) => {
-                let mut ddc = I2cDdc::new(i2c);
-                if let Some((min_brightness, max_brightness)) = Self::get_brightness_range(&mut ddc)
-                {
-                    let (min_contrast, max_contrast) =
-                        Self::get_contrast_range(&mut ddc).unwrap_or((0, 0));
-                    let (min_volume, max_volume) =
-                        Self::get_volume_range(&mut ddc).unwrap_or((0, 0));
-                    let supports_input_source = Self::check_input_source_support(&mut ddc);
-                    let supports_power_mode = Self::check_power_mode_support(&mut ddc);
-                    return Some((
-                        ddc,
-                        min_brightness,
-                        max_brightness,
-                        min_contrast,
-                        max_contrast,
-                        min_volume,
-                        max_volume,
-                        supports_input_source,
-                        supports_power_mode,
-                    ));
+/// Values listed for `code` in the `vcp(...)` section of a capabilities
+/// string, e.g. the presets in `14(05 08 0B)`; `None` if the feature is not
+/// listed or lists no values.
+pub fn capabilities_vcp_values(caps: &str, code: u8) -> Option<Vec<u8>> {
+    let caps = caps.to_ascii_lowercase();
+    let start = caps.find("vcp(")?;
+    let mut depth = 1;
+    let mut token = String::new();
+    let mut values: Option<Vec<u8>> = None;
+    for c in caps[start + 4..].chars() {
+        if c.is_ascii_alphanumeric() {
+            token.push(c);
+            continue;
+        }
+        match depth {
+            1 if c == '(' && u8::from_str_radix(&token, 16) == Ok(code) => {
+                values = Some(Vec::new());
+            }
+            2 if values.is_some() => {
+                if let (Some(values), Ok(value)) = (&mut values, u8::from_str_radix(&token, 16)) {
+                    values.push(value);
+                }
+                if c == ')' {
+                    return values.filter(|v| !v.is_empty());
                 }
             }
-            Err(_) => {}
+            _ => {}
+        }
+        token.clear();
+        match c {
+            '(' => depth += 1,
+            ')' if depth == 1 => return None,
+            ')' => depth -= 1,
+            _ => {}
         }
-        None
     }
+    None
+}
 
-    fn discover_monitors() -> Result<Vec<Monitor>, DdcError> {
-        let connectors = Self::get_connected_connectors();
+/// An on/off feature in the manufacturer range (VCP 0xE0-0xFF), such as an
+/// OLED's logo dimming or pixel shift. MCCS does not define these codes,
+/// so they are only known by number.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VendorToggle {
+    pub code: u8,
+    pub off: u8,
+    pub on: u8,
+}
 
-        if connectors.is_empty() {
-            return Err(DdcError::NoMonitors);
-        }
+/// Manufacturer features a capabilities string lists with exactly two
+/// values, taking the lower one as off.
+pub fn capabilities_vendor_toggles(caps: &str) -> Vec<VendorToggle> {
+    (0xe0..=0xff)
+        .filter_map(
+            |code| match capabilities_vcp_values(caps, code)?.as_slice() {
+                &[a, b] if a != b => Some(VendorToggle {
+                    code,
+                    off: a.min(b),
+                    on: a.max(b),
+                }),
+                _ => None,
+            },
+        )
+        .collect()
+}
 
-        let mut monitors: Vec<Monitor> = Vec::new();
-        let mut used_i2c: HashMap<String, bool> = HashMap::new();
+/// Color temperature range of the Kelvin slider on monitors with
+/// adjustable RGB gain.
+pub const MIN_KELVIN: u32 = 3000;
+pub const MAX_KELVIN: u32 = 10000;
 
-        for connector in &connectors {
-            let edid = Self::read_edid(connector);
-            let name = edid
-                .as_ref()
-                .and_then(|e| Self::parse_edid_name(e))
-                .unwrap_or_else(|| "Unknown Monitor".to_string());
+/// Color temperature presets of VCP 0x14: `(value, Kelvin)`.
+const COLOR_TEMPERATURE_PRESETS: [(u8, u32); 8] = [
+    (0x03, 4000),
+    (0x04, 5000),
+    (0x05, 6500),
+    (0x06, 7500),
+    (0x07, 8200),
+    (0x08, 9300),
+    (0x09, 10000),
+    (0x0a, 11500),
+];
 
-            let entries = fs::read_dir("/dev").map_err(|e| DdcError::OpenError(e.to_string()))?;
+/// Relative red, green and blue gain (0.0-1.0, the strongest channel at
+/// 1.0) that makes white look like a black body at `kelvin`, after Tanner
+/// Helland's fit of the CIE data.
+pub fn kelvin_to_rgb_gains(kelvin: u32) -> [f64; 3] {
+    let t = kelvin.clamp(1000, 40000) as f64 / 100.0;
+    let red = if t <= 66.0 {
+        255.0
+    } else {
+        329.698727446 * (t - 60.0).powf(-0.1332047592)
+    };
+    let green = if t <= 66.0 {
+        99.4708025861 * t.ln() - 161.1195681661
+    } else {
+        288.1221695283 * (t - 60.0).powf(-0.0755148492)
+    };
+    let blue = if t >= 66.0 {
+        255.0
+    } else if t <= 19.0 {
+        0.0
+    } else {
+        138.5177312231 * (t - 10.0).ln() - 305.0447927307
+    };
+    let rgb = [red, green, blue].map(|c| c.clamp(0.0, 255.0));
+    let strongest = rgb.iter().cloned().fold(0.0, f64::max);
+    rgb.map(|c| c / strongest)
+}
 
-            for entry in entries.flatten() {
-                let path = entry.path();
-                let path_str = path.to_string_lossy().to_string();
+/// How a monitor's color temperature can be set.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ColorTemperatureControl {
+    Unsupported,
+    /// Any temperature, through the red, green and blue gains.
+    Gains,
+    /// Only the listed preset temperatures in Kelvin, through VCP 0x14.
+    Presets(Vec<u32>),
+}
 
-                if !path_str.starts_with("/dev/i2c-") {
-                    continue;
-                }
+/// Exponent of the perceptual brightness curve, close to how both displays
+/// and eyes respond to light.
+const PERCEPTUAL_GAMMA: f64 = 2.2;
 
-                if used_i2c.contains_key(&path_str) {
-                    continue;
-                }
+/// How brightness slider positions map onto raw VCP values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BrightnessCurve {
+    #[default]
+    Linear,
+    /// Gamma 2.2, so equal slider steps look like equal brightness changes.
+    Perceptual,
+}
 
-                if let Some((
-                    handle,
-                    min_brightness,
-                    max_brightness,
-                    min_contrast,
-                    max_contrast,
-                    min_volume,
-                    max_volume,
-                    supports_input_source,
-                    supports_power_mode,
-                )) = Self::test_ddc_connection(&path_str)
-                {
-                    used_i2c.insert(path_str, true);
-
-                    monitors.push(Monitor {
-                        handle,
-                        name: name.clone(),
-                        connector: connector.clone(),
-                        min_brightness,
-                        max_brightness,
-                        min_contrast,
-                        max_contrast,
-                        min_volume,
-                        max_volume,
-                        supports_input_source,
-                        supports_power_mode,
-                    });
-                    break;
+impl BrightnessCurve {
+    pub fn percentage_to_raw(self, percentage: u8, min: u16, max: u16) -> u16 {
+        match self {
+            BrightnessCurve::Linear => percentage_to_raw(percentage, min, max),
+            BrightnessCurve::Perceptual => {
+                if max <= min {
+                    return min;
                 }
+                let fraction = (percentage.min(100) as f64 / 100.0).powf(PERCEPTUAL_GAMMA);
+                min + (fraction * (max - min) as f64).round() as u16
             }
         }
+    }
 
-        if monitors.is_empty() {
-            return Err(DdcError::NoMonitors);
-        }
-
-        for (i, monitor) in monitors.iter_mut().enumerate() {
-            if monitor.name == "Unknown Monitor" {
-                monitor.name = format!("Monitor {}", i + 1);
+    pub fn raw_to_percentage(self, raw: u16, min: u16, max: u16) -> u8 {
+        match self {
+            BrightnessCurve::Linear => raw_to_percentage(raw, min, max),
+            BrightnessCurve::Perceptual => {
+                if max <= min {
+                    return 0;
+                }
+                let fraction = (raw.clamp(min, max) - min) as f64 / (max - min) as f64;
+                (fraction.powf(1.0 / PERCEPTUAL_GAMMA) * 100.0).round() as u8
             }
         }
-
-        Ok(monitors)
     }
+}
 
-    pub fn get_brightness_percentage(&mut self, index: usize) -> Result<u8, DdcError> {
-        if index >= self.monitors.len() {
-            return Err(DdcError::NoMonitors);
-        }
+pub struct Monitor {
+    pub handle: I2cDdc<I2c<File>>,
+    pub name: String,
+    /// Name from the EDID, or "Unknown Monitor", before numbering and the
+    /// port suffix for shared names are applied to make up `name`.
+    pub edid_name: String,
+    pub connector: String,
+    pub min_brightness: u16,
+    pub max_brightness: u16,
+    pub min_contrast: u16,
+    pub max_contrast: u16,
+    pub min_volume: u16,
+    pub max_volume: u16,
+    pub supports_input_source: bool,
+    pub supports_power_mode: bool,
+    /// Whether the monitor accepts writes at all. Some locked-down systems
+    /// only let DDC/CI reads through.
+    pub writable: bool,
+    /// Whether the monitor reports OSD enable (VCP 0xCA).
+    pub supports_osd_lock: bool,
+    /// Whether writes to VCP 0xCA are accepted; some monitors only report
+    /// the OSD state.
+    pub osd_lock_writable: bool,
+    /// Whether the monitor answers on `invert_code`.
+    pub supports_invert: bool,
+    /// Manufacturer VCP code that inverts the image, as configured in
+    /// `DdcOptions::invert_codes`; MCCS defines none.
+    pub invert_code: u8,
+    /// Maximum black level per channel, in `ColorChannel` order; 0 when the
+    /// monitor does not support that channel's bias.
+    pub max_rgb_bias: [u16; 3],
+    /// Maximum video gain per channel, in `ColorChannel` order; 0 when the
+    /// monitor does not support that channel's gain.
+    pub max_rgb_gain: [u16; 3],
+    /// Color temperature presets (VCP 0x14 values) the monitor lists in its
+    /// capabilities.
+    pub color_temperature_presets: Vec<u8>,
+    /// Manufacturer on/off features the monitor lists in its capabilities.
+    pub vendor_toggles: Vec<VendorToggle>,
+    /// Panel type from VCP 0xB6; `Unknown` when the monitor doesn't say.
+    pub tech_type: DisplayTechnology,
+    pub mccs_version: Option<MccsVersion>,
+    /// Identifies the physical panel across connectors; `None` when the EDID
+    /// carries no serial number.
+    pub edid_serial: Option<String>,
+    pub i2c_path: String,
+    /// I2C devices that could drive this connector, the ones linked to it in
+    /// sysfs first.
+    pub i2c_candidates: Vec<String>,
+}
 
-        let vcp = self.monitors[index]
-            .handle
-            .get_vcp_feature(0x10)
-            .map_err(|e| DdcError::CommError(format!("Failed to get brightness: {}", e)))?;
+/// What sysfs reports about the output a monitor is plugged into.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConnectorInfo {
+    /// Connector without its card prefix, e.g. `"DP-1"`.
+    pub port: String,
+    /// Width and height of the first listed mode, which the kernel puts
+    /// first as the monitor's preferred one.
+    pub native_mode: Option<(u32, u32)>,
+    /// Whether the output is currently lit; `None` when unknown.
+    pub enabled: Option<bool>,
+}
 
-        let current = vcp.value();
-        let min = self.monitors[index].min_brightness;
-        let max = self.monitors[index].max_brightness;
+/// First `WxH` mode in the contents of a connector's `modes` file, ignoring
+/// suffixes such as `i` for interlaced modes.
+pub fn parse_native_mode(modes: &str) -> Option<(u32, u32)> {
+    let (width, height) = modes.lines().next()?.trim().split_once('x')?;
+    let height: String = height.chars().take_while(char::is_ascii_digit).collect();
+    Some((width.parse().ok()?, height.parse().ok()?))
+}
 
-        if max <= min {
-            return Ok(0);
-        }
+/// Port part of a DRM connector name, e.g. `DP-1` for `card0-DP-1`; other
+/// connectors, such as I2C devices, are returned as they are.
+fn connector_port(connector: &str) -> &str {
+    match connector.split_once('-') {
+        Some((card, port)) if card.starts_with("card") => port,
+        _ => connector,
+    }
+}
 
-        let current = current.clamp(min, max);
-        let percentage = ((current.saturating_sub(min)) * 100) / (max - min);
-        Ok(percentage as u8)
+impl Monitor {
+    /// Key for this monitor's entries in per-monitor settings: its EDID
+    /// serial, which stays with the panel when its name or port changes, or
+    /// its name when the EDID has no serial.
+    pub fn settings_key(&self) -> &str {
+        self.edid_serial.as_deref().unwrap_or(&self.name)
     }
 
-    pub fn set_brightness_percentage(
-        &mut self,
-        index: usize,
-        percentage: u8,
-    ) -> Result<(), DdcError> {
-        if index >= self.monitors.len() {
-            return Err(DdcError::NoMonitors);
+    /// Reads the connector's port name, native mode and state from `drm`,
+    /// usually `/sys/class/drm`. Monitors found without a DRM connector
+    /// only have their I2C device as the port.
+    pub fn connector_info(&self, drm: &Path) -> ConnectorInfo {
+        let dir = drm.join(&self.connector);
+        ConnectorInfo {
+            port: connector_port(&self.connector).to_string(),
+            native_mode: fs::read_to_string(dir.join("modes"))
+                .ok()
+                .and_then(|modes| parse_native_mode(&modes)),
+            enabled: fs::read_to_string(dir.join("enabled"))
+                .ok()
+                .map(|state| state.trim() == "enabled"),
         }
+    }
 
-        let min = self.monitors[index].min_brightness;
-        let max = self.monitors[index].max_brightness;
-
-        if max <= min {
-            return Ok(());
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            contrast: (self.min_contrast, self.max_contrast),
+            volume: (self.min_volume, self.max_volume),
+            supports_input_source: self.supports_input_source,
+            supports_power_mode: self.supports_power_mode,
+            supports_osd_lock: self.supports_osd_lock,
+            osd_lock_writable: self.osd_lock_writable,
+            max_rgb_bias: self.max_rgb_bias,
+            max_rgb_gain: self.max_rgb_gain,
+            color_temperature_presets: self.color_temperature_presets.clone(),
+            vendor_toggles: self.vendor_toggles.clone(),
+            tech_type: self.tech_type,
         }
+    }
+}
 
-        let percentage = percentage.clamp(0, 100);
-        let raw = min + ((percentage as u16 * (max - min)) / 100);
+/// Color features copied as raw VCP values: `(name, code)`.
+const COLOR_FEATURES: [(&str, u8); 4] = [
+    ("Color preset", 0x14),
+    ("Red gain", 0x16),
+    ("Green gain", 0x18),
+    ("Blue gain", 0x1a),
+];
 
-        self.monitors[index]
-            .handle
-            .set_vcp_feature(0x10, raw)
-            .map_err(|e| DdcError::CommError(format!("Failed to set brightness: {}", e)))?;
+/// Outcome of `DdcManager::copy_settings`.
+#[derive(Debug, Default)]
+pub struct CopyReport {
+    pub copied: Vec<&'static str>,
+    pub skipped: Vec<&'static str>,
+}
 
-        Ok(())
+impl CopyReport {
+    fn record(&mut self, name: &'static str, copied: bool) {
+        if copied {
+            self.copied.push(name);
+        } else {
+            self.skipped.push(name);
+        }
     }
+}
 
-    pub fn get_contrast_percentage(&mut self, index: usize) -> Result<u8, DdcError> {
-        if index >= self.monitors.len() {
+pub const DEFAULT_PROBE_TIMEOUT_MS: u32 = 500;
+
+/// Where discovery looks for display connectors and I2C devices; tests
+/// point these at a fake layout.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SysPaths {
+    /// DRM class directory holding a `cardN-PORT` entry per connector.
+    pub drm: PathBuf,
+    /// Directory holding the `i2c-N` device nodes.
+    pub dev: PathBuf,
+}
+
+impl Default for SysPaths {
+    fn default() -> Self {
+        Self {
+            drm: PathBuf::from("/sys/class/drm"),
+            dev: PathBuf::from("/dev"),
+        }
+    }
+}
+
+/// How `DdcManager` finds and talks to monitors.
+#[derive(Debug, Clone)]
+pub struct DdcOptions {
+    /// Connector (e.g. `"card0-DP-1"`) to I2C device (`"/dev/i2c-5"`) pins
+    /// tried before scanning.
+    pub pinned_devices: HashMap<String, String>,
+    /// How long to wait for the first reply on each I2C device; `None`
+    /// waits as long as the bus takes.
+    pub probe_timeout: Option<Duration>,
+    /// Extra pause after every command once discovery is done, for monitors
+    /// that answer garbage when addressed at the library's default pace.
+    pub extra_delay: Duration,
+    /// Read values back after writing them, to detect monitors that clamp
+    /// or round the request.
+    pub verify_writes: bool,
+    pub brightness_curve: BrightnessCurve,
+    /// File remembering each monitor's feature support and ranges by EDID
+    /// serial, so later launches skip probing them; `None` always probes.
+    pub capability_cache: Option<PathBuf>,
+    /// Probe every monitor again and rewrite its cache entry.
+    pub refresh_capabilities: bool,
+    /// `Monitor::settings_key` to the manufacturer VCP code that inverts its
+    /// image, 0 meaning normal and 1 inverted.
+    pub invert_codes: HashMap<String, u8>,
+    /// How many times a failed DDC command is sent again once discovery is
+    /// done.
+    pub retries: u32,
+    /// Name monitors from their EDID. When off, EDIDs are never read and
+    /// monitors are called "Monitor 1", "Monitor 2" and so on; without a
+    /// serial, duplicate connectors are not merged and capabilities are
+    /// not cached.
+    pub edid_names: bool,
+    pub paths: SysPaths,
+}
+
+impl Default for DdcOptions {
+    fn default() -> Self {
+        Self {
+            pinned_devices: HashMap::new(),
+            probe_timeout: Some(Duration::from_millis(DEFAULT_PROBE_TIMEOUT_MS as u64)),
+            extra_delay: Duration::ZERO,
+            verify_writes: false,
+            brightness_curve: BrightnessCurve::Linear,
+            capability_cache: None,
+            refresh_capabilities: false,
+            invert_codes: HashMap::new(),
+            retries: 0,
+            edid_names: true,
+            paths: SysPaths::default(),
+        }
+    }
+}
+
+impl DdcOptions {
+    /// These options with `BRIGHTLESS_DDC_TIMEOUT_MS` and
+    /// `BRIGHTLESS_DDC_RETRIES` applied, so hardware tests can tune timing
+    /// without touching settings. Values that don't parse are ignored.
+    pub fn with_env_overrides(self) -> Self {
+        self.with_overrides(
+            std::env::var("BRIGHTLESS_DDC_TIMEOUT_MS").ok().as_deref(),
+            std::env::var("BRIGHTLESS_DDC_RETRIES").ok().as_deref(),
+        )
+    }
+
+    fn with_overrides(mut self, timeout_ms: Option<&str>, retries: Option<&str>) -> Self {
+        if let Some(ms) = timeout_ms.and_then(|v| v.trim().parse::<u64>().ok()) {
+            self.probe_timeout = (ms > 0).then(|| Duration::from_millis(ms));
+        }
+        if let Some(retries) = retries.and_then(|v| v.trim().parse().ok()) {
+            self.retries = retries;
+        }
+        self
+    }
+}
+
+/// What discovery learns about a monitor beyond whether it answers at all.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct Capabilities {
+    contrast: (u16, u16),
+    volume: (u16, u16),
+    supports_input_source: bool,
+    supports_power_mode: bool,
+    #[serde(default)]
+    supports_osd_lock: bool,
+    #[serde(default)]
+    osd_lock_writable: bool,
+    max_rgb_bias: [u16; 3],
+    #[serde(default)]
+    max_rgb_gain: [u16; 3],
+    #[serde(default)]
+    color_temperature_presets: Vec<u8>,
+    #[serde(default)]
+    vendor_toggles: Vec<VendorToggle>,
+    #[serde(default)]
+    tech_type: DisplayTechnology,
+}
+
+/// Layout of `Capabilities` in the capability cache. Bump it whenever
+/// probing learns something new, so cached monitors are probed again
+/// instead of loading the new fields as their defaults. 2 added vendor
+/// toggles, 3 the display technology.
+const CAPABILITY_CACHE_SCHEMA: u32 = 3;
+
+/// `Capabilities` by EDID serial, as stored in `DdcOptions::capability_cache`.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct CapabilityCache {
+    /// Version of the crate that wrote the cache; entries from any other
+    /// version are probed again, as detection may have changed.
+    version: String,
+    /// `CAPABILITY_CACHE_SCHEMA` when the cache was written; caches from
+    /// before it existed have 0.
+    #[serde(default)]
+    schema: u32,
+    monitors: HashMap<String, Capabilities>,
+}
+
+impl CapabilityCache {
+    /// The cache discovery starts from: empty when there is none or
+    /// `refresh_capabilities` asks to probe every monitor again.
+    fn for_options(options: &DdcOptions) -> Self {
+        match &options.capability_cache {
+            Some(path) if !options.refresh_capabilities => Self::load(path),
+            _ => Self::default(),
+        }
+    }
+
+    fn load(path: &Path) -> Self {
+        fs::read_to_string(path)
+            .ok()
+            .and_then(|contents| serde_json::from_str::<Self>(&contents).ok())
+            .filter(|cache| {
+                cache.version == env!("CARGO_PKG_VERSION")
+                    && cache.schema == CAPABILITY_CACHE_SCHEMA
+            })
+            .map(|mut cache| {
+                migrate_serial_keys(&mut cache.monitors);
+                cache
+            })
+            .unwrap_or_default()
+    }
+
+    fn save(&mut self, path: &Path) -> std::io::Result<()> {
+        self.version = env!("CARGO_PKG_VERSION").to_string();
+        self.schema = CAPABILITY_CACHE_SCHEMA;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, serde_json::to_string_pretty(self)?)
+    }
+}
+
+/// Result of a successful write, as seen by reading the value back.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WriteOutcome {
+    /// The value was not read back; verification is off or the read failed.
+    Unverified,
+    /// The monitor reports exactly the raw value that was written.
+    Exact,
+    /// The monitor settled on a different value than the one requested.
+    Clamped { requested: u8, achieved: u8 },
+}
+
+/// `key` in its current form if it is an EDID serial written before the
+/// serial text was cleaned, which kept runs of spaces and trailing padding;
+/// `None` for any other key.
+pub fn migrated_serial_key(key: &str) -> Option<String> {
+    let parts: Vec<&str> = key.splitn(4, '-').collect();
+    let [vendor, product, number, text] = parts[..] else {
+        return None;
+    };
+    let hex = |part: &str, len| part.len() == len && part.chars().all(|c| c.is_ascii_hexdigit());
+    if !(hex(vendor, 4) && hex(product, 4) && hex(number, 8)) {
+        return None;
+    }
+    let cleaned = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    (cleaned != text).then(|| format!("{}-{}-{}-{}", vendor, product, number, cleaned))
+}
+
+/// Moves entries of `map` saved under old-format EDID serials to the
+/// serial's current form, unless that already has an entry. Returns
+/// whether any key changed.
+pub fn migrate_serial_keys<V>(map: &mut HashMap<String, V>) -> bool {
+    let old: Vec<(String, String)> = map
+        .keys()
+        .filter_map(|key| Some((key.clone(), migrated_serial_key(key)?)))
+        .collect();
+    for (key, migrated) in &old {
+        if let Some(value) = map.remove(key) {
+            map.entry(migrated.clone()).or_insert(value);
+        }
+    }
+    !old.is_empty()
+}
+
+/// `current` moved by `delta` percent, stopping at 0 and 100.
+fn nudged(current: u8, delta: i8) -> u8 {
+    (current as i16 + delta as i16).clamp(0, 100) as u8
+}
+
+/// Names for monitors without an EDID name, from their `(connector,
+/// serial)`: the serial when there is one, otherwise a number in connector
+/// order, so the same setup gets the same names on every run.
+fn generic_monitor_names(monitors: &[(&str, Option<&str>)]) -> Vec<String> {
+    let mut numbered: Vec<usize> = (0..monitors.len())
+        .filter(|&i| monitors[i].1.is_none())
+        .collect();
+    numbered.sort_by_cached_key(|&i| natural_sort_key(monitors[i].0));
+    monitors
+        .iter()
+        .enumerate()
+        .map(|(i, (_, serial))| match serial {
+            Some(serial) => format!("Monitor ({})", serial),
+            None => {
+                let n = numbered.iter().position(|&j| j == i).unwrap_or(i);
+                format!("Monitor {}", n + 1)
+            }
+        })
+        .collect()
+}
+
+/// `connector` as runs of text each followed by a number, so that sorting
+/// by it puts `DP-2` before `DP-10`.
+fn natural_sort_key(connector: &str) -> Vec<(&str, u64)> {
+    let mut key = Vec::new();
+    let mut rest = connector;
+    while !rest.is_empty() {
+        let digits_at = rest
+            .find(|c: char| c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        let (text, tail) = rest.split_at(digits_at);
+        let text_at = tail
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(tail.len());
+        let (digits, tail) = tail.split_at(text_at);
+        key.push((text, digits.parse().unwrap_or(0)));
+        rest = tail;
+    }
+    key
+}
+
+/// Names for monitors from their `(name, connector)`, with the port added to
+/// names that more than one monitor shares, e.g. "LG HDR 4K (DP-1)" and
+/// "LG HDR 4K (DP-2)". Names only one monitor has are kept as they are.
+fn distinct_monitor_names(monitors: &[(&str, &str)]) -> Vec<String> {
+    monitors
+        .iter()
+        .map(|&(name, connector)| {
+            if monitors.iter().filter(|(other, _)| *other == name).count() > 1 {
+                format!("{} ({})", name, connector_port(connector))
+            } else {
+                name.to_string()
+            }
+        })
+        .collect()
+}
+
+/// Text of an EDID string descriptor up to its newline terminator, with
+/// non-printable bytes treated as spaces, runs of spaces collapsed and the
+/// padding trimmed.
+fn clean_descriptor_text(bytes: &[u8]) -> String {
+    let text: String = bytes
+        .iter()
+        .take_while(|&&c| c != 0x0A)
+        .map(|&c| {
+            if (0x21..0x7F).contains(&c) {
+                c as char
+            } else {
+                ' '
+            }
+        })
+        .collect();
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// A continuous control whose changes are reported to observers. Ordered
+/// the way `DdcManager::flush_pending` writes them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Feature {
+    Brightness,
+    Contrast,
+    Volume,
+}
+
+impl Feature {
+    pub fn name(&self) -> &str {
+        match self {
+            Feature::Brightness => "brightness",
+            Feature::Contrast => "contrast",
+            Feature::Volume => "volume",
+        }
+    }
+}
+
+/// Last known values of one monitor, as persisted between sessions; `None`
+/// leaves that feature alone when applied.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MonitorState {
+    pub brightness: Option<u8>,
+    pub contrast: Option<u8>,
+    pub volume: Option<u8>,
+}
+
+impl MonitorState {
+    fn get(&self, feature: Feature) -> Option<u8> {
+        match feature {
+            Feature::Brightness => self.brightness,
+            Feature::Contrast => self.contrast,
+            Feature::Volume => self.volume,
+        }
+    }
+}
+
+/// A feature `DdcManager::apply_saved_state` changed, with the value it
+/// found and the one it wrote.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StateChange {
+    pub feature: Feature,
+    pub from: u8,
+    pub to: u8,
+}
+
+/// Called with the monitor index, the feature and its new percentage.
+pub type ChangeObserver = Box<dyn Fn(usize, Feature, u8)>;
+
+/// A connected display that could not be brought up during discovery.
+#[derive(Debug)]
+pub struct DiscoveryFailure {
+    pub connector: String,
+    pub error: DdcError,
+}
+
+/// Monitors `DdcManager::rediscover` found or lost, by name.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Rediscovery {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+}
+
+/// Monitors, failures and report of a discovery run, plus the
+/// `(serial, connector)` of every already open monitor it found again.
+type Discovered = (
+    Vec<Monitor>,
+    Vec<DiscoveryFailure>,
+    DiscoveryReport,
+    Vec<(String, String)>,
+);
+
+/// An open monitor with its MCCS version and brightness range, as
+/// `probe_device` found it.
+type Probed = (I2cDdc<I2c<File>>, Option<MccsVersion>, (u16, u16));
+
+/// Moves the per-control entries of `map` to the monitor indices in
+/// `mapping`, dropping those of monitors not in it.
+fn remap_indices<M, V>(map: &mut M, mapping: &HashMap<usize, usize>)
+where
+    M: Default + IntoIterator<Item = ((usize, Feature), V)> + FromIterator<((usize, Feature), V)>,
+{
+    *map = std::mem::take(map)
+        .into_iter()
+        .filter_map(|((index, feature), value)| Some(((*mapping.get(&index)?, feature), value)))
+        .collect();
+}
+
+/// What discovery made of one connected display.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConnectorStatus {
+    /// Driven through this I2C device.
+    Found(String),
+    /// Shows the same panel as this other connector.
+    Merged(String),
+    /// Could not be brought up, after probing `tried` I2C devices.
+    Failed { tried: usize, error: String },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConnectorOutcome {
+    pub connector: String,
+    /// Name from the EDID, or `"Unknown Monitor"`.
+    pub name: String,
+    pub status: ConnectorStatus,
+}
+
+/// Per-connector outcome of discovery, for telling users which monitors
+/// failed and why.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DiscoveryReport {
+    pub connectors: Vec<ConnectorOutcome>,
+    /// Where connectors were looked for.
+    pub drm: PathBuf,
+    /// Why discovery failed outside any connector, e.g. when `/dev` can't
+    /// be listed while probing I2C devices directly.
+    pub error: Option<String>,
+}
+
+impl DiscoveryReport {
+    fn push(&mut self, connector: &str, name: &str, status: ConnectorStatus) {
+        self.connectors.push(ConnectorOutcome {
+            connector: connector.to_string(),
+            name: name.to_string(),
+            status,
+        });
+    }
+
+    pub fn has_failures(&self) -> bool {
+        self.error.is_some()
+            || self
+                .connectors
+                .iter()
+                .any(|c| matches!(c.status, ConnectorStatus::Failed { .. }))
+    }
+}
+
+impl std::fmt::Display for DiscoveryReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.connectors.is_empty() {
+            write!(
+                f,
+                "No connected displays were found in {}",
+                self.drm.display()
+            )?;
+            if let Some(error) = &self.error {
+                write!(f, "; probing I2C devices directly: {}", error)?;
+            }
+            return Ok(());
+        }
+        if let Some(error) = &self.error {
+            writeln!(f, "{}", error)?;
+        }
+        for (i, outcome) in self.connectors.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{} ({}): ", outcome.connector, outcome.name)?;
+            match &outcome.status {
+                ConnectorStatus::Found(device) => write!(f, "working on {}", device)?,
+                ConnectorStatus::Merged(other) => write!(f, "same monitor as {}", other)?,
+                ConnectorStatus::Failed { tried: 0, error } => {
+                    write!(f, "no I2C device to try: {}", error)?
+                }
+                ConnectorStatus::Failed { tried, error } => {
+                    write!(f, "failed on {} I2C device(s): {}", tried, error)?
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Monitors found by `DdcManager::discover`. Unlike the manager it can be
+/// sent to another thread; `DdcManager::from_discovery` finishes the job.
+pub struct Discovery {
+    monitors: Vec<Monitor>,
+    failures: Vec<DiscoveryFailure>,
+    report: DiscoveryReport,
+    options: DdcOptions,
+}
+
+/// A value waiting in `DdcManager::pending` until `flush_pending`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Queued {
+    Percentage(u8),
+    /// Raw brightness, from sliders that move in raw steps.
+    RawBrightness(u16),
+}
+
+pub struct DdcManager {
+    pub monitors: Vec<Monitor>,
+    pub failures: Vec<DiscoveryFailure>,
+    /// Outcome of discovery for every connected display.
+    pub report: DiscoveryReport,
+    /// Where the monitors' DRM connectors are, for `connector_info`.
+    drm: PathBuf,
+    extra_delay: Duration,
+    retries: u32,
+    verify_writes: bool,
+    brightness_curve: BrightnessCurve,
+    /// Highest brightness percentage any write may set, e.g. at night.
+    brightness_ceiling: Option<u8>,
+    /// Brightness last asked for above the ceiling, per monitor, to go
+    /// back to once it lifts.
+    capped: HashMap<(usize, Feature), u8>,
+    observers: Vec<ChangeObserver>,
+    last_known: HashMap<(usize, Feature), u8>,
+    /// Value most recently written per control, which `correct_drift`
+    /// restores.
+    intended: HashMap<(usize, Feature), u8>,
+    /// Latest queued value per control, written by `flush_pending`.
+    pending: BTreeMap<(usize, Feature), Queued>,
+    /// Controls changed by hand, with when scheduled writes may resume.
+    holds: HashMap<(usize, Feature), Instant>,
+}
+
+impl DdcManager {
+    /// Discovers all DDC-capable monitors. Succeeds as long as at least one
+    /// monitor is usable; connectors that failed are kept in `failures`.
+    pub fn new() -> Result<Self, DdcError> {
+        Self::with_options(&DdcOptions::default())
+    }
+
+    /// Like `new`, with pinned devices and probe timeout from `options`.
+    /// `BRIGHTLESS_DDC_TIMEOUT_MS` and `BRIGHTLESS_DDC_RETRIES` take
+    /// precedence over the probe timeout and retries given there.
+    pub fn with_options(options: &DdcOptions) -> Result<Self, DdcError> {
+        Self::discover(options).map(Self::from_discovery)
+    }
+
+    /// Like `new`, but never fails: when no monitor is usable the manager
+    /// has none, and the report says what discovery made of each connector.
+    pub fn new_with_report() -> (Self, DiscoveryReport) {
+        Self::with_options_and_report(&DdcOptions::default())
+    }
+
+    /// Like `with_options`, but never fails; see `new_with_report`.
+    pub fn with_options_and_report(options: &DdcOptions) -> (Self, DiscoveryReport) {
+        let discovery = Self::discover(options).unwrap_or_else(|e| Discovery {
+            monitors: Vec::new(),
+            failures: Vec::new(),
+            report: match e {
+                DdcError::NoneDiscovered(report) => report,
+                e => DiscoveryReport {
+                    error: Some(e.to_string()),
+                    drm: options.paths.drm.clone(),
+                    ..DiscoveryReport::default()
+                },
+            },
+            options: options.clone().with_env_overrides(),
+        });
+        let ddc = Self::from_discovery(discovery);
+        let report = ddc.report.clone();
+        (ddc, report)
+    }
+
+    /// The slow part of `with_options`: finds and probes the monitors
+    /// without building the manager, so it can run on another thread.
+    pub fn discover(options: &DdcOptions) -> Result<Discovery, DdcError> {
+        let options = options.clone().with_env_overrides();
+        let (mut monitors, failures, report, _) =
+            Self::discover_monitors(&options, &HashMap::new())?;
+        Self::detect_invert(&mut monitors, &options.invert_codes);
+        Ok(Discovery {
+            monitors,
+            failures,
+            report,
+            options,
+        })
+    }
+
+    /// Describes what discovery sees, for bug reports: every connector and
+    /// its status, each I2C device tried and how it answered, and for the
+    /// monitors that did, their MCCS version, capabilities string and VCP
+    /// features. Only ever reads from monitors, and works even when
+    /// discovery would find none usable.
+    pub fn diagnose(options: &DdcOptions) -> String {
+        let options = options.clone().with_env_overrides();
+        let paths = &options.paths;
+        let mut out = String::new();
+        let _ = writeln!(out, "Brightless {}", env!("CARGO_PKG_VERSION"));
+
+        let mut connectors = Self::connector_statuses(paths);
+        connectors.sort();
+        if connectors.is_empty() {
+            let _ = writeln!(out, "\nNo display connectors in {}", paths.drm.display());
+        }
+        let mut probed: HashSet<String> = HashSet::new();
+        for (connector, status) in &connectors {
+            let _ = writeln!(out, "\n{}: {}", connector, status);
+            if status != "connected" {
+                continue;
+            }
+            match Self::read_edid(paths, connector) {
+                Some(edid) => {
+                    let _ = writeln!(
+                        out,
+                        "  EDID: {}, serial {}",
+                        Self::parse_edid_name(&edid).as_deref().unwrap_or("no name"),
+                        Self::parse_edid_serial(&edid).as_deref().unwrap_or("none")
+                    );
+                }
+                None => {
+                    let _ = writeln!(out, "  EDID: none in sysfs");
+                }
+            }
+            let mut devices: Vec<String> = options
+                .pinned_devices
+                .get(connector)
+                .cloned()
+                .into_iter()
+                .collect();
+            for device in Self::linked_i2c_devices(paths, connector) {
+                if !devices.contains(&device) {
+                    devices.push(device);
+                }
+            }
+            if devices.is_empty() {
+                let _ = writeln!(out, "  No I2C device linked in sysfs");
+            }
+            for device in devices {
+                Self::diagnose_device(&mut out, &device, options.probe_timeout);
+                probed.insert(device);
+            }
+        }
+
+        match Self::list_i2c_devices(paths) {
+            Ok(devices) => {
+                let others: Vec<String> = devices
+                    .into_iter()
+                    .filter(|device| !probed.contains(device))
+                    .collect();
+                if !others.is_empty() {
+                    let _ = writeln!(out, "\nOther I2C devices:");
+                }
+                for device in others {
+                    Self::diagnose_device(&mut out, &device, options.probe_timeout);
+                }
+            }
+            Err(e) => {
+                let _ = writeln!(
+                    out,
+                    "\nCouldn't list I2C devices in {}: {}",
+                    paths.dev.display(),
+                    e
+                );
+            }
+        }
+        out
+    }
+
+    /// Appends how the monitor on `path` answers to `out`, reading every
+    /// feature in `DIAGNOSTIC_FEATURES`.
+    fn diagnose_device(out: &mut String, path: &str, timeout: Option<Duration>) {
+        let (mut ddc, mccs_version) = match Self::probe_device(path, timeout) {
+            Ok((ddc, mccs_version, _)) => (ddc, mccs_version),
+            Err(e) => {
+                let _ = writeln!(out, "  {}: {}", path, e);
+                return;
+            }
+        };
+        match mccs_version {
+            Some(version) => {
+                let _ = writeln!(out, "  {}: DDC/CI responded, MCCS {}", path, version);
+            }
+            None => {
+                let _ = writeln!(out, "  {}: DDC/CI responded, no MCCS version", path);
+            }
+        }
+        match ddc.capabilities_string() {
+            Ok(caps) => {
+                let _ = writeln!(
+                    out,
+                    "    Capabilities: {}",
+                    String::from_utf8_lossy(&caps).trim()
+                );
+            }
+            Err(e) => {
+                let _ = writeln!(out, "    Capabilities: couldn't read: {}", e);
+            }
+        }
+        for &(code, name) in DIAGNOSTIC_FEATURES {
+            let _ = match ddc.get_vcp_feature(code) {
+                Ok(vcp) => writeln!(
+                    out,
+                    "    0x{:02X} {}: {} (max {})",
+                    code,
+                    name,
+                    vcp.value(),
+                    vcp.maximum()
+                ),
+                Err(e) => writeln!(out, "    0x{:02X} {}: {}", code, name, e),
+            };
+        }
+    }
+
+    pub fn from_discovery(discovery: Discovery) -> Self {
+        let Discovery {
+            monitors,
+            failures,
+            report,
+            options,
+        } = discovery;
+        Self {
+            monitors,
+            failures,
+            report,
+            drm: options.paths.drm.clone(),
+            extra_delay: options.extra_delay,
+            retries: options.retries,
+            verify_writes: options.verify_writes,
+            brightness_curve: options.brightness_curve,
+            brightness_ceiling: None,
+            capped: HashMap::new(),
+            observers: Vec::new(),
+            last_known: HashMap::new(),
+            intended: HashMap::new(),
+            pending: BTreeMap::new(),
+            holds: HashMap::new(),
+        }
+    }
+
+    pub fn brightness_curve(&self) -> BrightnessCurve {
+        self.brightness_curve
+    }
+
+    pub fn set_brightness_curve(&mut self, curve: BrightnessCurve) {
+        self.brightness_curve = curve;
+    }
+
+    pub fn brightness_ceiling(&self) -> Option<u8> {
+        self.brightness_ceiling
+    }
+
+    /// Caps every brightness write from now on at `ceiling` percent;
+    /// `None` lifts the cap. Monitors already brighter are left as they
+    /// are until the next write.
+    pub fn set_brightness_ceiling(&mut self, ceiling: Option<u8>) {
+        self.brightness_ceiling = ceiling;
+    }
+
+    /// Brightness percentage monitor `index` was last asked for while the
+    /// ceiling held it lower, to restore when the ceiling lifts. A write
+    /// the ceiling didn't cut clears it.
+    pub fn capped_brightness(&self, index: usize) -> Option<u8> {
+        self.capped.get(&(index, Feature::Brightness)).copied()
+    }
+
+    fn note_capped(&mut self, index: usize, requested: u8, written: u8) {
+        if requested > written {
+            self.capped.insert((index, Feature::Brightness), requested);
+        } else {
+            self.capped.remove(&(index, Feature::Brightness));
+        }
+    }
+
+    /// Percentage and raw value a brightness write of `percentage` sends
+    /// to monitor `index`, after the ceiling.
+    fn brightness_write(&self, index: usize, percentage: u8) -> (u8, u16) {
+        let monitor = &self.monitors[index];
+        let percentage = self
+            .brightness_ceiling
+            .map_or(percentage, |ceiling| percentage.min(ceiling));
+        let raw = self.brightness_curve.percentage_to_raw(
+            percentage,
+            monitor.min_brightness,
+            monitor.max_brightness,
+        );
+        (percentage, raw)
+    }
+
+    /// Raw value `set_brightness_raw` sends for `value`: within the range
+    /// monitor `index` reports and under the ceiling.
+    fn brightness_raw_write(&self, index: usize, value: u16) -> u16 {
+        let min = self.monitors[index].min_brightness;
+        let max = self.monitors[index].max_brightness;
+        self.cap_brightness_raw(index, value.clamp(min, max.max(min)))
+    }
+
+    /// Raw value `set_raw_vcp` sends for `value` on `code`: the ceiling
+    /// applies to brightness (0x10) only.
+    fn raw_vcp_write(&self, index: usize, code: u8, value: u16) -> u16 {
+        if code == 0x10 {
+            self.cap_brightness_raw(index, value)
+        } else {
+            value
+        }
+    }
+
+    /// The raw value `brightness_ceiling` allows on monitor `index`, or
+    /// `raw` when there's no ceiling.
+    fn cap_brightness_raw(&self, index: usize, raw: u16) -> u16 {
+        let monitor = &self.monitors[index];
+        match self.brightness_ceiling {
+            Some(ceiling) if monitor.max_brightness > monitor.min_brightness => {
+                raw.min(self.brightness_curve.percentage_to_raw(
+                    ceiling,
+                    monitor.min_brightness,
+                    monitor.max_brightness,
+                ))
+            }
+            _ => raw,
+        }
+    }
+
+    /// Registers `observer` to be called after every successful write, and
+    /// after a read that finds a value different from the last one seen
+    /// (e.g. changed on the monitor's OSD). Observers run while the manager
+    /// is borrowed, so they must not call back into it.
+    pub fn connect_changed(&mut self, observer: impl Fn(usize, Feature, u8) + 'static) {
+        self.observers.push(Box::new(observer));
+    }
+
+    fn notify(&self, index: usize, feature: Feature, percentage: u8) {
+        for observer in &self.observers {
+            observer(index, feature, percentage);
+        }
+    }
+
+    fn record_read(&mut self, index: usize, feature: Feature, percentage: u8) {
+        let previous = self.last_known.insert((index, feature), percentage);
+        if previous.is_some_and(|p| p != percentage) {
+            self.notify(index, feature, percentage);
+        }
+    }
+
+    fn record_write(
+        &mut self,
+        index: usize,
+        feature: Feature,
+        outcome: WriteOutcome,
+        requested: u8,
+    ) {
+        let percentage = match outcome {
+            WriteOutcome::Clamped { achieved, .. } => achieved,
+            _ => requested,
+        };
+        self.last_known.insert((index, feature), percentage);
+        self.intended.insert((index, feature), percentage);
+        // A value still queued from a slider is older than this one, e.g.
+        // from a profile or schedule, and would undo it when flushed.
+        self.pending.remove(&(index, feature));
+        self.notify(index, feature, percentage);
+    }
+
+    fn get_connected_connectors(paths: &SysPaths) -> Vec<String> {
+        Self::connector_statuses(paths)
+            .into_iter()
+            .filter(|(_, status)| status == "connected")
+            .map(|(name, _)| name)
+            .collect()
+    }
+
+    /// Every connector in `/sys/class/drm` with its `status`, such as
+    /// `connected` or `disconnected`.
+    fn connector_statuses(paths: &SysPaths) -> Vec<(String, String)> {
+        let mut connectors = Vec::new();
+
+        if let Ok(entries) = fs::read_dir(&paths.drm) {
+            for entry in entries.flatten() {
+                let path = entry.path();
+                if path.is_dir() {
+                    if let Some(name) = path.file_name().and_then(|n| n.to_str()) {
+                        if !name.starts_with("card") || name.contains("-") {
+                            let status_path = path.join("status");
+                            if let Ok(status) = fs::read_to_string(&status_path) {
+                                connectors.push((name.to_string(), status.trim().to_string()));
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        connectors
+    }
+
+    fn list_i2c_devices(paths: &SysPaths) -> std::io::Result<Vec<String>> {
+        let mut names: Vec<String> = fs::read_dir(&paths.dev)?
+            .flatten()
+            .map(|entry| entry.file_name().to_string_lossy().to_string())
+            .filter(|name| name.starts_with("i2c-"))
+            .collect();
+        names.sort_by_key(|name| {
+            name.trim_start_matches("i2c-")
+                .parse::<u32>()
+                .unwrap_or(u32::MAX)
+        });
+        Ok(names
+            .iter()
+            .map(|name| Self::device_path(paths, name))
+            .collect())
+    }
+
+    fn device_path(paths: &SysPaths, name: &str) -> String {
+        paths.dev.join(name).to_string_lossy().to_string()
+    }
+
+    /// I2C buses the kernel links to a connector: its `ddc` symlink and any
+    /// DP AUX `i2c-N` children.
+    fn linked_i2c_devices(paths: &SysPaths, connector: &str) -> Vec<String> {
+        let dir = paths.drm.join(connector);
+        let mut devices = Vec::new();
+
+        if let Ok(target) = fs::read_link(dir.join("ddc")) {
+            if let Some(name) = target.file_name().and_then(|n| n.to_str()) {
+                devices.push(Self::device_path(paths, name));
+            }
+        }
+
+        if let Ok(entries) = fs::read_dir(&dir) {
+            for entry in entries.flatten() {
+                let name = entry.file_name().to_string_lossy().to_string();
+                if name.starts_with("i2c-") {
+                    let device = Self::device_path(paths, &name);
+                    if !devices.contains(&device) {
+                        devices.push(device);
+                    }
+                }
+            }
+        }
+
+        devices
+    }
+
+    fn read_edid(paths: &SysPaths, connector: &str) -> Option<Vec<u8>> {
+        let edid_path = paths.drm.join(connector).join("edid");
+        let path = edid_path.as_path();
+
+        if path.exists() {
+            let mut file = match File::open(path) {
+                Ok(f) => f,
+                Err(_) => return None,
+            };
+
+            let mut data = Vec::new();
+            if file.read_to_end(&mut data).is_ok() && !data.is_empty() && data.len() >= 128 {
+                return Some(data);
+            }
+        }
+        None
+    }
+
+    /// Reads the EDID base block from the monitor itself over an open DDC
+    /// handle, for when sysfs has none to offer.
+    fn read_edid_over_ddc(ddc: &mut I2cDdc<I2c<File>>) -> Option<Vec<u8>> {
+        let mut edid = vec![0u8; 128];
+        match ddc.read_edid(0, &mut edid) {
+            Ok(128) => Some(edid),
+            _ => None,
+        }
+    }
+
+    pub fn parse_edid_name(edid: &[u8]) -> Option<String> {
+        if edid.len() < 128 {
+            return None;
+        }
+
+        if edid[0] != 0x00
+            || edid[1] != 0xFF
+            || edid[2] != 0xFF
+            || edid[3] != 0xFF
+            || edid[4] != 0xFF
+            || edid[5] != 0xFF
+            || edid[6] != 0xFF
+            || edid[7] != 0x00
+        {
+            return None;
+        }
+
+        // PNP ID: three 5-bit letters packed big-endian, 1 = 'A'.
+        let mfg_id = u16::from_be_bytes([edid[8], edid[9]]);
+        let mfg_chars = [
+            ((mfg_id >> 10) & 0x1F) as u8,
+            ((mfg_id >> 5) & 0x1F) as u8,
+            (mfg_id & 0x1F) as u8,
+        ];
+
+        let manufacturer: String = mfg_chars
+            .iter()
+            .filter_map(|&c| {
+                if c == 0 || c > 26 {
+                    None
+                } else {
+                    Some((c + 64) as char)
+                }
+            })
+            .collect();
+
+        let product_code = u16::from_be_bytes([edid[10], edid[11]]);
+
+        let mut name = String::new();
+        for i in 0..14 {
+            let offset = 0x36 + (i * 18);
+            if offset + 18 > edid.len() {
+                break;
+            }
+            if edid[offset] == 0x00 && edid[offset + 1] == 0x00 && edid[offset + 2] == 0x00 {
+                if edid[offset + 3] == 0xFC {
+                    // The descriptor holds at most 13 bytes, which also caps
+                    // the name's length.
+                    name = clean_descriptor_text(&edid[offset + 5..offset + 18]);
+                    break;
+                }
+            }
+        }
+
+        if name.is_empty() {
+            Some(format!("{} {:04x}", manufacturer, product_code))
+        } else {
+            Some(name)
+        }
+    }
+
+    /// Manufacturer, product code and serial number, or `None` when the
+    /// EDID has no serial to tell identical models apart.
+    pub fn parse_edid_serial(edid: &[u8]) -> Option<String> {
+        if edid.len() < 128 || edid[..8] != [0x00, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x00] {
+            return None;
+        }
+
+        let serial_number = u32::from_le_bytes([edid[12], edid[13], edid[14], edid[15]]);
+        let mut serial_string = String::new();
+        for i in 0..4 {
+            let offset = 0x36 + (i * 18);
+            if edid[offset..offset + 4] == [0x00, 0x00, 0x00, 0xFF] {
+                serial_string = clean_descriptor_text(&edid[offset + 5..offset + 18]);
+                break;
+            }
+        }
+
+        if serial_number == 0 && serial_string.is_empty() {
+            return None;
+        }
+        Some(format!(
+            "{:02x}{:02x}-{:02x}{:02x}-{:08x}-{}",
+            edid[8], edid[9], edid[10], edid[11], serial_number, serial_string
+        ))
+    }
+
+    // The VCP reply only carries a maximum; a nonzero floor has to come from
+    // the per-monitor setting applied by the caller.
+    fn get_brightness_range(ddc: &mut I2cDdc<I2c<File>>) -> Option<(u16, u16)> {
+        match ddc.get_vcp_feature(0x10) {
+            Ok(vcp) => Some((0, vcp.maximum())),
+            Err(_) => None,
+        }
+    }
+
+    fn get_contrast_range(ddc: &mut I2cDdc<I2c<File>>) -> Option<(u16, u16)> {
+        match ddc.get_vcp_feature(0x12) {
+            Ok(vcp) => Some((0, vcp.maximum())),
+            Err(_) => None,
+        }
+    }
+
+    fn get_volume_range(ddc: &mut I2cDdc<I2c<File>>) -> Option<(u16, u16)> {
+        match ddc.get_vcp_feature(0x62) {
+            Ok(vcp) => Some((0, vcp.maximum())),
+            Err(_) => None,
+        }
+    }
+
+    fn get_rgb_gain_range(ddc: &mut I2cDdc<I2c<File>>) -> [u16; 3] {
+        ColorChannel::ALL.map(|channel| {
+            ddc.get_vcp_feature(channel.gain_code())
+                .map(|vcp| vcp.maximum())
+                .unwrap_or(0)
+        })
+    }
+
+    /// Temperature presets of VCP 0x14 listed in the capabilities string.
+    fn get_color_temperature_presets(caps: Option<&str>) -> Vec<u8> {
+        caps.and_then(|caps| capabilities_vcp_values(caps, 0x14))
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|value| COLOR_TEMPERATURE_PRESETS.iter().any(|(p, _)| p == value))
+            .collect()
+    }
+
+    fn get_display_technology(ddc: &mut I2cDdc<I2c<File>>) -> DisplayTechnology {
+        ddc.get_vcp_feature(0xb6)
+            .map(|vcp| DisplayTechnology::from_vcp_value(vcp.value()))
+            .unwrap_or_default()
+    }
+
+    fn get_vendor_toggles(caps: Option<&str>) -> Vec<VendorToggle> {
+        caps.map(capabilities_vendor_toggles).unwrap_or_default()
+    }
+
+    fn get_rgb_bias_range(ddc: &mut I2cDdc<I2c<File>>) -> [u16; 3] {
+        ColorChannel::ALL.map(|channel| {
+            ddc.get_vcp_feature(channel.bias_code())
+                .map(|vcp| vcp.maximum())
+                .unwrap_or(0)
+        })
+    }
+
+    fn check_input_source_support(ddc: &mut I2cDdc<I2c<File>>, caps: Option<&str>) -> bool {
+        // Any successful read counts: plenty of monitors report vendor codes
+        // outside the MCCS table and still switch inputs fine.
+        if ddc.get_vcp_feature(0x60).is_ok() {
+            return true;
+        }
+        caps.is_some_and(|caps| capabilities_list_vcp(caps, 0x60))
+    }
+
+    fn check_power_mode_support(ddc: &mut I2cDdc<I2c<File>>) -> bool {
+        match ddc.get_vcp_feature(0xd6) {
+            Ok(vcp) => !matches!(
+                PowerMode::from_vcp_value(vcp.value()),
+                PowerMode::Unknown(_)
+            ),
+            Err(_) => false,
+        }
+    }
+
+    /// Writes the current brightness back unchanged to find out whether
+    /// writes get through. A monitor whose brightness can't be read is
+    /// assumed writable.
+    fn check_writable(ddc: &mut I2cDdc<I2c<File>>) -> bool {
+        match ddc.get_vcp_feature(0x10) {
+            Ok(vcp) => ddc.set_vcp_feature(0x10, vcp.value()).is_ok(),
+            Err(_) => true,
+        }
+    }
+
+    /// Marks monitors with an invert code in `codes` as supporting it if
+    /// that code reads as 0 or 1. Codes are looked up by settings key, then
+    /// by EDID name for codes not yet moved to the key.
+    fn detect_invert(monitors: &mut [Monitor], codes: &HashMap<String, u8>) {
+        for monitor in monitors {
+            let code = codes
+                .get(monitor.settings_key())
+                .or_else(|| codes.get(&monitor.edid_name));
+            let Some(&code) = code else {
+                continue;
+            };
+            monitor.invert_code = code;
+            monitor.supports_invert = monitor
+                .handle
+                .get_vcp_feature(code)
+                .is_ok_and(|vcp| vcp.value() <= 1);
+        }
+    }
+
+    /// Returns whether OSD enable (0xCA) reads as disabled or enabled, and
+    /// whether writing the current value back is accepted.
+    fn check_osd_lock_support(ddc: &mut I2cDdc<I2c<File>>) -> (bool, bool) {
+        match ddc.get_vcp_feature(0xca) {
+            Ok(vcp) if vcp.sl == 1 || vcp.sl == 2 => {
+                (true, ddc.set_vcp_feature(0xca, vcp.value()).is_ok())
+            }
+            _ => (false, false),
+        }
+    }
+
+    /// Monitors that just powered on or resumed often drop the first request,
+    /// so read the harmless VCP version (0xDF) and give them a moment before
+    /// the brightness read that decides whether the device is kept. Returns
+    /// the MCCS version if the monitor answered.
+    fn warm_up(ddc: &mut I2cDdc<I2c<File>>) -> Option<MccsVersion> {
+        let version = ddc
+            .get_vcp_feature(0xdf)
+            .ok()
+            .map(|vcp| MccsVersion {
+                major: vcp.sh,
+                minor: vcp.sl,
+            })
+            .filter(|v| v.major > 0);
+        thread::sleep(Duration::from_millis(WARM_UP_DELAY_MS));
+        version
+    }
+
+    /// Cheap check for a device at the DDC/CI address, a single-byte read
+    /// (or a quick write where the adapter can't read bytes) instead of a
+    /// full VCP exchange. Returns `true` when the bus can't be checked this
+    /// way, leaving the decision to the full probe, and `false` when it
+    /// gives no answer within `timeout`.
+    fn probe_ddc_present(path: &str, timeout: Option<Duration>) -> bool {
+        // Like the full probe, an empty bus can block, so the check runs on
+        // a thread we can stop waiting for.
+        let (sender, receiver) = mpsc::channel();
+        let path = path.to_string();
+        thread::spawn(move || {
+            let _ = sender.send(Self::ddc_address_answers(&path));
+        });
+        match timeout {
+            Some(timeout) => receiver.recv_timeout(timeout).unwrap_or(false),
+            None => receiver.recv().unwrap_or(true),
+        }
+    }
+
+    fn ddc_address_answers(path: &str) -> bool {
+        let Ok(mut i2c) = I2c::from_path(path) else {
+            return true;
+        };
+        let Ok(functionality) = i2c.i2c_functionality() else {
+            return true;
+        };
+        if i2c.smbus_set_slave_address(DDC_CI_ADDRESS, false).is_err() {
+            return true;
+        }
+        if functionality.contains(Functionality::SMBUS_READ_BYTE) {
+            i2c.smbus_read_byte().is_ok()
+        } else if functionality.contains(Functionality::SMBUS_QUICK) {
+            i2c.smbus_write_quick(ReadWrite::Write).is_ok()
+        } else {
+            true
+        }
+    }
+
+    /// Opens `path` and waits up to `timeout` for a brightness reply. Buses
+    /// that can't do plain I2C transfers, like SMBus-only sensor
+    /// controllers, are rejected without sending anything.
+    fn probe_device(path: &str, timeout: Option<Duration>) -> Result<Probed, DdcError> {
+        let i2c = I2c::from_path(path).map_err(|e| {
+            if e.kind() == std::io::ErrorKind::PermissionDenied {
+                DdcError::PermissionDenied(path.to_string())
+            } else {
+                DdcError::OpenError(format!("{}: {}", path, e))
+            }
+        })?;
+
+        if let Ok(functionality) = i2c.i2c_functionality() {
+            if !functionality.contains(Functionality::I2C) {
+                return Err(DdcError::CommError(format!(
+                    "{}: adapter does not support I2C transfers",
+                    path
+                )));
+            }
+        }
+
+        // A bus without a monitor can block until the adapter's own timeout
+        // expires, so the first reads run on a thread we can stop waiting for.
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            let mut ddc = I2cDdc::new(i2c);
+            let mccs_version = Self::warm_up(&mut ddc);
+            let range = Self::get_brightness_range(&mut ddc);
+            let _ = sender.send((ddc, mccs_version, range));
+        });
+        let reply = match timeout {
+            Some(timeout) => receiver.recv_timeout(timeout).map_err(|_| {
+                DdcError::Timeout(format!(
+                    "{}: no reply within {} ms",
+                    path,
+                    timeout.as_millis()
+                ))
+            })?,
+            None => receiver
+                .recv()
+                .map_err(|e| DdcError::CommError(format!("{}: {}", path, e)))?,
+        };
+
+        match reply {
+            (ddc, mccs_version, Some(range)) => Ok((ddc, mccs_version, range)),
+            (_, _, None) => Err(DdcError::CommError(format!("{}: no DDC/CI response", path))),
+        }
+    }
+
+    fn probe_capabilities(ddc: &mut I2cDdc<I2c<File>>) -> Capabilities {
+        let (supports_osd_lock, osd_lock_writable) = Self::check_osd_lock_support(ddc);
+        let max_rgb_gain = Self::get_rgb_gain_range(ddc);
+        // Reading the capabilities string is slow, so it is read once here
+        // for everything that looks at it.
+        let caps = ddc
+            .capabilities_string()
+            .ok()
+            .map(|caps| String::from_utf8_lossy(&caps).into_owned());
+        let caps = caps.as_deref();
+        // Presets are only offered when the gains can't set the temperature.
+        let color_temperature_presets = if max_rgb_gain.contains(&0) {
+            Self::get_color_temperature_presets(caps)
+        } else {
+            Vec::new()
+        };
+        Capabilities {
+            contrast: Self::get_contrast_range(ddc).unwrap_or((0, 0)),
+            volume: Self::get_volume_range(ddc).unwrap_or((0, 0)),
+            supports_input_source: Self::check_input_source_support(ddc, caps),
+            supports_power_mode: Self::check_power_mode_support(ddc),
+            supports_osd_lock,
+            osd_lock_writable,
+            max_rgb_bias: Self::get_rgb_bias_range(ddc),
+            max_rgb_gain,
+            color_temperature_presets,
+            vendor_toggles: Self::get_vendor_toggles(caps),
+            tech_type: Self::get_display_technology(ddc),
+        }
+    }
+
+    /// Opens `path` and builds a monitor from it, probing its features
+    /// unless `cached` already has them.
+    fn test_ddc_connection(
+        path: &str,
+        name: &str,
+        connector: &str,
+        timeout: Option<Duration>,
+        cached: Option<Capabilities>,
+    ) -> Result<Monitor, DdcError> {
+        let (mut ddc, mccs_version, (min_brightness, max_brightness)) =
+            Self::probe_device(path, timeout)?;
+        let writable = Self::check_writable(&mut ddc);
+        let Capabilities {
+            contrast: (min_contrast, max_contrast),
+            volume: (min_volume, max_volume),
+            supports_input_source,
+            supports_power_mode,
+            supports_osd_lock,
+            osd_lock_writable,
+            max_rgb_bias,
+            max_rgb_gain,
+            color_temperature_presets,
+            vendor_toggles,
+            tech_type,
+        } = cached.unwrap_or_else(|| Self::probe_capabilities(&mut ddc));
+        Ok(Monitor {
+            handle: ddc,
+            name: name.to_string(),
+            edid_name: name.to_string(),
+            connector: connector.to_string(),
+            min_brightness,
+            max_brightness,
+            min_contrast,
+            max_contrast,
+            min_volume,
+            max_volume,
+            supports_input_source,
+            supports_power_mode,
+            writable,
+            supports_osd_lock,
+            osd_lock_writable,
+            supports_invert: false,
+            invert_code: 0,
+            max_rgb_bias,
+            max_rgb_gain,
+            color_temperature_presets,
+            vendor_toggles,
+            tech_type,
+            mccs_version,
+            edid_serial: None,
+            i2c_path: path.to_string(),
+            i2c_candidates: Vec::new(),
+        })
+    }
+
+    /// Probes every connected display. Those whose EDID serial is in
+    /// `known`, mapped to the I2C device already driving it, are reported
+    /// but not probed again.
+    fn discover_monitors(
+        options: &DdcOptions,
+        known: &HashMap<String, String>,
+    ) -> Result<Discovered, DdcError> {
+        let connectors = Self::get_connected_connectors(&options.paths);
+
+        if connectors.is_empty() {
+            return Self::discover_without_connectors(options).map(|monitors| {
+                let report = DiscoveryReport {
+                    drm: options.paths.drm.clone(),
+                    ..DiscoveryReport::default()
+                };
+                (monitors, Vec::new(), report, Vec::new())
+            });
+        }
+
+        let mut monitors: Vec<Monitor> = Vec::new();
+        let mut failures: Vec<DiscoveryFailure> = Vec::new();
+        let mut report = DiscoveryReport {
+            drm: options.paths.drm.clone(),
+            ..DiscoveryReport::default()
+        };
+        let edids: Vec<Option<Vec<u8>>> = connectors
+            .iter()
+            .map(|connector| {
+                options
+                    .edid_names
+                    .then(|| Self::read_edid(&options.paths, connector))
+                    .flatten()
+            })
+            .collect();
+        // Devices of known monitors that are still connected stay in use;
+        // those of monitors that went away are free for new ones.
+        let present: HashSet<String> = edids
+            .iter()
+            .flatten()
+            .filter_map(|edid| Self::parse_edid_serial(edid))
+            .collect();
+        let mut used_i2c: HashMap<String, bool> = known
+            .iter()
+            .filter(|(serial, _)| present.contains(*serial))
+            .map(|(_, path)| (path.clone(), true))
+            .collect();
+        let mut kept: Vec<(String, String)> = Vec::new();
+        // Devices that timed out or can't carry DDC/CI; not worth probing
+        // again for the next connector.
+        let mut rejected_i2c: HashSet<String> = HashSet::new();
+        let mut timed_out = 0;
+        // (connector, serial) of connectors that failed, so a later connector
+        // showing the same panel can replace their failure entry.
+        let mut failed_serials: Vec<(String, String)> = Vec::new();
+        let all_devices = Self::list_i2c_devices(&options.paths);
+        let mut cache = CapabilityCache::for_options(options);
+        let mut cache_changed = false;
+
+        for (connector, edid) in connectors.iter().zip(edids) {
+            let name = edid
+                .as_ref()
+                .and_then(|e| Self::parse_edid_name(e))
+                .unwrap_or_else(|| "Unknown Monitor".to_string());
+            let serial = edid.as_ref().and_then(|e| Self::parse_edid_serial(e));
+
+            // Docks can expose one panel on two connectors; both would
+            // otherwise end up driving the same bus.
+            if let Some(existing) = monitors
+                .iter()
+                .find(|m| serial.is_some() && m.edid_serial == serial)
+            {
+                eprintln!(
+                    "Merging {} into {}: same monitor ({})",
+                    connector, existing.connector, name
+                );
+                report.push(
+                    connector,
+                    &name,
+                    ConnectorStatus::Merged(existing.connector.clone()),
+                );
+                continue;
+            }
+
+            if let Some((serial, path)) = serial.as_ref().and_then(|s| known.get_key_value(s)) {
+                match kept.iter().find(|(s, _)| s == serial) {
+                    Some((_, first)) => {
+                        report.push(connector, &name, ConnectorStatus::Merged(first.clone()))
+                    }
+                    None => {
+                        report.push(connector, &name, ConnectorStatus::Found(path.clone()));
+                        kept.push((serial.clone(), connector.clone()));
+                    }
+                }
+                continue;
+            }
+
+            let all_devices = match &all_devices {
+                Ok(devices) => devices,
+                Err(e) => {
+                    report.push(
+                        connector,
+                        &name,
+                        ConnectorStatus::Failed {
+                            tried: 0,
+                            error: e.to_string(),
+                        },
+                    );
+                    failures.push(DiscoveryFailure {
+                        connector: connector.clone(),
+                        error: DdcError::OpenError(e.to_string()),
+                    });
+                    continue;
+                }
+            };
+
+            let mut candidates = Self::linked_i2c_devices(&options.paths, connector);
+            for device in all_devices {
+                if !candidates.contains(device) {
+                    candidates.push(device.clone());
+                }
+            }
+
+            let pinned_device = options.pinned_devices.get(connector);
+            let mut probe_order: Vec<String> = pinned_device.into_iter().cloned().collect();
+            probe_order.extend(
+                candidates
+                    .iter()
+                    .filter(|device| Some(*device) != pinned_device)
+                    .cloned(),
+            );
+
+            let mut found = false;
+            let mut last_error: Option<DdcError> = None;
+            let mut tried = 0;
+
+            for path_str in probe_order {
+                if used_i2c.contains_key(&path_str) || rejected_i2c.contains(&path_str) {
+                    continue;
+                }
+                tried += 1;
+
+                if !Self::probe_ddc_present(&path_str, options.probe_timeout) {
+                    if last_error.is_none() {
+                        last_error = Some(DdcError::CommError(format!(
+                            "{}: nothing answers at the DDC/CI address",
+                            path_str
+                        )));
+                    }
+                    rejected_i2c.insert(path_str);
+                    continue;
+                }
+
+                let cached = serial.as_ref().and_then(|s| cache.monitors.get(s)).cloned();
+                let was_cached = cached.is_some();
+                match Self::test_ddc_connection(
+                    &path_str,
+                    &name,
+                    connector,
+                    options.probe_timeout,
+                    cached,
+                ) {
+                    Ok(mut monitor) => {
+                        if let (Some(serial), false) = (&serial, was_cached) {
+                            cache
+                                .monitors
+                                .insert(serial.clone(), monitor.capabilities());
+                            cache_changed = true;
+                        }
+                        report.push(connector, &name, ConnectorStatus::Found(path_str.clone()));
+                        used_i2c.insert(path_str, true);
+                        monitor.i2c_candidates = candidates.clone();
+                        monitor.edid_serial = serial.clone();
+                        if edid.is_none() && options.edid_names {
+                            Self::apply_edid_over_ddc(&mut monitor);
+                        }
+                        monitors.push(monitor);
+                        found = true;
+                        break;
+                    }
+                    Err(e) => {
+                        if matches!(e, DdcError::Timeout(_)) {
+                            timed_out += 1;
+                        }
+                        if matches!(e, DdcError::CommError(_) | DdcError::Timeout(_)) {
+                            rejected_i2c.insert(path_str);
+                        }
+                        // Keep a permission problem over later generic failures, it is
+                        // the most actionable thing to tell the user.
+                        if !matches!(last_error, Some(DdcError::PermissionDenied(_))) {
+                            last_error = Some(e);
+                        }
+                    }
+                }
+            }
+
+            if found {
+                if let Some(serial) = &serial {
+                    failures.retain(|f| {
+                        let phantom =
+                            failed_serials.contains(&(f.connector.clone(), serial.clone()));
+                        if phantom {
+                            eprintln!(
+                                "Merging {} into {}: same monitor ({})",
+                                f.connector, connector, name
+                            );
+                            for outcome in &mut report.connectors {
+                                if outcome.connector == f.connector {
+                                    outcome.status = ConnectorStatus::Merged(connector.clone());
+                                }
+                            }
+                        }
+                        !phantom
+                    });
+                }
+            }
+
+            if !found {
+                if let Some(serial) = &serial {
+                    failed_serials.push((connector.clone(), serial.clone()));
+                }
+                let error = last_error.unwrap_or(DdcError::NoMonitors);
+                report.push(
+                    connector,
+                    &name,
+                    ConnectorStatus::Failed {
+                        tried,
+                        error: error.to_string(),
+                    },
+                );
+                failures.push(DiscoveryFailure {
+                    connector: connector.clone(),
+                    error,
+                });
+            }
+        }
+
+        if timed_out > 0 {
+            eprintln!(
+                "{} I2C device(s) did not reply within the probe timeout and were skipped",
+                timed_out
+            );
+        }
+
+        if let (Some(path), true) = (&options.capability_cache, cache_changed) {
+            if let Err(e) = cache.save(path) {
+                eprintln!("Failed to save capability cache: {}", e);
+            }
+        }
+
+        if monitors.is_empty() && kept.is_empty() {
+            return Err(DdcError::NoneDiscovered(report));
+        }
+
+        Self::name_monitors(&mut monitors);
+        Ok((monitors, failures, report, kept))
+    }
+
+    /// Fallback for setups where `/sys/class/drm` lists no connected
+    /// connector (some VMs and headless machines): probes every I2C device
+    /// directly and names monitors from the EDID read over the bus.
+    fn discover_without_connectors(options: &DdcOptions) -> Result<Vec<Monitor>, DdcError> {
+        eprintln!("No connected display connectors in sysfs, probing I2C devices directly");
+        let devices = Self::list_i2c_devices(&options.paths)
+            .map_err(|e| DdcError::OpenError(e.to_string()))?;
+
+        let mut monitors: Vec<Monitor> = Vec::new();
+        for path in devices {
+            if !Self::probe_ddc_present(&path, options.probe_timeout) {
+                continue;
+            }
+            let connector = Path::new(&path)
+                .file_name()
+                .map_or_else(|| path.clone(), |n| n.to_string_lossy().to_string());
+            if let Ok(mut monitor) = Self::test_ddc_connection(
+                &path,
+                "Unknown Monitor",
+                &connector,
+                options.probe_timeout,
+                None,
+            ) {
+                if options.edid_names {
+                    Self::apply_edid_over_ddc(&mut monitor);
+                }
+                monitor.i2c_candidates = vec![path];
+                monitors.push(monitor);
+            }
+        }
+
+        if monitors.is_empty() {
+            return Err(DdcError::NoMonitors);
+        }
+
+        Self::name_monitors(&mut monitors);
+        Ok(monitors)
+    }
+
+    /// Names `monitor` and fills in its serial from the EDID read over its
+    /// own DDC handle; leaves it untouched if that read fails too.
+    fn apply_edid_over_ddc(monitor: &mut Monitor) {
+        let Some(edid) = Self::read_edid_over_ddc(&mut monitor.handle) else {
+            return;
+        };
+        if let Some(name) = Self::parse_edid_name(&edid) {
+            monitor.edid_name = name.clone();
+            monitor.name = name;
+        }
+        monitor.edid_serial = Self::parse_edid_serial(&edid);
+    }
+
+    /// Names every monitor from its EDID name, numbering unknown ones and
+    /// adding the port to names several monitors share. Run over all of a
+    /// manager's monitors at once, as both depend on the others.
+    fn name_monitors(monitors: &mut [Monitor]) {
+        for monitor in monitors.iter_mut() {
+            monitor.name = monitor.edid_name.clone();
+        }
+        Self::number_unknown_monitors(monitors);
+        Self::distinguish_duplicate_names(monitors);
+    }
+
+    fn number_unknown_monitors(monitors: &mut [Monitor]) {
+        let unknown: Vec<(&str, Option<&str>)> = monitors
+            .iter()
+            .filter(|m| m.name == "Unknown Monitor")
+            .map(|m| (m.connector.as_str(), m.edid_serial.as_deref()))
+            .collect();
+        let names = generic_monitor_names(&unknown);
+        for (monitor, name) in monitors
+            .iter_mut()
+            .filter(|m| m.name == "Unknown Monitor")
+            .zip(names)
+        {
+            monitor.name = name;
+        }
+    }
+
+    fn distinguish_duplicate_names(monitors: &mut [Monitor]) {
+        let names = distinct_monitor_names(
+            &monitors
+                .iter()
+                .map(|m| (m.name.as_str(), m.connector.as_str()))
+                .collect::<Vec<_>>(),
+        );
+        for (monitor, name) in monitors.iter_mut().zip(names) {
+            monitor.name = name;
+        }
+    }
+
+    /// Runs one DDC command on monitor `index`, followed by the configured
+    /// extra delay, sending it again up to `retries` times while it fails.
+    fn command<T, E>(
+        &mut self,
+        index: usize,
+        mut f: impl FnMut(&mut I2cDdc<I2c<File>>) -> Result<T, E>,
+    ) -> Result<T, E> {
+        let mut attempt = 0;
+        loop {
+            let result = f(&mut self.monitors[index].handle);
+            if !self.extra_delay.is_zero() {
+                thread::sleep(self.extra_delay);
+            }
+            if result.is_ok() || attempt >= self.retries {
+                return result;
+            }
+            attempt += 1;
+            thread::sleep(Duration::from_millis(RETRY_DELAY_MS));
+        }
+    }
+
+    /// Reads `code` back after writing `raw` to it, if verification is on.
+    /// `to_percentage` converts the value the monitor reports.
+    fn verify_write(
+        &mut self,
+        index: usize,
+        code: u8,
+        raw: u16,
+        requested: u8,
+        to_percentage: impl Fn(u16) -> u8,
+    ) -> WriteOutcome {
+        if !self.verify_writes {
+            return WriteOutcome::Unverified;
+        }
+        match self.command(index, |ddc| ddc.get_vcp_feature(code)) {
+            Ok(vcp) if vcp.value() == raw => WriteOutcome::Exact,
+            Ok(vcp) => WriteOutcome::Clamped {
+                requested,
+                achieved: to_percentage(vcp.value()),
+            },
+            Err(_) => WriteOutcome::Unverified,
+        }
+    }
+
+    pub fn get_brightness_percentage(&mut self, index: usize) -> Result<u8, DdcError> {
+        if index >= self.monitors.len() {
+            return Err(DdcError::NoMonitors);
+        }
+
+        let vcp = self
+            .command(index, |ddc| ddc.get_vcp_feature(0x10))
+            .map_err(|e| DdcError::CommError(format!("Failed to get brightness: {}", e)))?;
+
+        let current = vcp.value();
+        let min = self.monitors[index].min_brightness;
+        let max = self.monitors[index].max_brightness;
+
+        if max <= min {
+            return Ok(0);
+        }
+
+        let percentage = self.brightness_curve.raw_to_percentage(current, min, max);
+        self.record_read(index, Feature::Brightness, percentage);
+        Ok(percentage)
+    }
+
+    pub fn set_brightness_percentage(
+        &mut self,
+        index: usize,
+        percentage: u8,
+    ) -> Result<WriteOutcome, DdcError> {
+        if index >= self.monitors.len() {
+            return Err(DdcError::NoMonitors);
+        }
+
+        let min = self.monitors[index].min_brightness;
+        let max = self.monitors[index].max_brightness;
+
+        if max <= min {
+            return Ok(WriteOutcome::Unverified);
+        }
+
+        let requested = percentage;
+        let (percentage, raw) = self.brightness_write(index, requested);
+        let curve = self.brightness_curve;
+
+        self.command(index, |ddc| ddc.set_vcp_feature(0x10, raw))
+            .map_err(|e| DdcError::CommError(format!("Failed to set brightness: {}", e)))?;
+        self.note_capped(index, requested, percentage);
+
+        let outcome = match self.verify_write(index, 0x10, raw, percentage, |v| {
+            curve.raw_to_percentage(v, min, max)
+        }) {
+            WriteOutcome::Clamped { achieved, .. } => WriteOutcome::Clamped {
+                requested,
+                achieved,
+            },
+            _ if percentage != requested => WriteOutcome::Clamped {
+                requested,
+                achieved: percentage,
+            },
+            outcome => outcome,
+        };
+        self.record_write(index, Feature::Brightness, outcome, percentage);
+        Ok(outcome)
+    }
+
+    /// Writes raw brightness `value`, clamped to the range monitor `index`
+    /// reports and to the brightness ceiling, without rounding through a
+    /// percentage. Returns the raw value written, or the one read back when
+    /// writes are verified.
+    pub fn set_brightness_raw(&mut self, index: usize, value: u16) -> Result<u16, DdcError> {
+        self.set_brightness_raw_checked(index, value)
+            .map(|(raw, _)| raw)
+    }
+
+    /// `set_brightness_raw`, also returning the outcome in percentages:
+    /// `Clamped` when the range, the ceiling or the monitor cut `value`.
+    fn set_brightness_raw_checked(
+        &mut self,
+        index: usize,
+        value: u16,
+    ) -> Result<(u16, WriteOutcome), DdcError> {
+        if index >= self.monitors.len() {
+            return Err(DdcError::NoMonitors);
+        }
+
+        let min = self.monitors[index].min_brightness;
+        let max = self.monitors[index].max_brightness;
+        let raw = self.brightness_raw_write(index, value);
+
+        self.command(index, |ddc| ddc.set_vcp_feature(0x10, raw))
+            .map_err(|e| DdcError::CommError(format!("Failed to set brightness: {}", e)))?;
+        let read_back = if self.verify_writes {
+            self.command(index, |ddc| ddc.get_vcp_feature(0x10))
+                .ok()
+                .map(|vcp| vcp.value())
+        } else {
+            None
+        };
+        let written = read_back.unwrap_or(raw);
+        if max <= min {
+            return Ok((written, WriteOutcome::Unverified));
+        }
+
+        let curve = self.brightness_curve;
+        let requested = curve.raw_to_percentage(value.clamp(min, max), min, max);
+        let percentage = curve.raw_to_percentage(raw, min, max);
+        self.note_capped(index, requested, percentage);
+        let outcome = match read_back {
+            Some(read) if read == value => WriteOutcome::Exact,
+            None if raw == value => WriteOutcome::Unverified,
+            _ => WriteOutcome::Clamped {
+                requested,
+                achieved: curve.raw_to_percentage(written, min, max),
+            },
+        };
+        self.record_write(index, Feature::Brightness, outcome, percentage);
+        Ok((written, outcome))
+    }
+
+    /// Like `set_brightness_raw`, reporting in percentages for sliders:
+    /// `Clamped` when the range or the ceiling cut `value`. Writes nothing
+    /// to a monitor without a usable brightness range.
+    pub fn write_brightness_raw(
+        &mut self,
+        index: usize,
+        value: u16,
+    ) -> Result<WriteOutcome, DdcError> {
+        let monitor = self.monitors.get(index).ok_or(DdcError::NoMonitors)?;
+        let (min, max) = (monitor.min_brightness, monitor.max_brightness);
+        if max <= min {
+            return Ok(WriteOutcome::Unverified);
+        }
+        self.set_brightness_raw_checked(index, value)
+            .map(|(_, outcome)| outcome)
+    }
+
+    /// Moves the brightness of monitor `index` by `delta` percent, stopping
+    /// at 0 and 100, and returns the resulting percentage. Nothing is
+    /// written when brightness is already at the end it moves towards.
+    pub fn adjust_brightness_percentage(
+        &mut self,
+        index: usize,
+        delta: i8,
+    ) -> Result<u8, DdcError> {
+        let current = self.get_brightness_percentage(index)?;
+        let target = nudged(current, delta);
+        if target == current {
+            return Ok(current);
+        }
+        match self.set_brightness_percentage(index, target)? {
+            WriteOutcome::Clamped { achieved, .. } => Ok(achieved),
+            _ => Ok(target),
+        }
+    }
+
+    pub fn get_percentage(&mut self, index: usize, feature: Feature) -> Result<u8, DdcError> {
+        match feature {
+            Feature::Brightness => self.get_brightness_percentage(index),
+            Feature::Contrast => self.get_contrast_percentage(index),
+            Feature::Volume => self.get_volume_percentage(index),
+        }
+    }
+
+    pub fn set_percentage(
+        &mut self,
+        index: usize,
+        feature: Feature,
+        percentage: u8,
+    ) -> Result<WriteOutcome, DdcError> {
+        match feature {
+            Feature::Brightness => self.set_brightness_percentage(index, percentage),
+            Feature::Contrast => self.set_contrast_percentage(index, percentage),
+            Feature::Volume => self.set_volume_percentage(index, percentage),
+        }
+    }
+
+    /// Holds `percentage` for `feature` on monitor `index` until
+    /// `flush_pending`, replacing whatever was queued for it before.
+    pub fn queue_percentage(&mut self, index: usize, feature: Feature, percentage: u8) {
+        self.pending
+            .insert((index, feature), Queued::Percentage(percentage));
+    }
+
+    /// Like `queue_percentage`, for raw brightness `value` as
+    /// `write_brightness_raw` writes it.
+    pub fn queue_brightness_raw(&mut self, index: usize, value: u16) {
+        self.pending
+            .insert((index, Feature::Brightness), Queued::RawBrightness(value));
+    }
+
+    /// Writes every queued value, monitor by monitor and brightness before
+    /// contrast before volume.
+    pub fn flush_pending(&mut self) -> Vec<(usize, Feature, Result<WriteOutcome, DdcError>)> {
+        std::mem::take(&mut self.pending)
+            .into_iter()
+            .map(|((index, feature), value)| {
+                let outcome = match value {
+                    Queued::Percentage(percentage) => {
+                        self.set_percentage(index, feature, percentage)
+                    }
+                    Queued::RawBrightness(value) => self.write_brightness_raw(index, value),
+                };
+                (index, feature, outcome)
+            })
+            .collect()
+    }
+
+    /// Re-applies `state` to monitor `index` on demand, e.g. after a KVM
+    /// switch handed the monitor back. Fails without writing anything if the
+    /// monitor does not answer; otherwise only writes features whose current
+    /// value differs and reports those.
+    pub fn apply_saved_state(
+        &mut self,
+        index: usize,
+        state: &MonitorState,
+    ) -> Result<Vec<StateChange>, DdcError> {
+        let mut current = MonitorState {
+            brightness: Some(self.get_brightness_percentage(index)?),
+            ..MonitorState::default()
+        };
+        if self.supports_contrast(index) {
+            current.contrast = Some(self.get_contrast_percentage(index)?);
+        }
+        if self.supports_volume(index) {
+            current.volume = Some(self.get_volume_percentage(index)?);
+        }
+
+        let mut changes = Vec::new();
+        for feature in [Feature::Brightness, Feature::Contrast, Feature::Volume] {
+            let (Some(from), Some(to)) = (current.get(feature), state.get(feature)) else {
+                continue;
+            };
+            if from != to {
+                self.set_percentage(index, feature, to)?;
+                changes.push(StateChange { feature, from, to });
+            }
+        }
+        Ok(changes)
+    }
+
+    /// Runs discovery again in place, e.g. after a monitor was plugged in.
+    /// Monitors still connected are matched by EDID serial and keep their
+    /// open handle and index order; monitors without a serial cannot be
+    /// matched, so they are reported as removed and probed again like new
+    /// ones. Fails, leaving everything as it was, if no monitor is left.
+    pub fn rediscover(&mut self, options: &DdcOptions) -> Result<Rediscovery, DdcError> {
+        let options = &options.clone().with_env_overrides();
+        let known: HashMap<String, String> = self
+            .monitors
+            .iter()
+            .filter_map(|m| Some((m.edid_serial.clone()?, m.i2c_path.clone())))
+            .collect();
+        let (mut found, failures, report, kept) = Self::discover_monitors(options, &known)?;
+        Self::detect_invert(&mut found, &options.invert_codes);
+
+        let mut result = Rediscovery::default();
+        let mut mapping = HashMap::new();
+        for (old_index, mut monitor) in std::mem::take(&mut self.monitors).into_iter().enumerate() {
+            let connector = monitor
+                .edid_serial
+                .as_ref()
+                .and_then(|serial| kept.iter().find(|(s, _)| s == serial));
+            match connector {
+                Some((_, connector)) => {
+                    monitor.connector = connector.clone();
+                    mapping.insert(old_index, self.monitors.len());
+                    self.monitors.push(monitor);
+                }
+                None => result.removed.push(monitor.name),
+            }
+        }
+        let first_added = self.monitors.len();
+        self.monitors.extend(found);
+        Self::name_monitors(&mut self.monitors);
+        result.added = self.monitors[first_added..]
+            .iter()
+            .map(|m| m.name.clone())
+            .collect();
+        self.failures = failures;
+        self.report = report;
+
+        remap_indices(&mut self.last_known, &mapping);
+        remap_indices(&mut self.intended, &mapping);
+        remap_indices(&mut self.pending, &mapping);
+        remap_indices(&mut self.holds, &mapping);
+        remap_indices(&mut self.capped, &mapping);
+        Ok(result)
+    }
+
+    /// Marks a control of monitor `index` as under manual control for
+    /// `duration`, so schedules leave it alone until then.
+    pub fn hold(&mut self, index: usize, feature: Feature, duration: Duration) {
+        self.holds
+            .insert((index, feature), Instant::now() + duration);
+    }
+
+    /// Whether a manual change still holds the control, see `hold`.
+    pub fn is_held(&mut self, index: usize, feature: Feature) -> bool {
+        let now = Instant::now();
+        self.holds.retain(|_, until| *until > now);
+        self.holds.contains_key(&(index, feature))
+    }
+
+    /// Reads every control of monitor `index` this manager has written and
+    /// writes the last value again where the monitor now reports one more
+    /// than `threshold` percent away, e.g. after firmware reset it on a
+    /// signal loss. Controls that fail to read are left alone.
+    pub fn correct_drift(&mut self, index: usize, threshold: u8) -> Vec<StateChange> {
+        let mut intended: Vec<(Feature, u8)> = self
+            .intended
+            .iter()
+            .filter(|((i, _), _)| *i == index)
+            .map(|((_, feature), value)| (*feature, *value))
+            .collect();
+        intended.sort();
+
+        let mut changes = Vec::new();
+        for (feature, to) in intended {
+            let Ok(from) = self.get_percentage(index, feature) else {
+                continue;
+            };
+            if from.abs_diff(to) > threshold && self.set_percentage(index, feature, to).is_ok() {
+                changes.push(StateChange { feature, from, to });
+            }
+        }
+        changes
+    }
+
+    /// Briefly flips the monitor's brightness to the far end of its range
+    /// and back, so the user can see which panel a row controls. Restores
+    /// the exact raw value that was read.
+    pub fn identify(&mut self, index: usize) -> Result<(), DdcError> {
+        let current = self.start_identify_pulse(index)?;
+        thread::sleep(Duration::from_millis(IDENTIFY_PULSE_MS));
+        self.end_identify_pulse(index, current)
+    }
+
+    /// First half of `identify`, for callers that time the pulse
+    /// themselves: flips the brightness and returns the raw value to hand
+    /// to `end_identify_pulse`.
+    pub fn start_identify_pulse(&mut self, index: usize) -> Result<u16, DdcError> {
+        if index >= self.monitors.len() {
+            return Err(DdcError::NoMonitors);
+        }
+
+        let vcp = self
+            .command(index, |ddc| ddc.get_vcp_feature(0x10))
+            .map_err(|e| DdcError::CommError(format!("Failed to get brightness: {}", e)))?;
+        let current = vcp.value();
+        let min = self.monitors[index].min_brightness;
+        let max = self.monitors[index].max_brightness;
+        let pulse = if current > min + max.saturating_sub(min) / 2 {
+            min
+        } else {
+            self.cap_brightness_raw(index, max)
+        };
+
+        self.command(index, |ddc| ddc.set_vcp_feature(0x10, pulse))
+            .map_err(|e| DdcError::CommError(format!("Failed to set brightness: {}", e)))?;
+        Ok(current)
+    }
+
+    /// Restores the raw brightness `start_identify_pulse` returned.
+    pub fn end_identify_pulse(&mut self, index: usize, raw: u16) -> Result<(), DdcError> {
+        if index >= self.monitors.len() {
+            return Err(DdcError::NoMonitors);
+        }
+
+        self.command(index, |ddc| ddc.set_vcp_feature(0x10, raw))
+            .map_err(|e| DdcError::CommError(format!("Failed to restore brightness: {}", e)))?;
+        Ok(())
+    }
+
+    /// Applies `percentage` to every monitor, returning one result per
+    /// monitor. With `rollback`, a failure on any monitor restores the
+    /// previous brightness on the ones that had already been changed, and
+    /// those report `DdcError::RolledBack`.
+    pub fn set_brightness_all(
+        &mut self,
+        percentage: u8,
+        rollback: bool,
+    ) -> Vec<Result<WriteOutcome, DdcError>> {
+        let previous: Vec<Option<u8>> = (0..self.monitors.len())
+            .map(|i| {
+                if rollback {
+                    self.get_brightness_percentage(i).ok()
+                } else {
+                    None
+                }
+            })
+            .collect();
+
+        let mut results: Vec<Result<WriteOutcome, DdcError>> = (0..self.monitors.len())
+            .map(|i| self.set_brightness_percentage(i, percentage))
+            .collect();
+
+        if rollback && results.iter().any(|r| r.is_err()) {
+            for (i, result) in results.iter_mut().enumerate() {
+                if result.is_ok() {
+                    if let Some(prev) = previous[i] {
+                        let _ = self.set_brightness_percentage(i, prev);
+                        *result = Err(DdcError::RolledBack);
+                    }
+                }
+            }
+        }
+
+        results
+    }
+
+    pub fn get_contrast_percentage(&mut self, index: usize) -> Result<u8, DdcError> {
+        if index >= self.monitors.len() {
+            return Err(DdcError::NoMonitors);
+        }
+
+        let min = self.monitors[index].min_contrast;
+        let max = self.monitors[index].max_contrast;
+
+        if max == 0 || max <= min {
+            return Err(DdcError::Unsupported("Contrast"));
+        }
+
+        let vcp = self
+            .command(index, |ddc| ddc.get_vcp_feature(0x12))
+            .map_err(|e| DdcError::CommError(format!("Failed to get contrast: {}", e)))?;
+
+        let percentage = raw_to_percentage(vcp.value(), min, max);
+        self.record_read(index, Feature::Contrast, percentage);
+        Ok(percentage)
+    }
+
+    pub fn set_contrast_percentage(
+        &mut self,
+        index: usize,
+        percentage: u8,
+    ) -> Result<WriteOutcome, DdcError> {
+        if index >= self.monitors.len() {
+            return Err(DdcError::NoMonitors);
+        }
+
+        let min = self.monitors[index].min_contrast;
+        let max = self.monitors[index].max_contrast;
+
+        if max == 0 || max <= min {
+            return Err(DdcError::Unsupported("Contrast"));
+        }
+
+        let raw = percentage_to_raw(percentage, min, max);
+
+        self.command(index, |ddc| ddc.set_vcp_feature(0x12, raw))
+            .map_err(|e| DdcError::CommError(format!("Failed to set contrast: {}", e)))?;
+
+        let outcome = self.verify_write(index, 0x12, raw, percentage, |v| {
+            raw_to_percentage(v, min, max)
+        });
+        self.record_write(index, Feature::Contrast, outcome, percentage);
+        Ok(outcome)
+    }
+
+    /// Whether monitor `index` supports `feature`, telling an unsupported
+    /// feature apart from a monitor that doesn't exist. Brightness is always
+    /// supported; discovery skips monitors without it.
+    pub fn query_support(&self, index: usize, feature: Feature) -> Result<bool, DdcError> {
+        let monitor = self.monitors.get(index).ok_or(DdcError::NoMonitors)?;
+        Ok(match feature {
+            Feature::Brightness => true,
+            Feature::Contrast => monitor.max_contrast > monitor.min_contrast,
+            Feature::Volume => monitor.max_volume > monitor.min_volume,
+        })
+    }
+
+    /// Like `query_support`, but also `false` for an index out of range.
+    pub fn supports_contrast(&self, index: usize) -> bool {
+        self.query_support(index, Feature::Contrast)
+            .unwrap_or(false)
+    }
+
+    pub fn supports_volume(&self, index: usize) -> bool {
+        self.query_support(index, Feature::Volume).unwrap_or(false)
+    }
+
+    pub fn supports_input_source(&self, index: usize) -> bool {
+        if index >= self.monitors.len() {
+            return false;
+        }
+        self.monitors[index].supports_input_source
+    }
+
+    pub fn supports_power_mode(&self, index: usize) -> bool {
+        if index >= self.monitors.len() {
+            return false;
+        }
+        self.monitors[index].supports_power_mode
+    }
+
+    pub fn get_volume_percentage(&mut self, index: usize) -> Result<u8, DdcError> {
+        if index >= self.monitors.len() {
+            return Err(DdcError::NoMonitors);
+        }
+
+        let min = self.monitors[index].min_volume;
+        let max = self.monitors[index].max_volume;
+
+        if max <= min {
+            return Err(DdcError::Unsupported("Volume"));
+        }
+
+        let vcp = self
+            .command(index, |ddc| ddc.get_vcp_feature(0x62))
+            .map_err(|e| DdcError::CommError(format!("Failed to get volume: {}", e)))?;
+
+        let percentage = raw_to_percentage(vcp.value(), min, max);
+        self.record_read(index, Feature::Volume, percentage);
+        Ok(percentage)
+    }
+
+    pub fn set_volume_percentage(
+        &mut self,
+        index: usize,
+        percentage: u8,
+    ) -> Result<WriteOutcome, DdcError> {
+        if index >= self.monitors.len() {
+            return Err(DdcError::NoMonitors);
+        }
+
+        let min = self.monitors[index].min_volume;
+        let max = self.monitors[index].max_volume;
+
+        if max <= min {
+            return Err(DdcError::Unsupported("Volume"));
+        }
+
+        let raw = percentage_to_raw(percentage, min, max);
+
+        self.command(index, |ddc| ddc.set_vcp_feature(0x62, raw))
+            .map_err(|e| DdcError::CommError(format!("Failed to set volume: {}", e)))?;
+
+        let outcome = self.verify_write(index, 0x62, raw, percentage, |v| {
+            raw_to_percentage(v, min, max)
+        });
+        self.record_write(index, Feature::Volume, outcome, percentage);
+        Ok(outcome)
+    }
+
+    pub fn supports_rgb_bias(&self, index: usize, channel: ColorChannel) -> bool {
+        if index >= self.monitors.len() {
+            return false;
+        }
+        self.monitors[index].max_rgb_bias[channel.position()] > 0
+    }
+
+    pub fn get_rgb_bias(&mut self, index: usize, channel: ColorChannel) -> Result<u8, DdcError> {
+        if index >= self.monitors.len() {
+            return Err(DdcError::NoMonitors);
+        }
+
+        let max = self.monitors[index].max_rgb_bias[channel.position()];
+        if max == 0 {
+            return Err(DdcError::Unsupported("Black level"));
+        }
+
+        let vcp = self
+            .command(index, |ddc| ddc.get_vcp_feature(channel.bias_code()))
+            .map_err(|e| DdcError::CommError(format!("Failed to get black level: {}", e)))?;
+
+        Ok(raw_to_percentage(vcp.value(), 0, max))
+    }
+
+    pub fn set_rgb_bias(
+        &mut self,
+        index: usize,
+        channel: ColorChannel,
+        percentage: u8,
+    ) -> Result<WriteOutcome, DdcError> {
+        if index >= self.monitors.len() {
+            return Err(DdcError::NoMonitors);
+        }
+
+        let max = self.monitors[index].max_rgb_bias[channel.position()];
+        if max == 0 {
+            return Err(DdcError::Unsupported("Black level"));
+        }
+
+        let code = channel.bias_code();
+        let raw = percentage_to_raw(percentage, 0, max);
+
+        self.command(index, |ddc| ddc.set_vcp_feature(code, raw))
+            .map_err(|e| DdcError::CommError(format!("Failed to set black level: {}", e)))?;
+
+        Ok(self.verify_write(index, code, raw, percentage, |v| {
+            raw_to_percentage(v, 0, max)
+        }))
+    }
+
+    pub fn color_temperature_control(&self, index: usize) -> ColorTemperatureControl {
+        let Some(monitor) = self.monitors.get(index) else {
+            return ColorTemperatureControl::Unsupported;
+        };
+        if !monitor.max_rgb_gain.contains(&0) {
+            return ColorTemperatureControl::Gains;
+        }
+        let presets: Vec<u32> = COLOR_TEMPERATURE_PRESETS
+            .iter()
+            .filter(|(code, _)| monitor.color_temperature_presets.contains(code))
+            .map(|(_, kelvin)| *kelvin)
+            .collect();
+        if presets.is_empty() {
+            ColorTemperatureControl::Unsupported
+        } else {
+            ColorTemperatureControl::Presets(presets)
+        }
+    }
+
+    /// The active temperature preset in Kelvin. Only presets can be read
+    /// back; a temperature set through the gains is not known.
+    pub fn get_color_temperature(&mut self, index: usize) -> Result<Option<u32>, DdcError> {
+        if index >= self.monitors.len() {
+            return Err(DdcError::NoMonitors);
+        }
+        if !matches!(
+            self.color_temperature_control(index),
+            ColorTemperatureControl::Presets(_)
+        ) {
+            return Ok(None);
+        }
+
+        let vcp = self
+            .command(index, |ddc| ddc.get_vcp_feature(0x14))
+            .map_err(|e| DdcError::CommError(format!("Failed to get color preset: {}", e)))?;
+
+        Ok(COLOR_TEMPERATURE_PRESETS
+            .iter()
+            .find(|(code, _)| *code == vcp.sl)
+            .map(|(_, kelvin)| *kelvin))
+    }
+
+    /// Sets the color temperature closest to `kelvin` that the monitor
+    /// supports, through the RGB gains or the nearest preset, and returns
+    /// the temperature set.
+    pub fn set_color_temperature(&mut self, index: usize, kelvin: u32) -> Result<u32, DdcError> {
+        if index >= self.monitors.len() {
+            return Err(DdcError::NoMonitors);
+        }
+
+        match self.color_temperature_control(index) {
+            ColorTemperatureControl::Unsupported => Err(DdcError::Unsupported("Color temperature")),
+            ColorTemperatureControl::Gains => {
+                let kelvin = kelvin.clamp(MIN_KELVIN, MAX_KELVIN);
+                let gains = kelvin_to_rgb_gains(kelvin);
+                for channel in ColorChannel::ALL {
+                    let max = self.monitors[index].max_rgb_gain[channel.position()];
+                    let raw = (gains[channel.position()] * max as f64).round() as u16;
+                    self.command(index, |ddc| ddc.set_vcp_feature(channel.gain_code(), raw))
+                        .map_err(|e| {
+                            DdcError::CommError(format!(
+                                "Failed to set {} gain: {}",
+                                channel.name(),
+                                e
+                            ))
+                        })?;
+                }
+                Ok(kelvin)
+            }
+            ColorTemperatureControl::Presets(presets) => {
+                let nearest = presets
+                    .into_iter()
+                    .min_by_key(|preset| preset.abs_diff(kelvin))
+                    .unwrap_or(kelvin);
+                let code = COLOR_TEMPERATURE_PRESETS
+                    .iter()
+                    .find(|(_, k)| *k == nearest)
+                    .map(|(code, _)| *code as u16)
+                    .ok_or(DdcError::Unsupported("Color temperature"))?;
+                self.command(index, |ddc| ddc.set_vcp_feature(0x14, code))
+                    .map_err(|e| {
+                        DdcError::CommError(format!("Failed to set color preset: {}", e))
+                    })?;
+                Ok(nearest)
+            }
+        }
+    }
+
+    /// Copies brightness, contrast, volume and color settings from monitor
+    /// `from` to monitor `to`, skipping whatever either side does not
+    /// support or fails to read or write.
+    pub fn copy_settings(&mut self, from: usize, to: usize) -> Result<CopyReport, DdcError> {
+        if from >= self.monitors.len() || to >= self.monitors.len() {
+            return Err(DdcError::NoMonitors);
+        }
+
+        let mut report = CopyReport::default();
+
+        let brightness = self
+            .get_brightness_percentage(from)
+            .and_then(|v| self.set_brightness_percentage(to, v))
+            .is_ok();
+        report.record("Brightness", brightness);
+
+        let contrast = self.supports_contrast(from)
+            && self.supports_contrast(to)
+            && self
+                .get_contrast_percentage(from)
+                .and_then(|v| self.set_contrast_percentage(to, v))
+                .is_ok();
+        report.record("Contrast", contrast);
+
+        let volume = self.supports_volume(from)
+            && self.supports_volume(to)
+            && self
+                .get_volume_percentage(from)
+                .and_then(|v| self.set_volume_percentage(to, v))
+                .is_ok();
+        report.record("Volume", volume);
+
+        for (name, code) in COLOR_FEATURES {
+            let copied = match (self.get_raw_vcp(from, code), self.get_raw_vcp(to, code)) {
+                (Ok((value, from_max)), Ok((_, to_max))) => {
+                    // Presets are enumerated values, gains are continuous and
+                    // are rescaled when the two monitors use different ranges.
+                    let value = if code == 0x14 || from_max == to_max || from_max == 0 {
+                        value
+                    } else {
+                        ((value as u32 * to_max as u32) / from_max as u32) as u16
+                    };
+                    self.set_raw_vcp(to, code, value).is_ok()
+                }
+                _ => false,
+            };
+            report.record(name, copied);
+        }
+
+        Ok(report)
+    }
+
+    /// Reads an arbitrary VCP feature, returning `(current, maximum)`.
+    pub fn get_raw_vcp(&mut self, index: usize, code: u8) -> Result<(u16, u16), DdcError> {
+        if index >= self.monitors.len() {
+            return Err(DdcError::NoMonitors);
+        }
+
+        let vcp = self
+            .command(index, |ddc| ddc.get_vcp_feature(code))
+            .map_err(|e| DdcError::CommError(format!("Failed to get VCP 0x{:02x}: {}", code, e)))?;
+
+        Ok((vcp.value(), vcp.maximum()))
+    }
+
+    /// Current raw brightness and the maximum the monitor reports with it,
+    /// from a single read and without percentage scaling.
+    pub fn get_brightness_raw(&mut self, index: usize) -> Result<(u16, u16), DdcError> {
+        self.read_feature_raw(index, 0x10, "brightness")
+    }
+
+    /// Like `get_brightness_raw`, for contrast.
+    pub fn get_contrast_raw(&mut self, index: usize) -> Result<(u16, u16), DdcError> {
+        if index < self.monitors.len() && !self.supports_contrast(index) {
+            return Err(DdcError::Unsupported("Contrast"));
+        }
+        self.read_feature_raw(index, 0x12, "contrast")
+    }
+
+    /// Like `get_brightness_raw`, for volume.
+    pub fn get_volume_raw(&mut self, index: usize) -> Result<(u16, u16), DdcError> {
+        if index < self.monitors.len() && !self.supports_volume(index) {
+            return Err(DdcError::Unsupported("Volume"));
+        }
+        self.read_feature_raw(index, 0x62, "volume")
+    }
+
+    fn read_feature_raw(
+        &mut self,
+        index: usize,
+        code: u8,
+        feature: &str,
+    ) -> Result<(u16, u16), DdcError> {
+        if index >= self.monitors.len() {
             return Err(DdcError::NoMonitors);
         }
 
-        let min = self.monitors[index].min_contrast;
-        let max = self.monitors[index].max_contrast;
+        let vcp = self
+            .command(index, |ddc| ddc.get_vcp_feature(code))
+            .map_err(|e| DdcError::CommError(format!("Failed to get {}: {}", feature, e)))?;
 
-        if max == 0 || max <= min {
-            return Err(DdcError::CommError("Contrast not supported".to_string()));
+        Ok((vcp.value(), vcp.maximum()))
+    }
+
+    /// Writes an arbitrary VCP feature without any range checking, except
+    /// that brightness (0x10) still keeps to the brightness ceiling.
+    pub fn set_raw_vcp(&mut self, index: usize, code: u8, value: u16) -> Result<(), DdcError> {
+        if index >= self.monitors.len() {
+            return Err(DdcError::NoMonitors);
         }
 
-        let vcp = self.monitors[index]
-            .handle
-            .get_vcp_feature(0x12)
-            .map_err(|e| DdcError::CommError(format!("Failed to get contrast: {}", e)))?;
+        let value = self.raw_vcp_write(index, code, value);
 
-        let current = vcp.value();
-        let current = current.clamp(min, max);
-        let percentage = ((current.saturating_sub(min)) * 100) / (max - min);
-        Ok(percentage as u8)
+        self.command(index, |ddc| ddc.set_vcp_feature(code, value))
+            .map_err(|e| DdcError::CommError(format!("Failed to set VCP 0x{:02x}: {}", code, e)))?;
+
+        Ok(())
     }
 
-    pub fn set_contrast_percentage(
-        &mut self,
-        index: usize,
-        percentage: u8,
-    ) -> Result<(), DdcError> {
+    pub fn get_input_source(&mut self, index: usize) -> Result<InputSource, DdcError> {
         if index >= self.monitors.len() {
             return Err(DdcError::NoMonitors);
         }
 
-        let min = self.monitors[index].min_contrast;
-        let max = self.monitors[index].max_contrast;
+        let vcp = self
+            .command(index, |ddc| ddc.get_vcp_feature(0x60))
+            .map_err(|e| DdcError::CommError(format!("Failed to get input source: {}", e)))?;
 
-        if max == 0 || max <= min {
-            return Ok(());
+        Ok(InputSource::from_vcp_value(vcp.value()))
+    }
+
+    pub fn set_input_source(&mut self, index: usize, source: InputSource) -> Result<(), DdcError> {
+        if index >= self.monitors.len() {
+            return Err(DdcError::NoMonitors);
         }
 
-        let percentage = percentage.clamp(0, 100);
-        let raw = min + ((percentage as u16 * (max - min)) / 100);
+        self.command(index, |ddc| ddc.set_vcp_feature(0x60, source.code() as u16))
+            .map_err(|e| DdcError::CommError(format!("Failed to set input source: {}", e)))?;
 
-        self.monitors[index]
-            .handle
-            .set_vcp_feature(0x12, raw)
-            .map_err(|e| DdcError::CommError(format!("Failed to set contrast: {}", e)))?;
+        Ok(())
+    }
+
+    /// Like `set_input_source`, with the input given by name as accepted by
+    /// `InputSource::from_name`.
+    pub fn set_input_source_by_name(&mut self, index: usize, name: &str) -> Result<(), DdcError> {
+        let source = InputSource::from_name(name)
+            .ok_or_else(|| DdcError::UnknownInputSource(name.to_string()))?;
+        self.set_input_source(index, source)
+    }
+
+    pub fn get_power_mode(&mut self, index: usize) -> Result<PowerMode, DdcError> {
+        if index >= self.monitors.len() {
+            return Err(DdcError::NoMonitors);
+        }
+
+        let vcp = self
+            .command(index, |ddc| ddc.get_vcp_feature(0xd6))
+            .map_err(|e| DdcError::CommError(format!("Failed to get power mode: {}", e)))?;
+
+        Ok(PowerMode::from_vcp_value(vcp.value()))
+    }
+
+    pub fn set_power_mode(&mut self, index: usize, mode: PowerMode) -> Result<(), DdcError> {
+        if index >= self.monitors.len() {
+            return Err(DdcError::NoMonitors);
+        }
+
+        self.command(index, |ddc| ddc.set_vcp_feature(0xd6, mode.code() as u16))
+            .map_err(|e| DdcError::CommError(format!("Failed to set power mode: {}", e)))?;
 
         Ok(())
     }
 
-    pub fn supports_contrast(&self, index: usize) -> bool {
+    /// Which features monitor `index` was found to support, by display
+    /// name, in the order the UI lists them.
+    pub fn feature_summary(&self, index: usize) -> Vec<(&'static str, bool)> {
         if index >= self.monitors.len() {
-            return false;
+            return Vec::new();
         }
-        self.monitors[index].max_contrast > 0
+        vec![
+            ("Brightness", true),
+            ("Contrast", self.supports_contrast(index)),
+            ("Volume", self.supports_volume(index)),
+            ("Input", self.supports_input_source(index)),
+            ("Power", self.supports_power_mode(index)),
+            (
+                "Color",
+                ColorChannel::ALL
+                    .into_iter()
+                    .any(|c| self.supports_rgb_bias(index, c))
+                    || self.monitors[index].max_rgb_gain.iter().any(|&g| g > 0),
+            ),
+            ("OSD lock", self.supports_osd_lock(index)),
+            ("Invert", self.supports_invert(index)),
+        ]
     }
 
-    pub fn supports_volume(&self, index: usize) -> bool {
+    pub fn is_writable(&self, index: usize) -> bool {
         if index >= self.monitors.len() {
             return false;
         }
-        self.monitors[index].max_volume > 0
+        self.monitors[index].writable
     }
 
-    pub fn supports_input_source(&self, index: usize) -> bool {
+    pub fn supports_osd_lock(&self, index: usize) -> bool {
         if index >= self.monitors.len() {
             return false;
         }
-        self.monitors[index].supports_input_source
+        self.monitors[index].supports_osd_lock
     }
 
-    pub fn supports_power_mode(&self, index: usize) -> bool {
+    pub fn osd_lock_writable(&self, index: usize) -> bool {
         if index >= self.monitors.len() {
             return false;
         }
-        self.monitors[index].supports_power_mode
+        self.monitors[index].osd_lock_writable
     }
 
-    pub fn get_volume_percentage(&mut self, index: usize) -> Result<u8, DdcError> {
+    /// Whether the on-screen display is disabled, so the monitor's own
+    /// buttons can't change its settings.
+    pub fn get_osd_lock(&mut self, index: usize) -> Result<bool, DdcError> {
         if index >= self.monitors.len() {
             return Err(DdcError::NoMonitors);
         }
-
-        let vcp = self.monitors[index]
-            .handle
-            .get_vcp_feature(0x62)
-            .map_err(|e| DdcError::CommError(format!("Failed to get volume: {}", e)))?;
-
-        let current = vcp.value();
-        let min = self.monitors[index].min_volume;
-        let max = self.monitors[index].max_volume;
-
-        if max <= min {
-            return Ok(0);
+        if !self.monitors[index].supports_osd_lock {
+            return Err(DdcError::Unsupported("OSD lock"));
         }
 
-        let current = current.clamp(min, max);
-        let percentage = ((current.saturating_sub(min)) * 100) / (max - min);
-        Ok(percentage as u8)
+        let vcp = self
+            .command(index, |ddc| ddc.get_vcp_feature(0xca))
+            .map_err(|e| DdcError::CommError(format!("Failed to get OSD state: {}", e)))?;
+
+        Ok(vcp.sl == 1)
     }
 
-    pub fn set_volume_percentage(&mut self, index: usize, percentage: u8) -> Result<(), DdcError> {
+    pub fn set_osd_lock(&mut self, index: usize, locked: bool) -> Result<(), DdcError> {
         if index >= self.monitors.len() {
             return Err(DdcError::NoMonitors);
         }
+        if !self.monitors[index].osd_lock_writable {
+            return Err(DdcError::Unsupported("OSD lock"));
+        }
 
-        let min = self.monitors[index].min_volume;
-        let max = self.monitors[index].max_volume;
+        // MCCS 2.2a uses the high byte for power button control; keep it.
+        let vcp = self
+            .command(index, |ddc| ddc.get_vcp_feature(0xca))
+            .map_err(|e| DdcError::CommError(format!("Failed to get OSD state: {}", e)))?;
+        let value = (vcp.sh as u16) << 8 | if locked { 1 } else { 2 };
+        self.command(index, |ddc| ddc.set_vcp_feature(0xca, value))
+            .map_err(|e| DdcError::CommError(format!("Failed to set OSD state: {}", e)))?;
 
-        if max <= min {
-            return Ok(());
-        }
+        Ok(())
+    }
 
-        let percentage = percentage.clamp(0, 100);
-        let raw = min + ((percentage as u16 * (max - min)) / 100);
+    /// `Monitor::connector_info` for monitor `index`.
+    pub fn connector_info(&self, index: usize) -> ConnectorInfo {
+        self.monitors[index].connector_info(&self.drm)
+    }
 
-        self.monitors[index]
-            .handle
-            .set_vcp_feature(0x62, raw)
-            .map_err(|e| DdcError::CommError(format!("Failed to set volume: {}", e)))?;
+    pub fn display_technology(&self, index: usize) -> DisplayTechnology {
+        self.monitors
+            .get(index)
+            .map(|m| m.tech_type)
+            .unwrap_or_default()
+    }
 
-        Ok(())
+    pub fn supports_invert(&self, index: usize) -> bool {
+        if index >= self.monitors.len() {
+            return false;
+        }
+        self.monitors[index].supports_invert
     }
 
-    pub fn get_input_source(&mut self, index: usize) -> Result<InputSource, DdcError> {
+    /// Whether the monitor shows its image with inverted colors.
+    pub fn get_invert(&mut self, index: usize) -> Result<bool, DdcError> {
         if index >= self.monitors.len() {
             return Err(DdcError::NoMonitors);
         }
+        if !self.monitors[index].supports_invert {
+            return Err(DdcError::Unsupported("Invert"));
+        }
 
-        let vcp = self.monitors[index]
-            .handle
-            .get_vcp_feature(0x60)
-            .map_err(|e| DdcError::CommError(format!("Failed to get input source: {}", e)))?;
+        let code = self.monitors[index].invert_code;
+        let vcp = self
+            .command(index, |ddc| ddc.get_vcp_feature(code))
+            .map_err(|e| DdcError::CommError(format!("Failed to get invert state: {}", e)))?;
 
-        let current = vcp.value();
-        Ok(InputSource::from_code(current as u8))
+        Ok(vcp.value() == 1)
     }
 
-    pub fn set_input_source(&mut self, index: usize, source: InputSource) -> Result<(), DdcError> {
+    pub fn set_invert(&mut self, index: usize, inverted: bool) -> Result<(), DdcError> {
         if index >= self.monitors.len() {
             return Err(DdcError::NoMonitors);
         }
+        if !self.monitors[index].supports_invert {
+            return Err(DdcError::Unsupported("Invert"));
+        }
 
-        self.monitors[index]
-            .handle
-            .set_vcp_feature(0x60, source.code() as u16)
-            .map_err(|e| DdcError::CommError(format!("Failed to set input source: {}", e)))?;
+        let code = self.monitors[index].invert_code;
+        self.command(index, |ddc| ddc.set_vcp_feature(code, inverted as u16))
+            .map_err(|e| DdcError::CommError(format!("Failed to set invert state: {}", e)))?;
 
         Ok(())
     }
 
-    pub fn get_power_mode(&mut self, index: usize) -> Result<PowerMode, DdcError> {
-        if index >= self.monitors.len() {
-            return Err(DdcError::NoMonitors);
+    /// Manufacturer on/off features of monitor `index`; empty for an index
+    /// out of range.
+    pub fn vendor_toggles(&self, index: usize) -> &[VendorToggle] {
+        self.monitors
+            .get(index)
+            .map_or(&[], |m| m.vendor_toggles.as_slice())
+    }
+
+    fn vendor_toggle(&self, index: usize, code: u8) -> Result<VendorToggle, DdcError> {
+        let monitor = self.monitors.get(index).ok_or(DdcError::NoMonitors)?;
+        monitor
+            .vendor_toggles
+            .iter()
+            .find(|t| t.code == code)
+            .copied()
+            .ok_or(DdcError::Unsupported("Vendor toggle"))
+    }
+
+    /// Whether the manufacturer feature `code` is on; any value other than
+    /// its listed off value counts as on.
+    pub fn get_vendor_toggle(&mut self, index: usize, code: u8) -> Result<bool, DdcError> {
+        let toggle = self.vendor_toggle(index, code)?;
+        let vcp = self
+            .command(index, |ddc| ddc.get_vcp_feature(code))
+            .map_err(|e| DdcError::CommError(format!("Failed to get VCP 0x{:02x}: {}", code, e)))?;
+
+        Ok(vcp.value() & 0xff != toggle.off as u16)
+    }
+
+    pub fn set_vendor_toggle(&mut self, index: usize, code: u8, on: bool) -> Result<(), DdcError> {
+        let toggle = self.vendor_toggle(index, code)?;
+        let value = if on { toggle.on } else { toggle.off };
+        self.command(index, |ddc| ddc.set_vcp_feature(code, value as u16))
+            .map_err(|e| DdcError::CommError(format!("Failed to set VCP 0x{:02x}: {}", code, e)))?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn input_source_codes_round_trip() {
+        for source in InputSource::ALL {
+            assert_eq!(InputSource::from_code(source.code()), source);
         }
+        assert_eq!(InputSource::from_code(0x0f), InputSource::DisplayPort1);
+        assert_eq!(InputSource::from_code(0x10), InputSource::DisplayPort2);
+        assert_eq!(InputSource::from_code(0x42), InputSource::Unknown(0x42));
+        assert_eq!(InputSource::Unknown(0x42).code(), 0x42);
+    }
 
-        let vcp = self.monitors[index]
-            .handle
-            .get_vcp_feature(0xd6)
-            .map_err(|e| DdcError::CommError(format!("Failed to get power mode: {}", e)))?;
+    #[test]
+    fn display_technology_decodes_known_types() {
+        assert_eq!(
+            DisplayTechnology::from_vcp_value(0x06),
+            DisplayTechnology::Oled
+        );
+        assert_eq!(
+            DisplayTechnology::from_vcp_value(0x0103),
+            DisplayTechnology::Lcd
+        );
+        assert_eq!(
+            DisplayTechnology::from_vcp_value(0x00),
+            DisplayTechnology::Unknown
+        );
+        assert_eq!(
+            DisplayTechnology::from_vcp_value(0x2a),
+            DisplayTechnology::Unknown
+        );
+    }
 
-        let current = vcp.value();
-        Ok(PowerMode::from_code(current as u8))
+    #[test]
+    fn vcp_values_ignore_the_high_byte() {
+        assert_eq!(InputSource::from_vcp_value(0x0011), InputSource::Hdmi1);
+        assert_eq!(InputSource::from_vcp_value(0x0111), InputSource::Hdmi1);
+        assert_eq!(
+            InputSource::from_vcp_value(0xff0f),
+            InputSource::DisplayPort1
+        );
+        assert_eq!(PowerMode::from_vcp_value(0x0104), PowerMode::Off);
+        assert_eq!(PowerMode::from_vcp_value(0x8001), PowerMode::On);
+        assert_eq!(PowerMode::from_vcp_value(0x0209), PowerMode::Unknown(0x09));
     }
 
-    pub fn set_power_mode(&mut self, index: usize, mode: PowerMode) -> Result<(), DdcError> {
-        if index >= self.monitors.len() {
-            return Err(DdcError::NoMonitors);
+    #[test]
+    fn input_source_from_name_accepts_aliases() {
+        for source in InputSource::ALL {
+            assert_eq!(InputSource::from_name(source.name()), Some(source));
+        }
+        assert_eq!(InputSource::from_name("hdmi 2"), Some(InputSource::Hdmi2));
+        assert_eq!(
+            InputSource::from_name("DP1"),
+            Some(InputSource::DisplayPort1)
+        );
+        assert_eq!(
+            InputSource::from_name("displayport-2"),
+            Some(InputSource::DisplayPort2)
+        );
+        assert_eq!(InputSource::from_name("usbc"), Some(InputSource::UsbC));
+        assert_eq!(InputSource::from_name("USB-C"), Some(InputSource::UsbC));
+        assert_eq!(InputSource::from_name("Unknown"), None);
+        assert_eq!(InputSource::from_name("svideo"), None);
+    }
+
+    #[test]
+    fn power_mode_codes_round_trip() {
+        for mode in PowerMode::ALL {
+            assert_eq!(PowerMode::from_code(mode.code()), mode);
         }
+        assert_eq!(PowerMode::from_code(0x09), PowerMode::Unknown(0x09));
+        assert_eq!(PowerMode::Unknown(0x09).code(), 0x09);
+    }
 
-        self.monitors[index]
-            .handle
-            .set_vcp_feature(0xd6, mode.code() as u16)
-            .map_err(|e| DdcError::CommError(format!("Failed to set power mode: {}", e)))?;
+    #[test]
+    fn native_mode_is_the_first_listed_mode() {
+        assert_eq!(
+            parse_native_mode("2560x1440\n1920x1080\n1280x720\n"),
+            Some((2560, 1440))
+        );
+        assert_eq!(parse_native_mode("1920x1080i\n"), Some((1920, 1080)));
+        assert_eq!(parse_native_mode(""), None);
+    }
 
-        Ok(())
+    #[test]
+    fn discovery_report_explains_each_connector() {
+        let mut report = DiscoveryReport::default();
+        report.push(
+            "card0-DP-1",
+            "DELL U2720Q",
+            ConnectorStatus::Found("/dev/i2c-5".into()),
+        );
+        report.push(
+            "card0-HDMI-A-1",
+            "LG",
+            ConnectorStatus::Failed {
+                tried: 2,
+                error: "Permission denied: /dev/i2c-4".into(),
+            },
+        );
+        assert!(report.has_failures());
+        assert_eq!(
+            report.to_string(),
+            "card0-DP-1 (DELL U2720Q): working on /dev/i2c-5\n\
+             card0-HDMI-A-1 (LG): failed on 2 I2C device(s): Permission denied: /dev/i2c-4"
+        );
+    }
+
+    #[test]
+    fn capabilities_vcp_values_reads_nested_lists() {
+        let caps = "(vcp(02 10 14(05 08 0B) 60(0F 11))mccs_ver(2.2))";
+        assert_eq!(
+            capabilities_vcp_values(caps, 0x14),
+            Some(vec![0x05, 0x08, 0x0b])
+        );
+        assert_eq!(capabilities_vcp_values(caps, 0x60), Some(vec![0x0f, 0x11]));
+        assert_eq!(capabilities_vcp_values(caps, 0x10), None);
+        assert_eq!(capabilities_vcp_values(caps, 0x05), None);
+    }
+
+    #[test]
+    fn kelvin_gains_warm_below_daylight_and_cool_above() {
+        let daylight = kelvin_to_rgb_gains(6500);
+        assert!(daylight.iter().all(|&c| c > 0.95));
+
+        let [red, green, blue] = kelvin_to_rgb_gains(3000);
+        assert_eq!(red, 1.0);
+        assert!(red > green && green > blue);
+
+        let [red, _, blue] = kelvin_to_rgb_gains(10000);
+        assert_eq!(blue, 1.0);
+        assert!(red < blue);
+    }
+
+    #[test]
+    fn vendor_toggles_need_exactly_two_values() {
+        let caps = "(prot(monitor)vcp(10 12 60(0F 11) E0 E3(01 00) E9(00 01 02) FD(00 02)))";
+        assert_eq!(
+            capabilities_vendor_toggles(caps),
+            [
+                VendorToggle {
+                    code: 0xe3,
+                    off: 0,
+                    on: 1
+                },
+                VendorToggle {
+                    code: 0xfd,
+                    off: 0,
+                    on: 2
+                },
+            ]
+        );
+        assert!(capabilities_vendor_toggles("(vcp(10 12))").is_empty());
+    }
+
+    #[test]
+    fn capabilities_vcp_list_skips_nested_values() {
+        let caps = "(prot(monitor)type(lcd)vcp(02 10 12 14(05 08 0B) 60(0F 11 12) D6(01 04))mccs_ver(2.2))";
+        assert!(capabilities_list_vcp(caps, 0x60));
+        assert!(capabilities_list_vcp(caps, 0xd6));
+        assert!(!capabilities_list_vcp(caps, 0x0f));
+        assert!(!capabilities_list_vcp("(vcp(10 12))", 0x60));
+    }
+
+    #[test]
+    fn percentage_conversion_with_nonzero_min() {
+        assert_eq!(percentage_to_raw(0, 20, 120), 20);
+        assert_eq!(percentage_to_raw(50, 20, 120), 70);
+        assert_eq!(percentage_to_raw(100, 20, 120), 120);
+
+        assert_eq!(raw_to_percentage(20, 20, 120), 0);
+        assert_eq!(raw_to_percentage(70, 20, 120), 50);
+        assert_eq!(raw_to_percentage(120, 20, 120), 100);
+        assert_eq!(raw_to_percentage(5, 20, 120), 0);
+    }
+
+    #[test]
+    fn contrast_and_volume_conversion_with_nonzero_min() {
+        // Contrast often runs over a narrow window like 25..=75.
+        assert_eq!(percentage_to_raw(0, 25, 75), 25);
+        assert_eq!(percentage_to_raw(50, 25, 75), 50);
+        assert_eq!(percentage_to_raw(100, 25, 75), 75);
+        assert_eq!(raw_to_percentage(25, 25, 75), 0);
+        assert_eq!(raw_to_percentage(50, 25, 75), 50);
+        assert_eq!(raw_to_percentage(80, 25, 75), 100);
+
+        assert_eq!(percentage_to_raw(10, 1, 31), 4);
+        assert_eq!(raw_to_percentage(16, 1, 31), 50);
+    }
+
+    #[test]
+    fn percentage_conversion_handles_large_ranges() {
+        assert_eq!(percentage_to_raw(100, 0, 1000), 1000);
+        assert_eq!(raw_to_percentage(1000, 0, 1000), 100);
+    }
+
+    /// An EDID block with a valid header, and `name` and `serial` in a
+    /// monitor name and a serial string descriptor unless they are empty.
+    fn edid_with_name(name: &[u8], serial: &[u8]) -> [u8; 128] {
+        let mut edid = [0u8; 128];
+        edid[..8].copy_from_slice(&[0x00, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x00]);
+        for (offset, tag, text) in [(0x36, 0xFC, name), (0x48, 0xFF, serial)] {
+            if !text.is_empty() {
+                edid[offset..offset + 5].copy_from_slice(&[0x00, 0x00, 0x00, tag, 0x00]);
+                edid[offset + 5..offset + 5 + text.len()].copy_from_slice(text);
+            }
+        }
+        edid
+    }
+
+    #[test]
+    fn edid_serial_needs_a_serial_number_or_string() {
+        let mut edid = edid_with_name(b"", b"");
+        edid[8..12].copy_from_slice(&[0x10, 0xAC, 0x34, 0x12]);
+        assert_eq!(DdcManager::parse_edid_serial(&edid), None);
+
+        let mut edid = edid_with_name(b"", b"AB12\n");
+        edid[8..12].copy_from_slice(&[0x10, 0xAC, 0x34, 0x12]);
+        assert_eq!(
+            DdcManager::parse_edid_serial(&edid).as_deref(),
+            Some("10ac-3412-00000000-AB12")
+        );
+    }
+
+    /// A fake `/sys/class/drm` and `/dev` under a temporary directory, which
+    /// is removed again when this is dropped.
+    struct FakeSys {
+        root: PathBuf,
+        paths: SysPaths,
+    }
+
+    impl std::ops::Deref for FakeSys {
+        type Target = SysPaths;
+
+        fn deref(&self) -> &SysPaths {
+            &self.paths
+        }
+    }
+
+    impl Drop for FakeSys {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.root);
+        }
+    }
+
+    fn fake_sys(test: &str) -> FakeSys {
+        let root = std::env::temp_dir().join(format!("brightless-{}-{}", test, std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        let paths = SysPaths {
+            drm: root.join("sys/class/drm"),
+            dev: root.join("dev"),
+        };
+        fs::create_dir_all(&paths.drm).unwrap();
+        fs::create_dir_all(&paths.dev).unwrap();
+        FakeSys { root, paths }
+    }
+
+    fn fake_connector(paths: &SysPaths, name: &str, status: &str, edid: Option<&[u8]>) {
+        let dir = paths.drm.join(name);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("status"), format!("{}\n", status)).unwrap();
+        if let Some(edid) = edid {
+            fs::write(dir.join("edid"), edid).unwrap();
+        }
+    }
+
+    #[test]
+    fn discovery_reads_connected_connectors_and_their_edid() {
+        let paths = fake_sys("connectors");
+        let edid = edid_with_name(b"DELL U2720Q\n", b"");
+        fake_connector(&paths, "card0-DP-1", "connected", Some(&edid));
+        fake_connector(&paths, "card0-HDMI-A-1", "disconnected", Some(&edid));
+        fake_connector(&paths, "card0-DP-2", "connected", Some(&[0u8; 16]));
+        fs::create_dir_all(paths.drm.join("card0")).unwrap();
+
+        let mut connectors = DdcManager::get_connected_connectors(&paths);
+        connectors.sort();
+        assert_eq!(connectors, ["card0-DP-1", "card0-DP-2"]);
+
+        let edid = DdcManager::read_edid(&paths, "card0-DP-1").unwrap();
+        assert_eq!(
+            DdcManager::parse_edid_name(&edid).as_deref(),
+            Some("DELL U2720Q")
+        );
+        assert_eq!(DdcManager::read_edid(&paths, "card0-DP-2"), None);
+    }
+
+    #[test]
+    fn rediscovery_probes_the_devices_of_unplugged_monitors() {
+        let paths = fake_sys("rediscover");
+        fs::write(paths.dev.join("i2c-4"), "").unwrap();
+        let edid = edid_with_name(b"", b"2A\n");
+        fake_connector(&paths, "card0-DP-1", "connected", Some(&edid));
+        let options = DdcOptions {
+            paths: paths.clone(),
+            ..DdcOptions::default()
+        };
+        let device = paths.dev.join("i2c-4").to_string_lossy().to_string();
+        let serial = DdcManager::parse_edid_serial(&edid).unwrap();
+
+        // Still connected: kept on its device without probing it again.
+        let known = HashMap::from([(serial.clone(), device.clone())]);
+        let (_, _, report, kept) = DdcManager::discover_monitors(&options, &known).unwrap();
+        assert_eq!(kept, [(serial, "card0-DP-1".to_string())]);
+        assert_eq!(
+            report.connectors[0].status,
+            ConnectorStatus::Found(device.clone())
+        );
+
+        // Replaced by another panel: the old monitor's device is probed.
+        let known = HashMap::from([("unplugged".to_string(), device)]);
+        let Err(DdcError::NoneDiscovered(report)) = DdcManager::discover_monitors(&options, &known)
+        else {
+            panic!("a fake device can't be a monitor");
+        };
+        assert!(matches!(
+            report.connectors[0].status,
+            ConnectorStatus::Failed { tried: 1, .. }
+        ));
+    }
+
+    #[test]
+    fn capability_cache_finds_saved_monitors_unless_refreshing() {
+        let paths = fake_sys("cache-hit");
+        let path = paths.drm.join("capabilities.json");
+        let mut options = DdcOptions {
+            capability_cache: Some(path.clone()),
+            ..DdcOptions::default()
+        };
+        assert!(CapabilityCache::for_options(&options).monitors.is_empty());
+
+        let mut cache = CapabilityCache::default();
+        let capabilities = fake_monitor(&paths, "i2c-1").capabilities();
+        cache
+            .monitors
+            .insert("ABC".to_string(), capabilities.clone());
+        cache.save(&path).unwrap();
+
+        let loaded = CapabilityCache::for_options(&options);
+        assert_eq!(loaded.monitors.get("ABC"), Some(&capabilities));
+        assert_eq!(loaded.monitors.get("XYZ"), None);
+
+        options.refresh_capabilities = true;
+        assert!(CapabilityCache::for_options(&options).monitors.is_empty());
+    }
+
+    #[test]
+    fn capability_cache_is_dropped_when_its_schema_changes() {
+        let paths = fake_sys("cache-schema");
+        let path = paths.drm.join("capabilities.json");
+        let mut cache = CapabilityCache::default();
+        let capabilities = fake_monitor(&paths, "i2c-1").capabilities();
+        cache.monitors.insert("ABC".to_string(), capabilities);
+        cache.save(&path).unwrap();
+        assert_eq!(CapabilityCache::load(&path).monitors.len(), 1);
+
+        let old = fs::read_to_string(&path).unwrap().replace(
+            &format!("\"schema\": {}", CAPABILITY_CACHE_SCHEMA),
+            "\"schema\": 1",
+        );
+        fs::write(&path, old).unwrap();
+        assert!(CapabilityCache::load(&path).monitors.is_empty());
+    }
+
+    #[test]
+    fn discovery_lists_i2c_devices_linked_ones_first() {
+        let paths = fake_sys("i2c");
+        for name in ["i2c-10", "i2c-2", "i2c-5", "tty0"] {
+            fs::write(paths.dev.join(name), "").unwrap();
+        }
+        fake_connector(&paths, "card0-DP-1", "connected", None);
+        let connector = paths.drm.join("card0-DP-1");
+        std::os::unix::fs::symlink("../../i2c-5", connector.join("ddc")).unwrap();
+        fs::create_dir_all(connector.join("i2c-10")).unwrap();
+
+        let device = |name: &str| paths.dev.join(name).to_string_lossy().to_string();
+        assert_eq!(
+            DdcManager::list_i2c_devices(&paths).unwrap(),
+            [device("i2c-2"), device("i2c-5"), device("i2c-10")]
+        );
+        assert_eq!(
+            DdcManager::linked_i2c_devices(&paths, "card0-DP-1"),
+            [device("i2c-5"), device("i2c-10")]
+        );
+    }
+
+    #[test]
+    fn diagnose_lists_every_connector() {
+        let paths = fake_sys("diagnose");
+        let edid = edid_with_name(b"DELL U2720Q\n", b"");
+        fake_connector(&paths, "card0-DP-1", "connected", Some(&edid));
+        fake_connector(&paths, "card0-HDMI-A-1", "disconnected", None);
+
+        let report = DdcManager::diagnose(&DdcOptions {
+            paths: paths.clone(),
+            ..DdcOptions::default()
+        });
+        assert!(report.contains("card0-DP-1: connected\n  EDID: DELL U2720Q, serial none\n"));
+        assert!(report.contains("  No I2C device linked in sysfs"));
+        assert!(report.contains("card0-HDMI-A-1: disconnected"));
+        assert!(!report.contains("Other I2C devices"));
+    }
+
+    #[test]
+    fn report_constructor_returns_a_manager_without_monitors() {
+        let paths = fake_sys("report");
+        let options = DdcOptions {
+            paths: paths.clone(),
+            ..DdcOptions::default()
+        };
+        let (ddc, report) = DdcManager::with_options_and_report(&options);
+        assert!(ddc.monitors.is_empty());
+        assert!(report.connectors.is_empty());
+        assert_eq!(
+            report.to_string(),
+            format!(
+                "No connected displays were found in {}; \
+                 probing I2C devices directly: No DDC monitors found",
+                paths.drm.display()
+            )
+        );
+
+        fake_connector(&paths, "card0-DP-1", "connected", None);
+        let (ddc, report) = DdcManager::with_options_and_report(&options);
+        assert!(ddc.monitors.is_empty());
+        assert_eq!(report.connectors.len(), 1);
+        assert!(report.has_failures());
+        assert_eq!(ddc.report, report);
+    }
+
+    #[test]
+    fn environment_overrides_timeout_and_retries() {
+        let options = DdcOptions {
+            retries: 2,
+            ..DdcOptions::default()
+        };
+
+        let unchanged = options.clone().with_overrides(None, Some("many"));
+        assert_eq!(unchanged.probe_timeout, options.probe_timeout);
+        assert_eq!(unchanged.retries, 2);
+
+        let overridden = options.clone().with_overrides(Some("1500"), Some("5"));
+        assert_eq!(overridden.probe_timeout, Some(Duration::from_millis(1500)));
+        assert_eq!(overridden.retries, 5);
+
+        assert_eq!(options.with_overrides(Some("0"), None).probe_timeout, None);
+    }
+
+    #[test]
+    fn rediscovery_moves_control_state_to_new_indices() {
+        let mut last_known = HashMap::from([
+            ((0, Feature::Brightness), 40),
+            ((1, Feature::Brightness), 70),
+            ((2, Feature::Volume), 10),
+        ]);
+        let mut pending = BTreeMap::from([((2, Feature::Contrast), 55)]);
+        // Monitor 0 was unplugged; 1 and 2 moved up.
+        let mapping = HashMap::from([(1, 0), (2, 1)]);
+
+        remap_indices(&mut last_known, &mapping);
+        remap_indices(&mut pending, &mapping);
+
+        assert_eq!(
+            last_known,
+            HashMap::from([((0, Feature::Brightness), 70), ((1, Feature::Volume), 10)])
+        );
+        assert_eq!(pending, BTreeMap::from([((1, Feature::Contrast), 55)]));
+    }
+
+    #[test]
+    fn edid_name_falls_back_to_pnp_id_and_product_code() {
+        let mut edid = edid_with_name(b"", b"");
+        edid[10..12].copy_from_slice(&[0x41, 0x23]);
+        for (id, vendor) in [
+            ([0x10, 0xAC], "DEL"),
+            ([0x4C, 0x2D], "SAM"),
+            ([0x1E, 0x6D], "GSM"),
+        ] {
+            edid[8..10].copy_from_slice(&id);
+            assert_eq!(
+                DdcManager::parse_edid_name(&edid),
+                Some(format!("{} 4123", vendor))
+            );
+        }
+
+        edid[0x36..0x3b].copy_from_slice(&[0x00, 0x00, 0x00, 0xFC, 0x00]);
+        edid[0x3b..0x43].copy_from_slice(b"DELL U27");
+        edid[0x43] = 0x0A;
+        assert_eq!(
+            DdcManager::parse_edid_name(&edid).as_deref(),
+            Some("DELL U27")
+        );
+    }
+
+    #[test]
+    fn generic_names_are_stable_and_distinct() {
+        let names = generic_monitor_names(&[
+            ("card0-HDMI-A-1", None),
+            ("card0-DP-2", Some("SN12345")),
+            ("card0-DP-1", None),
+        ]);
+        assert_eq!(names, ["Monitor 2", "Monitor (SN12345)", "Monitor 1"]);
+        let reordered = generic_monitor_names(&[("card0-DP-1", None), ("card0-HDMI-A-1", None)]);
+        assert_eq!(reordered, ["Monitor 1", "Monitor 2"]);
+        let numeric = generic_monitor_names(&[("card0-DP-10", None), ("card0-DP-2", None)]);
+        assert_eq!(numeric, ["Monitor 2", "Monitor 1"]);
+    }
+
+    #[test]
+    fn duplicate_names_get_their_port() {
+        let names = distinct_monitor_names(&[
+            ("LG HDR 4K", "card0-DP-1"),
+            ("DELL U2720Q", "card0-HDMI-A-1"),
+            ("LG HDR 4K", "card0-DP-2"),
+        ]);
+        assert_eq!(
+            names,
+            ["LG HDR 4K (DP-1)", "DELL U2720Q", "LG HDR 4K (DP-2)"]
+        );
+    }
+
+    #[test]
+    fn edid_name_is_trimmed_and_cleaned() {
+        let edid = edid_with_name(b"DELL\x01U2720Q  ", b"");
+        assert_eq!(
+            DdcManager::parse_edid_name(&edid).as_deref(),
+            Some("DELL U2720Q")
+        );
+    }
+
+    fn empty_manager() -> DdcManager {
+        DdcManager {
+            monitors: Vec::new(),
+            failures: Vec::new(),
+            report: DiscoveryReport::default(),
+            drm: PathBuf::new(),
+            extra_delay: Duration::ZERO,
+            retries: 0,
+            verify_writes: false,
+            brightness_curve: BrightnessCurve::Linear,
+            brightness_ceiling: None,
+            capped: HashMap::new(),
+            observers: Vec::new(),
+            last_known: HashMap::new(),
+            intended: HashMap::new(),
+            pending: BTreeMap::new(),
+            holds: HashMap::new(),
+        }
+    }
+
+    /// A manager with one `fake_monitor` on `i2c-3`, and the fake sysfs that
+    /// has to outlive it.
+    fn fake_manager(test: &str) -> (DdcManager, FakeSys) {
+        let sys = fake_sys(test);
+        let mut ddc = empty_manager();
+        ddc.monitors.push(fake_monitor(&sys, "i2c-3"));
+        (ddc, sys)
+    }
+
+    /// A monitor on a plain file under `paths`, with brightness 0..=200.
+    /// Writes to it fail, so tests check what would be sent.
+    fn fake_monitor(paths: &SysPaths, name: &str) -> Monitor {
+        let device = paths.dev.join(name);
+        let file = File::create(&device).unwrap();
+        Monitor {
+            handle: I2cDdc::new(I2c::new(file)),
+            name: name.to_string(),
+            edid_name: name.to_string(),
+            connector: String::new(),
+            min_brightness: 0,
+            max_brightness: 200,
+            min_contrast: 0,
+            max_contrast: 100,
+            min_volume: 0,
+            max_volume: 0,
+            supports_input_source: false,
+            supports_power_mode: false,
+            writable: true,
+            supports_osd_lock: false,
+            osd_lock_writable: false,
+            supports_invert: false,
+            invert_code: 0,
+            max_rgb_bias: [0; 3],
+            max_rgb_gain: [0; 3],
+            color_temperature_presets: Vec::new(),
+            vendor_toggles: Vec::new(),
+            tech_type: DisplayTechnology::Unknown,
+            mccs_version: None,
+            edid_serial: None,
+            i2c_path: device.display().to_string(),
+            i2c_candidates: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn color_counts_gains_as_well_as_black_levels() {
+        let (mut ddc, _sys) = fake_manager("summary");
+        let color = |ddc: &DdcManager| {
+            ddc.feature_summary(0)
+                .into_iter()
+                .find(|&(name, _)| name == "Color")
+                .unwrap()
+                .1
+        };
+
+        assert!(!color(&ddc));
+        ddc.monitors[0].max_rgb_gain = [0, 100, 0];
+        assert!(color(&ddc));
+        ddc.monitors[0].max_rgb_gain = [0; 3];
+        ddc.monitors[0].max_rgb_bias = [255, 0, 0];
+        assert!(color(&ddc));
+    }
+
+    #[test]
+    fn watchdog_keeps_the_value_each_monitor_settled_on() {
+        let (mut ddc, _sys) = fake_manager("watchdog");
+        ddc.record_write(0, Feature::Brightness, WriteOutcome::Exact, 60);
+        let clamped = WriteOutcome::Clamped {
+            requested: 90,
+            achieved: 80,
+        };
+        ddc.record_write(0, Feature::Contrast, clamped, 90);
+        ddc.record_write(1, Feature::Brightness, WriteOutcome::Exact, 30);
+
+        assert_eq!(ddc.intended.get(&(0, Feature::Brightness)), Some(&60));
+        assert_eq!(ddc.intended.get(&(0, Feature::Contrast)), Some(&80));
+        // The fake device can't be read, and a control that doesn't read
+        // back is left alone rather than written blindly.
+        assert!(ddc.correct_drift(0, 2).is_empty());
+        assert_eq!(ddc.intended.len(), 3);
+    }
+
+    #[test]
+    fn holds_cover_one_control_until_they_expire() {
+        let mut ddc = empty_manager();
+        ddc.hold(0, Feature::Brightness, Duration::from_secs(600));
+        ddc.hold(0, Feature::Contrast, Duration::ZERO);
+
+        assert!(ddc.is_held(0, Feature::Brightness));
+        assert!(!ddc.is_held(0, Feature::Contrast));
+        assert!(!ddc.is_held(1, Feature::Brightness));
+        // Expired holds are dropped as they are checked.
+        assert_eq!(ddc.holds.len(), 1);
+    }
+
+    #[test]
+    fn nudges_stop_at_either_end() {
+        assert_eq!(nudged(50, 10), 60);
+        assert_eq!(nudged(5, -10), 0);
+        assert_eq!(nudged(95, 10), 100);
+        assert_eq!(nudged(0, -100), 0);
+        assert_eq!(nudged(100, 100), 100);
+    }
+
+    #[test]
+    fn brightness_writes_respect_the_ceiling() {
+        let (mut ddc, _sys) = fake_manager("ceiling");
+
+        assert_eq!(ddc.brightness_write(0, 90), (90, 180));
+        assert_eq!(ddc.brightness_raw_write(0, 250), 200);
+        assert_eq!(ddc.raw_vcp_write(0, 0x10, 180), 180);
+
+        ddc.set_brightness_ceiling(Some(30));
+        // set_brightness_percentage
+        assert_eq!(ddc.brightness_write(0, 90), (30, 60));
+        assert_eq!(ddc.brightness_write(0, 20), (20, 40));
+        // set_brightness_raw
+        assert_eq!(ddc.brightness_raw_write(0, 180), 60);
+        assert_eq!(ddc.brightness_raw_write(0, 40), 40);
+        // set_raw_vcp, which only caps brightness
+        assert_eq!(ddc.raw_vcp_write(0, 0x10, 180), 60);
+        assert_eq!(ddc.raw_vcp_write(0, 0x12, 180), 180);
+
+        ddc.note_capped(0, 90, 30);
+        assert_eq!(ddc.capped_brightness(0), Some(90));
+        ddc.note_capped(0, 20, 20);
+        assert_eq!(ddc.capped_brightness(0), None);
+    }
+
+    #[test]
+    fn query_support_rejects_unknown_monitors() {
+        let ddc = empty_manager();
+        for feature in [Feature::Brightness, Feature::Contrast, Feature::Volume] {
+            assert!(matches!(
+                ddc.query_support(0, feature),
+                Err(DdcError::NoMonitors)
+            ));
+        }
+        assert!(!ddc.supports_contrast(0));
+        assert!(!ddc.supports_volume(3));
+    }
+
+    #[test]
+    fn controls_with_an_empty_range_are_unsupported() {
+        let (mut ddc, _sys) = fake_manager("support");
+        assert!(ddc.supports_contrast(0));
+        assert!(!ddc.supports_volume(0));
+
+        ddc.monitors[0].min_contrast = 100;
+        ddc.monitors[0].min_volume = 50;
+        ddc.monitors[0].max_volume = 50;
+        assert!(!ddc.supports_contrast(0));
+        assert!(!ddc.supports_volume(0));
+    }
+
+    #[test]
+    fn raw_brightness_rejects_unknown_monitors() {
+        let mut ddc = empty_manager();
+        assert!(matches!(
+            ddc.set_brightness_raw(0, 37),
+            Err(DdcError::NoMonitors)
+        ));
+        assert!(matches!(
+            ddc.get_brightness_raw(0),
+            Err(DdcError::NoMonitors)
+        ));
+    }
+
+    #[test]
+    fn observers_see_writes_and_reads_that_changed() {
+        use std::cell::RefCell;
+        use std::rc::Rc;
+
+        let mut ddc = empty_manager();
+        let seen = Rc::new(RefCell::new(Vec::new()));
+        let seen_observer = seen.clone();
+        ddc.connect_changed(move |i, feature, value| {
+            seen_observer.borrow_mut().push((i, feature, value))
+        });
+
+        ddc.record_read(0, Feature::Brightness, 40);
+        ddc.record_read(0, Feature::Brightness, 40);
+        ddc.record_write(0, Feature::Brightness, WriteOutcome::Unverified, 50);
+        ddc.record_read(0, Feature::Brightness, 50);
+        ddc.record_read(0, Feature::Brightness, 70);
+        let clamped = WriteOutcome::Clamped {
+            requested: 5,
+            achieved: 10,
+        };
+        ddc.record_write(1, Feature::Volume, clamped, 5);
+
+        assert_eq!(
+            *seen.borrow(),
+            [
+                (0, Feature::Brightness, 50),
+                (0, Feature::Brightness, 70),
+                (1, Feature::Volume, 10)
+            ]
+        );
+    }
+
+    #[test]
+    fn direct_writes_cancel_queued_ones() {
+        let (mut ddc, _sys) = fake_manager("queued");
+        ddc.queue_brightness_raw(0, 120);
+        ddc.queue_percentage(0, Feature::Contrast, 40);
+        ddc.queue_percentage(0, Feature::Volume, 10);
+
+        // As a profile's brightness write does once it got through.
+        ddc.record_write(0, Feature::Brightness, WriteOutcome::Unverified, 70);
+
+        let flushed: Vec<(usize, Feature)> = ddc
+            .flush_pending()
+            .into_iter()
+            .map(|(index, feature, _)| (index, feature))
+            .collect();
+        assert_eq!(flushed, [(0, Feature::Contrast), (0, Feature::Volume)]);
+        assert!(ddc.flush_pending().is_empty());
+    }
+
+    #[test]
+    fn perceptual_curve_keeps_endpoints_and_darkens_the_middle() {
+        let curve = BrightnessCurve::Perceptual;
+        assert_eq!(curve.percentage_to_raw(0, 20, 120), 20);
+        assert_eq!(curve.percentage_to_raw(100, 20, 120), 120);
+        assert!(curve.percentage_to_raw(50, 0, 100) < percentage_to_raw(50, 0, 100));
+
+        for percentage in [0, 25, 50, 75, 100] {
+            let raw = curve.percentage_to_raw(percentage, 0, 1000);
+            assert_eq!(curve.raw_to_percentage(raw, 0, 1000), percentage);
+        }
     }
 }
@@ -0,0 +1,295 @@
+//! Minimal HTTP control endpoint, e.g. for Home Assistant.
+//!
+//! The server runs on its own thread and never touches a [`DdcManager`]:
+//! each parsed request is sent over a channel to the thread that owns the
+//! manager, which answers it with [`handle`].
+
+use crate::ddc_manager::{DdcError, DdcManager, WriteOutcome};
+use serde_json::{json, Value};
+use std::io;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Request {
+    /// `GET /monitors`
+    ListMonitors,
+    /// `POST /monitors/{id}/brightness` with `{"brightness": 0-100}`.
+    SetBrightness { index: usize, percentage: u8 },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Response {
+    pub status: u16,
+    pub body: Value,
+}
+
+impl Response {
+    fn ok(body: Value) -> Self {
+        Self { status: 200, body }
+    }
+
+    fn error(status: u16, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            body: json!({ "error": message.into() }),
+        }
+    }
+}
+
+/// A request waiting for the manager's owner, and where to send the reply.
+pub struct Pending {
+    pub request: Request,
+    pub reply: Sender<Response>,
+}
+
+/// Parses a request line, headers and body, or returns the error to send
+/// back. `port` is the one the server listens on.
+///
+/// There is no authentication, so requests a web page could make are
+/// refused: any with an `Origin`, a `Host` other than this machine (DNS
+/// rebinding), or a body that isn't declared as JSON (a "simple" cross-site
+/// POST can't set that content type).
+pub fn parse(
+    method: &str,
+    url: &str,
+    headers: &[(&str, &str)],
+    body: &str,
+    port: u16,
+) -> Result<Request, Response> {
+    let header = |name: &str| {
+        headers
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.trim())
+    };
+    if header("Origin").is_some() {
+        return Err(Response::error(
+            403,
+            "cross-origin requests are not allowed",
+        ));
+    }
+    let host_allowed = header("Host").is_some_and(|host| {
+        [format!("127.0.0.1:{}", port), format!("localhost:{}", port)]
+            .iter()
+            .any(|allowed| host.eq_ignore_ascii_case(allowed))
+    });
+    if !host_allowed {
+        return Err(Response::error(403, "unexpected Host header"));
+    }
+
+    let path = url.split('?').next().unwrap_or_default();
+    let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+    match (method, segments.as_slice()) {
+        ("GET", ["monitors"]) => Ok(Request::ListMonitors),
+        ("POST", ["monitors", id, "brightness"]) => {
+            let index = id
+                .parse()
+                .map_err(|_| Response::error(404, format!("no monitor with id {}", id)))?;
+            let is_json = header("Content-Type").is_some_and(|value| {
+                value
+                    .split(';')
+                    .next()
+                    .is_some_and(|mime| mime.trim().eq_ignore_ascii_case("application/json"))
+            });
+            if !is_json {
+                return Err(Response::error(
+                    415,
+                    "expected Content-Type: application/json",
+                ));
+            }
+            let percentage = serde_json::from_str::<Value>(body)
+                .ok()
+                .and_then(|v| v.get("brightness")?.as_u64())
+                .filter(|v| *v <= 100)
+                .ok_or_else(|| {
+                    Response::error(400, "expected a JSON body like {\"brightness\": 50}")
+                })?;
+            Ok(Request::SetBrightness {
+                index,
+                percentage: percentage as u8,
+            })
+        }
+        (_, ["monitors"]) | (_, ["monitors", _, "brightness"]) => {
+            Err(Response::error(405, "method not allowed"))
+        }
+        _ => Err(Response::error(404, "not found")),
+    }
+}
+
+/// Answers `request` against the monitors `ddc` controls.
+pub fn handle(ddc: &mut DdcManager, request: &Request) -> Response {
+    match *request {
+        Request::ListMonitors => {
+            let monitors: Vec<Value> = (0..ddc.monitors.len())
+                .map(|i| {
+                    json!({
+                        "id": i,
+                        "name": ddc.monitors[i].name,
+                        "connector": ddc.monitors[i].connector,
+                        "brightness": ddc.get_brightness_percentage(i).ok(),
+                    })
+                })
+                .collect();
+            Response::ok(json!({ "monitors": monitors }))
+        }
+        Request::SetBrightness { index, percentage } => {
+            match ddc.set_brightness_percentage(index, percentage) {
+                Ok(WriteOutcome::Clamped { achieved, .. }) => {
+                    Response::ok(json!({ "brightness": achieved }))
+                }
+                Ok(_) => Response::ok(json!({ "brightness": percentage })),
+                Err(DdcError::NoMonitors) => {
+                    Response::error(404, format!("no monitor with id {}", index))
+                }
+                Err(e) => Response::error(502, e.to_string()),
+            }
+        }
+    }
+}
+
+/// Starts serving on `127.0.0.1:port`. Requests arrive on the returned
+/// receiver; once it is dropped the server answers 503.
+pub fn spawn(port: u16) -> io::Result<Receiver<Pending>> {
+    let server = tiny_http::Server::http(("127.0.0.1", port)).map_err(io::Error::other)?;
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        for mut incoming in server.incoming_requests() {
+            let mut body = String::new();
+            let response = match incoming.as_reader().read_to_string(&mut body) {
+                Err(_) => Response::error(400, "request body is not UTF-8"),
+                Ok(_) => match parse(
+                    incoming.method().as_str(),
+                    incoming.url(),
+                    &headers(&incoming),
+                    &body,
+                    port,
+                ) {
+                    Err(response) => response,
+                    Ok(request) => {
+                        let (reply, replies) = mpsc::channel();
+                        sender
+                            .send(Pending { request, reply })
+                            .ok()
+                            .and_then(|_| replies.recv().ok())
+                            .unwrap_or_else(|| Response::error(503, "Brightless is not running"))
+                    }
+                },
+            };
+            let content_type =
+                tiny_http::Header::from_bytes("Content-Type", "application/json").unwrap();
+            let _ = incoming.respond(
+                tiny_http::Response::from_string(response.body.to_string())
+                    .with_status_code(response.status)
+                    .with_header(content_type),
+            );
+        }
+    });
+    Ok(receiver)
+}
+
+fn headers(request: &tiny_http::Request) -> Vec<(&str, &str)> {
+    request
+        .headers()
+        .iter()
+        .map(|h| (h.field.as_str().as_str(), h.value.as_str()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PORT: u16 = 9531;
+    const LOCAL: &[(&str, &str)] = &[
+        ("Host", "127.0.0.1:9531"),
+        ("Content-Type", "application/json"),
+    ];
+
+    fn status(r: Result<Request, Response>) -> u16 {
+        r.unwrap_err().status
+    }
+
+    #[test]
+    fn parse_routes_requests() {
+        let parse = |method, url, body| parse(method, url, LOCAL, body, PORT);
+        assert_eq!(parse("GET", "/monitors", ""), Ok(Request::ListMonitors));
+        assert_eq!(
+            parse("GET", "/monitors/?pretty", ""),
+            Ok(Request::ListMonitors)
+        );
+        assert_eq!(
+            parse("POST", "/monitors/1/brightness", r#"{"brightness": 40}"#),
+            Ok(Request::SetBrightness {
+                index: 1,
+                percentage: 40
+            })
+        );
+        assert_eq!(status(parse("POST", "/monitors/1/brightness", "40")), 400);
+        assert_eq!(
+            status(parse(
+                "POST",
+                "/monitors/1/brightness",
+                r#"{"brightness": 101}"#
+            )),
+            400
+        );
+        assert_eq!(status(parse("POST", "/monitors/x/brightness", "")), 404);
+        assert_eq!(status(parse("DELETE", "/monitors", "")), 405);
+        assert_eq!(status(parse("GET", "/", "")), 404);
+    }
+
+    #[test]
+    fn parse_rejects_requests_a_web_page_could_make() {
+        let body = r#"{"brightness": 40}"#;
+        let post =
+            |headers: &[(&str, &str)]| parse("POST", "/monitors/0/brightness", headers, body, PORT);
+
+        assert!(post(&[
+            ("host", "localhost:9531"),
+            ("content-type", "application/json; charset=utf-8"),
+        ])
+        .is_ok());
+        assert_eq!(
+            status(post(&[
+                ("Host", "127.0.0.1:9531"),
+                ("Content-Type", "text/plain")
+            ])),
+            415
+        );
+        assert_eq!(status(post(&[("Host", "127.0.0.1:9531")])), 415);
+        assert_eq!(
+            status(post(&[
+                ("Host", "127.0.0.1:9531"),
+                ("Content-Type", "application/json"),
+                ("Origin", "https://example.com"),
+            ])),
+            403
+        );
+        assert_eq!(
+            status(post(&[
+                ("Host", "attacker.example:9531"),
+                ("Content-Type", "application/json"),
+            ])),
+            403
+        );
+        assert_eq!(
+            status(post(&[
+                ("Host", "127.0.0.1:8080"),
+                ("Content-Type", "application/json"),
+            ])),
+            403
+        );
+        assert_eq!(status(post(&[("Content-Type", "application/json")])), 403);
+        assert_eq!(
+            status(parse(
+                "GET",
+                "/monitors",
+                &[("Host", "rebound.example:9531")],
+                "",
+                PORT
+            )),
+            403
+        );
+    }
+}
@@ -0,0 +1,10 @@
+//! DDC/CI monitor discovery and control behind the Brightless GUI, usable on
+//! its own. Build with `default-features = false` to leave out GTK.
+
+pub mod ddc_manager;
+#[cfg(feature = "http")]
+pub mod http;
+
+pub use ddc_manager::{
+    DdcError, DdcManager, DisplayTechnology, Feature, InputSource, MonitorState, PowerMode,
+};
@@ -1,38 +1,208 @@
-mod ddc_manager;
+mod cli;
 mod monitor_row;
+mod profiles;
+mod schedule;
 mod settings;
 mod window;
+mod window_position;
 
+use crate::settings::AppSettings;
 use crate::window::MainWindow;
 use adw::prelude::*;
 use adw::Application;
+use brightless::ddc_manager::{self, DdcManager};
+use gtk::{gio, glib};
+use std::cell::Cell;
+use std::path::PathBuf;
+
+/// Removes `<flag> <value>` / `<flag>=<value>` from `args` so GTK does not
+/// reject it, returning the value.
+fn take_arg_value(args: &mut Vec<String>, flag: &str) -> Option<String> {
+    let prefix = format!("{}=", flag);
+    let pos = args
+        .iter()
+        .position(|a| a == flag || a.starts_with(&prefix))?;
+    let arg = args.remove(pos);
+    match arg.strip_prefix(&prefix) {
+        Some(value) => Some(value.to_string()),
+        None if pos < args.len() => Some(args.remove(pos)),
+        None => None,
+    }
+}
+
+/// Removes every occurrence of the given flags from `args`, returning
+/// whether any was present.
+fn take_flag(args: &mut Vec<String>, flags: &[&str]) -> bool {
+    let before = args.len();
+    args.retain(|a| !flags.contains(&a.as_str()));
+    args.len() != before
+}
 
 fn main() {
+    let mut args: Vec<String> = std::env::args().collect();
+    if let Some(path) = take_arg_value(&mut args, "--config") {
+        settings::set_config_override(PathBuf::from(path));
+    }
+    if let Some(name) = take_arg_value(&mut args, "--profile") {
+        settings::set_config_profile(name);
+    }
+    let hidden_flag = take_flag(&mut args, &["--hidden", "--no-window"]);
+    if take_flag(&mut args, &["--refresh-capabilities"]) {
+        settings::set_refresh_capabilities();
+    }
+
+    if let Some(code) = cli::run(&args[1..]) {
+        std::process::exit(code);
+    }
+
     let application = Application::builder()
         .application_id("com.brightless.app")
         .build();
 
-    application.connect_activate(move |app| match MainWindow::new(app) {
+    application.connect_startup(|_| {
+        adw::StyleManager::default().set_color_scheme(AppSettings::load().color_scheme.to_adw());
+    });
+
+    // Only the first activation may start hidden; later ones (launching the
+    // app again while it runs) show the existing window.
+    let start_hidden = Cell::new(hidden_flag || AppSettings::load().start_hidden);
+    application.connect_activate(move |app| {
+        if let Some(window) = app.windows().first() {
+            window.present();
+            return;
+        }
+        build_window(app, start_hidden.replace(false));
+    });
+
+    application.run_with_args(&args);
+}
+
+/// Probes for monitors on a worker thread, showing a loading window in the
+/// meantime unless starting hidden, then builds the main window.
+fn build_window(app: &Application, hidden: bool) {
+    let loading = (!hidden).then(|| loading_window(app));
+    if let Some(window) = &loading {
+        window.present();
+    }
+    // Nothing else keeps a hidden application alive until its window exists.
+    let hold = app.hold();
+    let app = app.clone();
+    let options = AppSettings::load().ddc_options();
+    glib::MainContext::default().spawn_local(async move {
+        let discovery = gio::spawn_blocking(move || DdcManager::discover(&options))
+            .await
+            .unwrap_or_else(|_| {
+                Err(ddc_manager::DdcError::CommError(
+                    "Monitor discovery stopped unexpectedly".to_string(),
+                ))
+            });
+        // Closing the loading window gives up on starting.
+        if loading.as_ref().is_some_and(|window| !window.is_visible()) {
+            return;
+        }
+        show_window(&app, discovery, hidden);
+        if let Some(window) = loading {
+            window.close();
+        }
+        drop(hold);
+    });
+}
+
+fn show_window(
+    app: &Application,
+    discovery: Result<ddc_manager::Discovery, ddc_manager::DdcError>,
+    hidden: bool,
+) {
+    match discovery.map(|discovery| MainWindow::new(app, DdcManager::from_discovery(discovery))) {
         Ok(window) => {
             window.init_brightness();
-            window.window.present();
+            window.show_active_profile();
+            window.apply_startup_profile();
+            window.suggest_night_cap();
+            if !AppSettings::is_persistent() {
+                window.notify("No config directory found; settings will not be saved");
+            }
+            if hidden {
+                // The application stays alive while it has a window, so
+                // schedules and polling keep running; closing the window
+                // later hides it again instead of quitting.
+                window.window.set_hide_on_close(true);
+            } else {
+                window.window.present();
+            }
             std::mem::forget(window);
         }
         Err(e) => {
             eprintln!("Failed to initialize: {}", e);
-            let window = adw::ApplicationWindow::new(app);
-            window.set_title(Some("Error"));
-            window.set_default_size(300, 100);
-
-            let label = gtk::Label::new(Some(&format!("Error: {}", e)));
-            label.set_margin_start(20);
-            label.set_margin_end(20);
-            label.set_margin_top(20);
-            label.set_margin_bottom(20);
-            window.set_child(Some(&label));
-            window.present();
+            error_window(app, &e).present();
         }
+    }
+}
+
+/// Shown while startup discovery probes the I2C buses, which can take a
+/// few seconds on machines with many of them.
+fn loading_window(app: &Application) -> adw::ApplicationWindow {
+    let window = adw::ApplicationWindow::new(app);
+    window.set_title(Some("Brightless"));
+    window.set_default_size(480, 420);
+
+    let status_page = adw::StatusPage::new();
+    status_page.set_title("Looking for Monitors");
+    status_page.set_paintable(Some(&adw::SpinnerPaintable::new(Some(&status_page))));
+
+    let toolbar_view = adw::ToolbarView::new();
+    toolbar_view.add_top_bar(&adw::HeaderBar::new());
+    toolbar_view.set_content(Some(&status_page));
+    window.set_content(Some(&toolbar_view));
+    window
+}
+
+/// Explains why no window could be built, with a Retry button for problems
+/// that pass, like a monitor still waking up.
+fn error_window(app: &Application, error: &ddc_manager::DdcError) -> adw::ApplicationWindow {
+    let window = adw::ApplicationWindow::new(app);
+    window.set_title(Some("Brightless"));
+    window.set_default_size(480, 420);
+
+    let status_page = adw::StatusPage::new();
+    status_page.set_icon_name(Some("video-display-symbolic"));
+    status_page.set_title("Couldn't Start Brightless");
+    status_page.set_description(Some(&gtk::glib::markup_escape_text(&error.to_string())));
+
+    let content = gtk::Box::new(gtk::Orientation::Vertical, 18);
+    if let ddc_manager::DdcError::NoneDiscovered(report) = error {
+        let label = gtk::Label::new(Some(&report.to_string()));
+        label.set_wrap(true);
+        label.set_selectable(true);
+        label.set_xalign(0.0);
+        content.append(&label);
+    }
+
+    let buttons = gtk::Box::new(gtk::Orientation::Horizontal, 12);
+    buttons.set_halign(gtk::Align::Center);
+    let quit_button = gtk::Button::with_label("Quit");
+    quit_button.add_css_class("pill");
+    let retry_button = gtk::Button::with_label("Retry");
+    retry_button.add_css_class("pill");
+    retry_button.add_css_class("suggested-action");
+    buttons.append(&quit_button);
+    buttons.append(&retry_button);
+    content.append(&buttons);
+    status_page.set_child(Some(&content));
+
+    let window_quit = window.clone();
+    quit_button.connect_clicked(move |_| window_quit.close());
+    let app_retry = app.clone();
+    let window_retry = window.clone();
+    retry_button.connect_clicked(move |_| {
+        // The new window keeps the application alive while this one closes.
+        build_window(&app_retry, false);
+        window_retry.close();
     });
 
-    application.run();
+    let toolbar_view = adw::ToolbarView::new();
+    toolbar_view.add_top_bar(&adw::HeaderBar::new());
+    toolbar_view.set_content(Some(&status_page));
+    window.set_content(Some(&toolbar_view));
+    window
 }
@@ -1,17 +1,392 @@
+use crate::ddc_manager::{
+    percentage_to_raw, BrightnessCurve, ColorChannel, ColorTemperatureControl, InputSource,
+    PowerMode, MAX_KELVIN, MIN_KELVIN,
+};
+use crate::settings::{CustomControl, CustomControlKind};
 use adw::prelude::*;
-use adw::ActionRow;
+use adw::{ActionRow, ExpanderRow, PreferencesRow};
 use glib::Propagation;
 use gtk::{
-    Box, ComboBoxText, EventControllerScroll, EventControllerScrollFlags, Label, Orientation, Scale,
-    Switch,
+    gdk, Box, Button, ComboBoxText, EventControllerLegacy, EventControllerMotion,
+    EventControllerScroll, EventControllerScrollFlags, Expander, Label, MenuButton, Orientation,
+    Popover, Scale, ScrollType, ScrolledWindow, Switch, ToggleButton,
 };
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::rc::Rc;
 
+/// Shared flag that suppresses change callbacks while widgets are updated
+/// programmatically, so values read from a monitor are not written back.
+#[derive(Debug, Clone, Default)]
+struct UpdateGuard(Rc<Cell<bool>>);
+
+impl UpdateGuard {
+    fn suppress<F: FnOnce()>(&self, f: F) {
+        let was_updating = self.0.replace(true);
+        f();
+        self.0.set(was_updating);
+    }
+
+    fn is_updating(&self) -> bool {
+        self.0.get()
+    }
+}
+
+/// How long the pointer has to stay on a row before `connect_hover` fires,
+/// so passing over rows on the way elsewhere doesn't flash monitors.
+const HOVER_DELAY_MS: u64 = 500;
+
+/// Scroll distance in surface pixels that moves a slider by one
+/// `scroll_step` when scrolling with a touchpad.
+const SMOOTH_SCROLL_PIXELS_PER_STEP: f64 = 20.0;
+
+/// Explains how the 0-100% slider maps onto a hardware range that does not
+/// start at zero; `None` for the usual `0..=max`.
+fn range_tooltip(min: u16, max: u16) -> Option<String> {
+    (min > 0).then(|| format!("0–100% covers the monitor's range {}–{}", min, max))
+}
+
+/// Keeps `scale`'s tooltip on the raw hardware range it drives and the raw
+/// value at its current position, e.g. "Brightness 0–100 raw, current 62".
+fn add_calibration_tooltip<F>(scale: &Scale, title: &'static str, min: u16, max: u16, to_raw: F)
+where
+    F: Fn(f64) -> u16 + 'static,
+{
+    let tooltip =
+        move |value: f64| format!("{} {}–{} raw, current {}", title, min, max, to_raw(value));
+    scale.set_tooltip_text(Some(&tooltip(scale.value())));
+    let scale_tooltip = scale.clone();
+    scale.adjustment().connect_value_changed(move |adj| {
+        scale_tooltip.set_tooltip_text(Some(&tooltip(adj.value())));
+    });
+}
+
+/// `(id, label)` entries of the input source combo. Ids are the decimal
+/// VCP codes, as passed to `set_input_source`.
+fn input_source_choices() -> Vec<(String, String)> {
+    InputSource::ALL
+        .iter()
+        .map(|s| (s.code().to_string(), s.name().to_string()))
+        .collect()
+}
+
+/// `(id, label)` entries of the power mode combo, like
+/// `input_source_choices`.
+fn power_mode_choices() -> Vec<(String, String)> {
+    PowerMode::ALL
+        .iter()
+        .map(|m| (m.code().to_string(), m.name().to_string()))
+        .collect()
+}
+
+/// How close, in percent, a drag or click has to land to a brightness mark
+/// to snap onto it.
+const MARK_SNAP_DISTANCE: f64 = 2.0;
+
+/// The mark within `MARK_SNAP_DISTANCE` of `value`, if any.
+fn nearest_mark(value: f64, marks: &[u8]) -> Option<u8> {
+    marks
+        .iter()
+        .copied()
+        .filter(|&m| (value - m as f64).abs() <= MARK_SNAP_DISTANCE)
+        .min_by(|&a, &b| {
+            (value - a as f64)
+                .abs()
+                .total_cmp(&(value - b as f64).abs())
+        })
+}
+
+/// The raw brightness range the brightness sliders span, one position
+/// per value the monitor accepts, and the curve that turns positions into
+/// the percentages labels show.
+#[derive(Debug, Clone)]
+struct BrightnessRange {
+    min: u16,
+    max: u16,
+    curve: Rc<Cell<BrightnessCurve>>,
+}
+
+impl BrightnessRange {
+    /// Monitors that report no usable range get 0–100.
+    fn new(min: u16, max: u16) -> Self {
+        let (min, max) = if max > min { (min, max) } else { (0, 100) };
+        Self {
+            min,
+            max,
+            curve: Rc::new(Cell::new(BrightnessCurve::Linear)),
+        }
+    }
+
+    fn percentage(&self, position: f64) -> u8 {
+        let raw = (position.round() as u16).clamp(self.min, self.max);
+        self.curve.get().raw_to_percentage(raw, self.min, self.max)
+    }
+
+    /// The lowest raw value shown as `percentage`, so a percentage read
+    /// back from the monitor puts the slider where the monitor is.
+    fn position(&self, percentage: u8) -> f64 {
+        let curve = self.curve.get();
+        let (mut low, mut high) = (self.min, self.max);
+        while low < high {
+            let middle = low + (high - low) / 2;
+            if curve.raw_to_percentage(middle, self.min, self.max) < percentage {
+                low = middle + 1;
+            } else {
+                high = middle;
+            }
+        }
+        low as f64
+    }
+
+    /// Where a step of `percent` from `current` lands: the position for the
+    /// percentage that far away, but at least one raw value from `current`,
+    /// so a step always reaches another level the monitor can show.
+    fn step(&self, current: f64, percent: f64) -> f64 {
+        let target = (self.percentage(current) as f64 + percent).clamp(0.0, 100.0);
+        let position = self.position(target as u8);
+        let position = if percent > 0.0 && position <= current {
+            current + 1.0
+        } else if percent < 0.0 && position >= current {
+            current - 1.0
+        } else {
+            position
+        };
+        position.clamp(self.min as f64, self.max as f64)
+    }
+}
+
+/// Marks saved brightness percentages on `scale` above the trough.
+fn add_brightness_marks(scale: &Scale, marks: &[u8], range: &BrightnessRange) {
+    for &mark in marks {
+        scale.add_mark(range.position(mark), gtk::PositionType::Top, None);
+    }
+}
+
+/// The mark a change of kind `scroll` to `value` snaps to. Only clicks and
+/// drags snap; key presses step off a mark instead of landing back on it.
+fn snap_target(scroll: ScrollType, value: f64, marks: &[u8]) -> Option<u8> {
+    if scroll == ScrollType::Jump {
+        nearest_mark(value, marks)
+    } else {
+        None
+    }
+}
+
+/// Adds `marks` to `scale` and snaps clicks and drags that land near one.
+fn add_snapping_marks(scale: &Scale, marks: &[u8], range: &BrightnessRange) {
+    add_brightness_marks(scale, marks, range);
+    let marks = marks.to_vec();
+    let range = range.clone();
+    scale.connect_change_value(move |scale, scroll, value| {
+        match snap_target(scroll, range.percentage(value) as f64, &marks) {
+            Some(mark) => {
+                scale.set_value(range.position(mark));
+                Propagation::Stop
+            }
+            None => Propagation::Proceed,
+        }
+    });
+}
+
+/// Shows brightness percentages, not raw positions, when `scale` draws
+/// its value.
+fn add_percentage_format(scale: &Scale, range: &BrightnessRange) {
+    let range = range.clone();
+    scale.set_format_value_func(move |_, value| range.percentage(value).to_string());
+}
+
+/// Turns scroll deltas into whole-percent slider changes, carrying the
+/// fractional remainder between events. The step is shared with the settings
+/// popover so changes apply to existing rows immediately.
+#[derive(Debug, Clone)]
+struct ScrollAccumulator {
+    step: Rc<Cell<u8>>,
+    pending: Rc<Cell<f64>>,
+}
+
+impl ScrollAccumulator {
+    fn new(step: Rc<Cell<u8>>) -> Self {
+        Self {
+            step,
+            pending: Rc::new(Cell::new(0.0)),
+        }
+    }
+
+    /// Adds `notches` of scrolling (negative is up) and returns the whole
+    /// number of percent to move the slider by.
+    fn consume(&self, notches: f64) -> f64 {
+        let total = self.pending.get() - notches * self.step.get() as f64;
+        let whole = total.trunc();
+        self.pending.set(total - whole);
+        whole
+    }
+
+    fn reset(&self) {
+        self.pending.set(0.0);
+    }
+}
+
+/// Adds a scroll controller to `scale`. Mouse wheel notches move the value
+/// by exactly `scroll_step`; smooth scrolling from touchpads is accumulated
+/// and applied in proportion to the distance scrolled. Devices that only
+/// scroll sideways work too, scrolling right raising the value. Brightness
+/// sliders pass their `range`, stepping by percent over raw positions and
+/// always reaching the next level the monitor can show; other sliders step
+/// in their own units. `label`, if given, shows the new percentage.
+/// While `enabled` is off the scale ignores scrolling, which scrolls the
+/// list around it instead.
+fn add_scroll_controller(
+    scale: &Scale,
+    label: Option<&Label>,
+    scroll_step: &Rc<Cell<u8>>,
+    enabled: &Rc<Cell<bool>>,
+    range: Option<&BrightnessRange>,
+) {
+    // Runs in the capture phase to keep the scale's own scroll handling
+    // from seeing the event too.
+    let passthrough = EventControllerScroll::new(EventControllerScrollFlags::VERTICAL);
+    passthrough.set_propagation_phase(gtk::PropagationPhase::Capture);
+    let scale_pass = scale.clone();
+    let enabled = enabled.clone();
+    passthrough.connect_scroll(move |controller, _dx, dy| {
+        if enabled.get() {
+            return Propagation::Proceed;
+        }
+        if let Some(scrolled) = scale_pass
+            .ancestor(ScrolledWindow::static_type())
+            .and_downcast::<ScrolledWindow>()
+        {
+            let adjustment = scrolled.vadjustment();
+            // The distance GTK scrolls a list by per wheel notch.
+            let distance = match controller.unit() {
+                gdk::ScrollUnit::Surface => dy,
+                _ => dy * adjustment.page_size().powf(2.0 / 3.0),
+            };
+            adjustment.set_value(adjustment.value() + distance);
+        }
+        Propagation::Stop
+    });
+    scale.add_controller(passthrough);
+
+    let accumulator = ScrollAccumulator::new(scroll_step.clone());
+    let controller = EventControllerScroll::new(EventControllerScrollFlags::BOTH_AXES);
+
+    let scale_scroll = scale.clone();
+    let label_scroll = label.cloned();
+    let accumulator_scroll = accumulator.clone();
+    let range = range.cloned();
+    controller.connect_scroll(move |controller, dx, dy| {
+        // Locked or read-only scales keep their value.
+        if !scale_scroll.is_sensitive() {
+            return Propagation::Proceed;
+        }
+        let delta = if dy != 0.0 { dy } else { -dx };
+        let notches = match controller.unit() {
+            gdk::ScrollUnit::Surface => delta / SMOOTH_SCROLL_PIXELS_PER_STEP,
+            _ => delta,
+        };
+        let whole = accumulator_scroll.consume(notches);
+        if whole != 0.0 {
+            let current = scale_scroll.value();
+            let (new_value, percentage) = match &range {
+                Some(range) => {
+                    let position = range.step(current, whole);
+                    (position, range.percentage(position))
+                }
+                None => {
+                    let adjustment = scale_scroll.adjustment();
+                    let value = (current + whole).clamp(adjustment.lower(), adjustment.upper());
+                    (value, value as u8)
+                }
+            };
+            scale_scroll.set_value(new_value);
+            if let Some(label) = &label_scroll {
+                label.set_text(&format!("{}%", percentage));
+            }
+        }
+        Propagation::Proceed
+    });
+    controller.connect_scroll_end(move |_| accumulator.reset());
+    scale.add_controller(controller);
+}
+
+/// Calls `callback` with each position the user gives `scale`. With `live`
+/// off, a drag is reported once, with its final value, when the pointer or
+/// finger is released; scroll and keyboard steps are reported as they
+/// happen.
+fn connect_user_value<F>(scale: &Scale, updating: &UpdateGuard, live: &Rc<Cell<bool>>, callback: F)
+where
+    F: Fn(f64) + 'static,
+{
+    let callback = Rc::new(callback);
+    let dragging = Rc::new(Cell::new(false));
+
+    // Runs in the capture phase, so it sees the release even though the
+    // scale's own drag gesture claims the sequence.
+    let controller = EventControllerLegacy::new();
+    controller.set_propagation_phase(gtk::PropagationPhase::Capture);
+    let scale_release = scale.clone();
+    let dragging_event = dragging.clone();
+    let live_release = live.clone();
+    let callback_release = callback.clone();
+    controller.connect_event(move |_, event| {
+        match event.event_type() {
+            gdk::EventType::ButtonPress | gdk::EventType::TouchBegin => dragging_event.set(true),
+            gdk::EventType::ButtonRelease
+            | gdk::EventType::TouchEnd
+            | gdk::EventType::TouchCancel => {
+                let was_dragging = dragging_event.replace(false);
+                if was_dragging && !live_release.get() {
+                    callback_release(scale_release.value());
+                }
+            }
+            _ => {}
+        }
+        Propagation::Proceed
+    });
+    scale.add_controller(controller);
+
+    let updating = updating.clone();
+    let live = live.clone();
+    scale.adjustment().connect_value_changed(move |adj| {
+        if !updating.is_updating() && (live.get() || !dragging.get()) {
+            callback(adj.value());
+        }
+    });
+}
+
+#[derive(Debug)]
+enum CustomWidget {
+    Dropdown(ComboBoxText),
+    Slider(Scale),
+}
+
+/// Calls `callback` when the user flips `switch`, and puts the switch back
+/// when it returns false, so it never shows a state the monitor refused.
+fn connect_reverting_switch<F>(switch: &Switch, updating: &UpdateGuard, callback: F)
+where
+    F: Fn(bool) -> bool + 'static,
+{
+    let updating = updating.clone();
+    switch.connect_state_set(move |switch, state| {
+        if updating.is_updating() || callback(state) {
+            return Propagation::Proceed;
+        }
+        // The nested emission moves the state back; stopping this one
+        // keeps it from being set to the refused value afterwards.
+        updating.suppress(|| switch.set_active(!state));
+        Propagation::Stop
+    });
+}
+
 #[derive(Debug)]
 pub struct MonitorRow {
-    pub container: ActionRow,
+    /// An `ActionRow`, or an `ExpanderRow` holding the secondary controls
+    /// in compact mode.
+    pub container: PreferencesRow,
     pub name: String,
+    /// Key of the monitor's per-monitor settings, from
+    /// `Monitor::settings_key`.
+    pub settings_key: String,
     pub brightness_scale: Scale,
     pub brightness_label: Label,
     pub contrast_scale: Option<Scale>,
@@ -20,9 +395,37 @@ pub struct MonitorRow {
     pub volume_label: Option<Label>,
     pub input_source_combo: Option<ComboBoxText>,
     pub power_mode_combo: Option<ComboBoxText>,
+    /// On when the monitor's OSD is locked; insensitive when the monitor
+    /// only reports the state.
+    pub osd_lock_switch: Option<Switch>,
+    /// On while the monitor shows inverted colors.
+    pub invert_switch: Option<Switch>,
+    /// User-defined controls by VCP code.
+    custom_controls: Vec<(u8, CustomWidget)>,
+    /// Manufacturer on/off features by VCP code.
+    vendor_switches: Vec<(u8, Switch)>,
     pub dynamic_contrast_scale: Option<Scale>,
     pub dynamic_contrast_label: Option<Label>,
     pub dynamic_contrast_toggle: Option<Switch>,
+    identify_button: Button,
+    favorite_button: ToggleButton,
+    asleep_label: Label,
+    asleep: Rc<Cell<bool>>,
+    read_only_label: Label,
+    read_only: Rc<Cell<bool>>,
+    /// Set while controls are locked against accidental changes.
+    locked: Rc<Cell<bool>>,
+    osd_lock_writable: bool,
+    /// Whether sliders write while being dragged rather than on release.
+    live_update: Rc<Cell<bool>>,
+    /// Saved brightness percentages marked on the brightness sliders.
+    brightness_marks: Vec<u8>,
+    brightness_range: BrightnessRange,
+    high_contrast: Cell<bool>,
+    rgb_bias_controls: Vec<(ColorChannel, Scale, Label)>,
+    /// Color temperature in hundreds of Kelvin.
+    color_temperature_scale: Option<Scale>,
+    color_temperature_label: Option<Label>,
     brightness_row: Box,
     contrast_row: Option<Box>,
     dynamic_contrast_row: Option<Box>,
@@ -30,31 +433,50 @@ pub struct MonitorRow {
     contrast_label_inner: Option<Rc<RefCell<Label>>>,
     volume_label_inner: Option<Rc<RefCell<Label>>>,
     dynamic_contrast_label_inner: Option<Rc<RefCell<Label>>>,
+    updating: UpdateGuard,
 }
 
 impl MonitorRow {
     pub fn new(
         name: String,
-        _min_brightness: u16,
-        _max_brightness: u16,
-        _min_contrast: u16,
+        settings_key: String,
+        min_brightness: u16,
+        max_brightness: u16,
+        min_contrast: u16,
         max_contrast: u16,
-        _min_volume: u16,
+        min_volume: u16,
         max_volume: u16,
         supports_input_source: bool,
         supports_power_mode: bool,
-        scroll_step: u8,
+        supports_osd_lock: bool,
+        osd_lock_writable: bool,
+        supports_invert: bool,
+        scroll_step: Rc<Cell<u8>>,
+        scroll_enabled: Rc<Cell<bool>>,
         dynamic_contrast_enabled: bool,
         dynamic_contrast_global: bool,
         _dynamic_contrast_ratio: f32,
+        compact: bool,
+        rgb_bias_channels: &[ColorChannel],
+        color_temperature: &ColorTemperatureControl,
+        brightness_marks: &[u8],
+        custom_controls: &[(&CustomControl, u16)],
+        vendor_toggles: &[u8],
     ) -> Self {
         let brightness_scale = Scale::builder()
             .orientation(Orientation::Horizontal)
             .hexpand(true)
             .build();
-        brightness_scale.set_range(0.0, 100.0);
+        let brightness_range = BrightnessRange::new(min_brightness, max_brightness);
+        // About 1% per key press, but never less than one raw step.
+        let brightness_step = ((brightness_range.max - brightness_range.min) as f64 / 100.0)
+            .round()
+            .max(1.0);
+        brightness_scale.set_range(brightness_range.min as f64, brightness_range.max as f64);
+        brightness_scale.set_increments(brightness_step, brightness_step * 10.0);
         brightness_scale.set_digits(0);
         brightness_scale.set_draw_value(false);
+        add_percentage_format(&brightness_scale, &brightness_range);
 
         let brightness_label = Label::new(Some("50%"));
         brightness_label.set_width_chars(5);
@@ -62,30 +484,27 @@ impl MonitorRow {
 
         let brightness_label_inner = Rc::new(RefCell::new(brightness_label.clone()));
 
-        // Add scroll controller for brightness slider
-        let brightness_label_scroll = brightness_label_inner.clone();
-        let brightness_scale_scroll = brightness_scale.clone();
-        let brightness_scroll_controller =
-            EventControllerScroll::new(EventControllerScrollFlags::VERTICAL);
-        brightness_scroll_controller.connect_scroll(move |_, _dx, dy| {
-            let current = brightness_scale_scroll.value();
-            let step = scroll_step as f64;
-            let new_value = if dy < 0.0 {
-                (current + step).min(100.0)
-            } else {
-                (current - step).max(0.0)
-            };
-            brightness_scale_scroll.set_value(new_value);
-            brightness_label_scroll
-                .borrow()
-                .set_text(&format!("{}%", new_value as u8));
-            Propagation::Proceed
-        });
-        brightness_scale.add_controller(brightness_scroll_controller);
+        add_scroll_controller(
+            &brightness_scale,
+            Some(&brightness_label),
+            &scroll_step,
+            &scroll_enabled,
+            Some(&brightness_range),
+        );
+        add_snapping_marks(&brightness_scale, brightness_marks, &brightness_range);
+        add_calibration_tooltip(
+            &brightness_scale,
+            "Brightness",
+            min_brightness,
+            max_brightness,
+            |position| position as u16,
+        );
 
         let brightness_row = Box::new(Orientation::Horizontal, 8);
         let brightness_label_text = Label::new(Some("Brightness:"));
         brightness_label_text.set_width_chars(12);
+        brightness_label_text
+            .set_tooltip_text(range_tooltip(min_brightness, max_brightness).as_deref());
         brightness_row.append(&brightness_label_text);
         brightness_row.append(&brightness_scale);
         brightness_row.append(&brightness_label);
@@ -104,32 +523,18 @@ impl MonitorRow {
             label.set_width_chars(5);
             label.set_halign(gtk::Align::End);
 
-            // Add scroll controller for contrast slider
-            let contrast_label_scroll = Rc::new(RefCell::new(label.clone()));
-            let contrast_scale_scroll = scale.clone();
-            let contrast_scroll_controller =
-                EventControllerScroll::new(EventControllerScrollFlags::VERTICAL);
-            contrast_scroll_controller.connect_scroll(move |_, _dx, dy| {
-                let current = contrast_scale_scroll.value();
-                let step = scroll_step as f64;
-                let new_value = if dy < 0.0 {
-                    (current + step).min(100.0)
-                } else {
-                    (current - step).max(0.0)
-                };
-                contrast_scale_scroll.set_value(new_value);
-                contrast_label_scroll
-                    .borrow()
-                    .set_text(&format!("{}%", new_value as u8));
-                Propagation::Proceed
+            add_scroll_controller(&scale, Some(&label), &scroll_step, &scroll_enabled, None);
+            add_calibration_tooltip(&scale, "Contrast", min_contrast, max_contrast, move |p| {
+                percentage_to_raw(p as u8, min_contrast, max_contrast)
             });
-            scale.add_controller(contrast_scroll_controller);
 
             let label_inner = Rc::new(RefCell::new(label.clone()));
 
             let row = Box::new(Orientation::Horizontal, 8);
             let contrast_label_text = Label::new(Some("Contrast:"));
             contrast_label_text.set_width_chars(12);
+            contrast_label_text
+                .set_tooltip_text(range_tooltip(min_contrast, max_contrast).as_deref());
             row.append(&contrast_label_text);
             row.append(&scale);
             row.append(&label);
@@ -146,32 +551,31 @@ impl MonitorRow {
                 .orientation(Orientation::Horizontal)
                 .hexpand(true)
                 .build();
-            scale.set_range(0.0, 100.0);
+            scale.set_range(brightness_range.min as f64, brightness_range.max as f64);
+            scale.set_increments(brightness_step, brightness_step * 10.0);
             scale.set_digits(0);
             scale.set_draw_value(false);
+            add_percentage_format(&scale, &brightness_range);
 
             let label = Label::new(Some("50%"));
             label.set_width_chars(5);
             label.set_halign(gtk::Align::End);
 
-            let dc_label_scroll = Rc::new(RefCell::new(label.clone()));
-            let dc_scale_scroll = scale.clone();
-            let dc_scroll_controller = EventControllerScroll::new(EventControllerScrollFlags::VERTICAL);
-            dc_scroll_controller.connect_scroll(move |_, _dx, dy| {
-                let current = dc_scale_scroll.value();
-                let step = scroll_step as f64;
-                let new_value = if dy < 0.0 {
-                    (current + step).min(100.0)
-                } else {
-                    (current - step).max(0.0)
-                };
-                dc_scale_scroll.set_value(new_value);
-                dc_label_scroll
-                    .borrow()
-                    .set_text(&format!("{}%", new_value as u8));
-                Propagation::Proceed
-            });
-            scale.add_controller(dc_scroll_controller);
+            add_scroll_controller(
+                &scale,
+                Some(&label),
+                &scroll_step,
+                &scroll_enabled,
+                Some(&brightness_range),
+            );
+            add_snapping_marks(&scale, brightness_marks, &brightness_range);
+            add_calibration_tooltip(
+                &scale,
+                "Brightness",
+                min_brightness,
+                max_brightness,
+                |position| position as u16,
+            );
 
             let label_inner = Rc::new(RefCell::new(label.clone()));
 
@@ -206,13 +610,20 @@ impl MonitorRow {
         };
 
         let main_box = Box::new(Orientation::Vertical, 0);
+        // In compact mode only the brightness controls stay in the row
+        // header; everything else goes behind the expander.
+        let extras_box = if compact {
+            Box::new(Orientation::Vertical, 0)
+        } else {
+            main_box.clone()
+        };
 
         if let Some((ref row, _)) = dc_toggle_row {
-            main_box.append(row);
+            extras_box.append(row);
         }
         main_box.append(&brightness_row);
         if let Some(ref row) = contrast_row {
-            main_box.append(row);
+            extras_box.append(row);
         }
         if let Some(ref row) = dynamic_contrast_row {
             main_box.append(row);
@@ -232,25 +643,10 @@ impl MonitorRow {
             label.set_width_chars(5);
             label.set_halign(gtk::Align::End);
 
-            let volume_label_scroll = Rc::new(RefCell::new(label.clone()));
-            let volume_scale_scroll = scale.clone();
-            let volume_scroll_controller =
-                EventControllerScroll::new(EventControllerScrollFlags::VERTICAL);
-            volume_scroll_controller.connect_scroll(move |_, _dx, dy| {
-                let current = volume_scale_scroll.value();
-                let step = scroll_step as f64;
-                let new_value = if dy < 0.0 {
-                    (current + step).min(100.0)
-                } else {
-                    (current - step).max(0.0)
-                };
-                volume_scale_scroll.set_value(new_value);
-                volume_label_scroll
-                    .borrow()
-                    .set_text(&format!("{}%", new_value as u8));
-                Propagation::Proceed
+            add_scroll_controller(&scale, Some(&label), &scroll_step, &scroll_enabled, None);
+            add_calibration_tooltip(&scale, "Volume", min_volume, max_volume, move |p| {
+                percentage_to_raw(p as u8, min_volume, max_volume)
             });
-            scale.add_controller(volume_scroll_controller);
 
             let label_inner = Rc::new(RefCell::new(label.clone()));
 
@@ -262,26 +658,21 @@ impl MonitorRow {
         if let (Some(v_scale), Some(v_label)) = (&volume_scale, &volume_label) {
             let volume_row = Box::new(Orientation::Horizontal, 8);
             let volume_label_text = Label::new(Some("Volume:"));
+            volume_label_text.set_tooltip_text(range_tooltip(min_volume, max_volume).as_deref());
             volume_label_text.set_width_chars(12);
             volume_row.append(&volume_label_text);
             volume_row.append(v_scale);
             volume_row.append(v_label);
             volume_row.set_margin_top(8);
             volume_row.set_margin_bottom(8);
-            main_box.append(&volume_row);
+            extras_box.append(&volume_row);
         }
 
         let input_source_combo = if supports_input_source {
             let combo = ComboBoxText::new();
-            combo.append(Some("1"), "VGA");
-            combo.append(Some("3"), "DVI");
-            combo.append(Some("15"), "DisplayPort 1");
-            combo.append(Some("16"), "DisplayPort 2");
-            combo.append(Some("17"), "HDMI 1");
-            combo.append(Some("18"), "HDMI 2");
-            combo.append(Some("19"), "HDMI 3");
-            combo.append(Some("20"), "HDMI 4");
-            combo.append(Some("27"), "USB-C");
+            for (id, label) in input_source_choices() {
+                combo.append(Some(&id), &label);
+            }
             Some(combo)
         } else {
             None
@@ -289,11 +680,9 @@ impl MonitorRow {
 
         let power_mode_combo = if supports_power_mode {
             let combo = ComboBoxText::new();
-            combo.append(Some("1"), "On");
-            combo.append(Some("2"), "Standby");
-            combo.append(Some("3"), "Suspend");
-            combo.append(Some("4"), "Off");
-            combo.append(Some("5"), "Normal");
+            for (id, label) in power_mode_choices() {
+                combo.append(Some(&id), &label);
+            }
             Some(combo)
         } else {
             None
@@ -317,7 +706,222 @@ impl MonitorRow {
                 controls_row.append(combo);
             }
 
-            main_box.append(&controls_row);
+            extras_box.append(&controls_row);
+        }
+
+        let osd_lock_switch = if supports_osd_lock {
+            let switch = Switch::new();
+            switch.set_valign(gtk::Align::Center);
+            switch.set_sensitive(osd_lock_writable);
+            let osd_row = Box::new(Orientation::Horizontal, 8);
+            osd_row.set_margin_top(8);
+            let osd_label = Label::new(Some("OSD Lock:"));
+            osd_label.set_width_chars(12);
+            osd_label.set_halign(gtk::Align::Start);
+            if osd_lock_writable {
+                osd_label.set_tooltip_text(Some("Disable the monitor's on-screen menu"));
+            } else {
+                osd_label.set_tooltip_text(Some(
+                    "Reported by the monitor for information; it can't be changed over DDC/CI",
+                ));
+            }
+            osd_row.append(&osd_label);
+            osd_row.append(&switch);
+            extras_box.append(&osd_row);
+            Some(switch)
+        } else {
+            None
+        };
+
+        let invert_switch = if supports_invert {
+            let switch = Switch::new();
+            switch.set_valign(gtk::Align::Center);
+            let invert_row = Box::new(Orientation::Horizontal, 8);
+            invert_row.set_margin_top(8);
+            let invert_label = Label::new(Some("Invert:"));
+            invert_label.set_width_chars(12);
+            invert_label.set_halign(gtk::Align::Start);
+            invert_label.set_tooltip_text(Some("Show the image with inverted colors"));
+            invert_row.append(&invert_label);
+            invert_row.append(&switch);
+            extras_box.append(&invert_row);
+            Some(switch)
+        } else {
+            None
+        };
+
+        // Callers only pass controls whose code parsed and was answered.
+        let custom_controls: Vec<(u8, CustomWidget)> = custom_controls
+            .iter()
+            .filter_map(|&(control, max)| Some((control, control.code()?, max)))
+            .map(|(control, code, max)| {
+                let custom_row = Box::new(Orientation::Horizontal, 8);
+                custom_row.set_margin_top(8);
+                let custom_label = Label::new(Some(&format!("{}:", control.name)));
+                custom_label.set_width_chars(12);
+                custom_label.set_halign(gtk::Align::Start);
+                custom_label.set_tooltip_text(Some(&format!("VCP 0x{:02X}", code)));
+                custom_row.append(&custom_label);
+                let widget = match control.kind {
+                    CustomControlKind::Dropdown => {
+                        let combo = ComboBoxText::new();
+                        for value in &control.values {
+                            combo.append(Some(&value.code.to_string()), &value.label);
+                        }
+                        custom_row.append(&combo);
+                        CustomWidget::Dropdown(combo)
+                    }
+                    CustomControlKind::Slider => {
+                        let scale = Scale::builder()
+                            .orientation(Orientation::Horizontal)
+                            .hexpand(true)
+                            .build();
+                        scale.set_range(0.0, max as f64);
+                        scale.set_increments(1.0, 1.0);
+                        scale.set_digits(0);
+                        scale.set_draw_value(false);
+                        let label = Label::new(Some("0"));
+                        label.set_width_chars(5);
+                        label.set_halign(gtk::Align::End);
+                        let label_value = label.clone();
+                        scale.adjustment().connect_value_changed(move |adj| {
+                            label_value.set_text(&(adj.value() as u16).to_string());
+                        });
+                        add_scroll_controller(&scale, None, &scroll_step, &scroll_enabled, None);
+                        custom_row.append(&scale);
+                        custom_row.append(&label);
+                        CustomWidget::Slider(scale)
+                    }
+                };
+                extras_box.append(&custom_row);
+                (code, widget)
+            })
+            .collect();
+
+        let calibration_box = Box::new(Orientation::Vertical, 0);
+
+        // The scale counts in hundreds of Kelvin, so it shares the 0-255
+        // value handling of the percentage sliders.
+        let (color_temperature_scale, color_temperature_label) = match color_temperature {
+            ColorTemperatureControl::Unsupported => (None, None),
+            control => {
+                let scale = Scale::builder()
+                    .orientation(Orientation::Horizontal)
+                    .hexpand(true)
+                    .build();
+                scale.set_digits(0);
+                scale.set_draw_value(false);
+                match control {
+                    ColorTemperatureControl::Presets(presets) => {
+                        let hundreds: Vec<f64> = presets.iter().map(|k| (k / 100) as f64).collect();
+                        let first = hundreds.first().copied().unwrap_or(0.0);
+                        let last = hundreds.last().copied().unwrap_or(0.0);
+                        scale.set_range(first, last.max(first + 1.0));
+                        for &mark in &hundreds {
+                            scale.add_mark(mark, gtk::PositionType::Top, None);
+                        }
+                        // Only the presets can be set, so every change
+                        // lands on the closest one.
+                        scale.connect_change_value(move |scale, _, value| {
+                            if let Some(nearest) = hundreds
+                                .iter()
+                                .copied()
+                                .min_by(|a, b| (a - value).abs().total_cmp(&(b - value).abs()))
+                            {
+                                scale.set_value(nearest);
+                            }
+                            Propagation::Stop
+                        });
+                    }
+                    _ => scale.set_range((MIN_KELVIN / 100) as f64, (MAX_KELVIN / 100) as f64),
+                }
+                scale.set_value(65.0);
+                add_scroll_controller(&scale, None, &scroll_step, &scroll_enabled, None);
+
+                let label = Label::new(Some(&format!("{} K", scale.value() as u32 * 100)));
+                label.set_width_chars(7);
+                label.set_halign(gtk::Align::End);
+                let label_value = label.clone();
+                scale.adjustment().connect_value_changed(move |adj| {
+                    label_value.set_text(&format!("{} K", adj.value() as u32 * 100));
+                });
+
+                let row = Box::new(Orientation::Horizontal, 8);
+                let temperature_label = Label::new(Some("Temperature:"));
+                temperature_label.set_width_chars(12);
+                row.append(&temperature_label);
+                row.append(&scale);
+                row.append(&label);
+                row.set_margin_top(8);
+                calibration_box.append(&row);
+
+                (Some(scale), Some(label))
+            }
+        };
+
+        let mut rgb_bias_controls = Vec::new();
+        for &channel in rgb_bias_channels {
+            let scale = Scale::builder()
+                .orientation(Orientation::Horizontal)
+                .hexpand(true)
+                .build();
+            scale.set_range(0.0, 100.0);
+            scale.set_digits(0);
+            scale.set_draw_value(false);
+
+            let label = Label::new(Some("50%"));
+            label.set_width_chars(5);
+            label.set_halign(gtk::Align::End);
+
+            add_scroll_controller(&scale, Some(&label), &scroll_step, &scroll_enabled, None);
+
+            let row = Box::new(Orientation::Horizontal, 8);
+            let channel_label = Label::new(Some(&format!("{} Black:", channel.name())));
+            channel_label.set_width_chars(12);
+            row.append(&channel_label);
+            row.append(&scale);
+            row.append(&label);
+            row.set_margin_top(8);
+            calibration_box.append(&row);
+
+            rgb_bias_controls.push((channel, scale, label));
+        }
+
+        if calibration_box.first_child().is_some() {
+            let calibration = Expander::new(Some("Calibration"));
+            calibration.set_child(Some(&calibration_box));
+            calibration.set_margin_top(8);
+            extras_box.append(&calibration);
+        }
+
+        // Vendor codes have no standard meaning, so they are labelled by
+        // number and kept out of the way.
+        let vendor_box = Box::new(Orientation::Vertical, 0);
+        let vendor_switches: Vec<(u8, Switch)> = vendor_toggles
+            .iter()
+            .map(|&code| {
+                let switch = Switch::new();
+                switch.set_valign(gtk::Align::Center);
+                let row = Box::new(Orientation::Horizontal, 8);
+                row.set_margin_top(8);
+                let label = Label::new(Some(&format!("VCP 0x{:02X}:", code)));
+                label.set_width_chars(12);
+                label.set_halign(gtk::Align::Start);
+                label.set_tooltip_text(Some(
+                    "A manufacturer feature the monitor lists as on/off, such as logo dimming \
+                     or pixel shift; see its manual for which",
+                ));
+                row.append(&label);
+                row.append(&switch);
+                vendor_box.append(&row);
+                (code, switch)
+            })
+            .collect();
+        if !vendor_switches.is_empty() {
+            let advanced = Expander::new(Some("Advanced"));
+            advanced.set_child(Some(&vendor_box));
+            advanced.set_margin_top(8);
+            extras_box.append(&advanced);
         }
 
         // Set initial visibility based on DC mode
@@ -330,12 +934,61 @@ impl MonitorRow {
             row.set_visible(dc_active);
         }
 
-        let container = ActionRow::builder().title(&name).build();
-        container.add_suffix(&main_box);
+        let identify_button = Button::from_icon_name("find-location-symbolic");
+        identify_button.set_tooltip_text(Some("Identify monitor"));
+        identify_button.set_valign(gtk::Align::Center);
+        identify_button.add_css_class("flat");
+
+        let favorite_button = ToggleButton::new();
+        favorite_button.set_icon_name("non-starred-symbolic");
+        favorite_button.set_tooltip_text(Some("Pin to top"));
+        favorite_button.set_valign(gtk::Align::Center);
+        favorite_button.add_css_class("flat");
+        favorite_button.connect_toggled(|button| {
+            button.set_icon_name(if button.is_active() {
+                "starred-symbolic"
+            } else {
+                "non-starred-symbolic"
+            });
+        });
+
+        let asleep_label = Label::new(Some("Asleep"));
+        asleep_label.add_css_class("dim-label");
+        asleep_label.set_visible(false);
+
+        let read_only_label = Label::new(Some("Read-only"));
+        read_only_label.add_css_class("dim-label");
+        read_only_label.set_tooltip_text(Some(
+            "This system blocks DDC/CI writes; the values are shown for information",
+        ));
+        read_only_label.set_visible(false);
+
+        let container: PreferencesRow = if compact && extras_box.first_child().is_some() {
+            let expander = ExpanderRow::builder().title(&name).build();
+            expander.add_suffix(&asleep_label);
+            expander.add_suffix(&read_only_label);
+            expander.add_suffix(&main_box);
+            expander.add_suffix(&identify_button);
+            expander.add_suffix(&favorite_button);
+            extras_box.set_margin_start(12);
+            extras_box.set_margin_end(12);
+            extras_box.set_margin_bottom(8);
+            expander.add_row(&extras_box);
+            expander.upcast()
+        } else {
+            let row = ActionRow::builder().title(&name).build();
+            row.add_suffix(&asleep_label);
+            row.add_suffix(&read_only_label);
+            row.add_suffix(&main_box);
+            row.add_suffix(&identify_button);
+            row.add_suffix(&favorite_button);
+            row.upcast()
+        };
 
         Self {
             container,
             name,
+            settings_key,
             brightness_scale,
             brightness_label,
             contrast_scale,
@@ -344,9 +997,28 @@ impl MonitorRow {
             volume_label,
             input_source_combo,
             power_mode_combo,
+            osd_lock_switch,
+            invert_switch,
+            custom_controls,
+            vendor_switches,
             dynamic_contrast_scale,
             dynamic_contrast_label,
             dynamic_contrast_toggle: dc_toggle_row.map(|(_, t)| t),
+            identify_button,
+            favorite_button,
+            asleep_label,
+            asleep: Rc::new(Cell::new(false)),
+            read_only_label,
+            read_only: Rc::new(Cell::new(false)),
+            locked: Rc::new(Cell::new(false)),
+            osd_lock_writable,
+            live_update: Rc::new(Cell::new(true)),
+            brightness_marks: brightness_marks.to_vec(),
+            brightness_range,
+            high_contrast: Cell::new(false),
+            rgb_bias_controls,
+            color_temperature_scale,
+            color_temperature_label,
             brightness_row,
             contrast_row,
             dynamic_contrast_row,
@@ -354,35 +1026,143 @@ impl MonitorRow {
             contrast_label_inner,
             volume_label_inner,
             dynamic_contrast_label_inner,
+            updating: UpdateGuard::default(),
         }
     }
 
-    pub fn set_brightness(&self, percentage: u8) {
-        self.brightness_scale.set_value(percentage as f64);
-        self.brightness_label.set_text(&format!("{}%", percentage));
+    pub fn set_subtitle(&self, subtitle: &str) {
+        if let Some(row) = self.container.downcast_ref::<ActionRow>() {
+            row.set_subtitle(subtitle);
+        } else if let Some(row) = self.container.downcast_ref::<ExpanderRow>() {
+            row.set_subtitle(subtitle);
+        }
     }
 
-    pub fn set_contrast(&self, percentage: u8) {
-        if let Some(ref scale) = self.contrast_scale {
-            scale.set_value(percentage as f64);
+    /// Adds a button whose popover lists each feature with ✓ or ✗, so
+    /// users can see what detection found without trying every control.
+    pub fn set_feature_summary(&self, technology: &str, features: &[(&str, bool)]) {
+        let list = Box::new(Orientation::Vertical, 4);
+        list.set_margin_top(8);
+        list.set_margin_bottom(8);
+        list.set_margin_start(8);
+        list.set_margin_end(8);
+        let display = Label::new(Some(&format!("Display: {}", technology)));
+        display.set_halign(gtk::Align::Start);
+        display.add_css_class("heading");
+        list.append(&display);
+        for &(name, supported) in features {
+            let label = Label::new(Some(&format!(
+                "{} {}",
+                name,
+                if supported { "✓" } else { "✗" }
+            )));
+            label.set_halign(gtk::Align::Start);
+            if !supported {
+                label.add_css_class("dim-label");
+            }
+            list.append(&label);
+        }
+        let popover = Popover::new();
+        popover.set_child(Some(&list));
+
+        let button = MenuButton::new();
+        button.set_icon_name("dialog-information-symbolic");
+        button.set_tooltip_text(Some("Supported features"));
+        button.set_valign(gtk::Align::Center);
+        button.add_css_class("flat");
+        button.set_popover(Some(&popover));
+
+        if let Some(row) = self.container.downcast_ref::<ActionRow>() {
+            row.add_suffix(&button);
+        } else if let Some(row) = self.container.downcast_ref::<ExpanderRow>() {
+            row.add_suffix(&button);
         }
-        if let Some(ref label) = self.contrast_label {
-            label.set_text(&format!("{}%", percentage));
+    }
+
+    /// Sets the curve brightness percentages are shown with, to match the
+    /// one writes go through. The sliders keep their raw positions; labels
+    /// and marks move to the new percentages.
+    pub fn set_brightness_curve(&self, curve: BrightnessCurve) {
+        self.brightness_range.curve.set(curve);
+        let range = &self.brightness_range;
+        let percentage = range.percentage(self.brightness_scale.value());
+        self.brightness_label.set_text(&format!("{}%", percentage));
+        if let (Some(scale), Some(label)) =
+            (&self.dynamic_contrast_scale, &self.dynamic_contrast_label)
+        {
+            label.set_text(&format!("{}%", range.percentage(scale.value())));
         }
+        self.draw_marks();
+    }
+
+    /// Stops the brightness sliders at `ceiling`, so dragging, scrolling
+    /// and keys can't ask for more than the night cap allows. `None` lifts
+    /// the stop.
+    pub fn set_brightness_ceiling(&self, ceiling: Option<u8>) {
+        let scales = std::iter::once(&self.brightness_scale).chain(&self.dynamic_contrast_scale);
+        self.updating.suppress(|| {
+            for scale in scales {
+                let range = &self.brightness_range;
+                scale.set_fill_level(ceiling.map_or(range.max as f64, |c| range.position(c)));
+                scale.set_show_fill_level(ceiling.is_some());
+                scale.set_restrict_to_fill_level(ceiling.is_some());
+            }
+        });
+    }
+
+    pub fn set_brightness(&self, percentage: u8) {
+        self.updating.suppress(|| {
+            self.brightness_scale
+                .set_value(self.brightness_range.position(percentage));
+            self.brightness_label.set_text(&format!("{}%", percentage));
+        });
+    }
+
+    pub fn set_contrast(&self, percentage: u8) {
+        self.updating.suppress(|| {
+            if let Some(ref scale) = self.contrast_scale {
+                scale.set_value(percentage as f64);
+            }
+            if let Some(ref label) = self.contrast_label {
+                label.set_text(&format!("{}%", percentage));
+            }
+        });
     }
 
     pub fn connect_brightness_changed<F>(&self, callback: F)
     where
         F: Fn(u8) + Clone + 'static,
+    {
+        self.connect_brightness_raw_changed(move |_, percentage| callback(percentage));
+    }
+
+    /// Like `connect_brightness_changed`, also passing the raw value the
+    /// slider is at, which is what should be written.
+    pub fn connect_brightness_raw_changed<F>(&self, callback: F)
+    where
+        F: Fn(u16, u8) + Clone + 'static,
     {
         let label_inner = self.brightness_label_inner.clone();
-        let callback_clone = callback.clone();
+        let range = self.brightness_range.clone();
         let adjustment = self.brightness_scale.adjustment();
         adjustment.connect_value_changed(move |adj| {
-            let val = adj.value() as u8;
-            callback_clone(val);
-            label_inner.borrow().set_text(&format!("{}%", val));
+            label_inner
+                .borrow()
+                .set_text(&format!("{}%", range.percentage(adj.value())));
         });
+        let range = self.brightness_range.clone();
+        connect_user_value(
+            &self.brightness_scale,
+            &self.updating,
+            &self.live_update,
+            move |position| callback(position as u16, range.percentage(position)),
+        );
+    }
+
+    /// Switches between writing while a slider is dragged and writing
+    /// once when it is released.
+    pub fn set_live_update(&self, live: bool) {
+        self.live_update.set(live);
     }
 
     pub fn connect_contrast_changed<F>(&self, callback: F)
@@ -393,12 +1173,14 @@ impl MonitorRow {
             (&self.contrast_scale, &self.contrast_label_inner)
         {
             let label_inner = label_inner.clone();
-            let callback_clone = callback.clone();
             let adjustment = scale.adjustment();
             adjustment.connect_value_changed(move |adj| {
-                let val = adj.value() as u8;
-                callback_clone(val);
-                label_inner.borrow().set_text(&format!("{}%", val));
+                label_inner
+                    .borrow()
+                    .set_text(&format!("{}%", adj.value() as u8));
+            });
+            connect_user_value(scale, &self.updating, &self.live_update, move |value| {
+                callback(value as u8)
             });
         }
     }
@@ -420,25 +1202,152 @@ impl MonitorRow {
     }
 
     pub fn set_volume(&self, percentage: u8) {
-        if let Some(ref scale) = self.volume_scale {
-            scale.set_value(percentage as f64);
-        }
-        if let Some(ref label) = self.volume_label {
-            label.set_text(&format!("{}%", percentage));
-        }
+        self.updating.suppress(|| {
+            if let Some(ref scale) = self.volume_scale {
+                scale.set_value(percentage as f64);
+            }
+            if let Some(ref label) = self.volume_label {
+                label.set_text(&format!("{}%", percentage));
+            }
+        });
     }
 
     pub fn set_input_source(&self, source_code: u8) {
         if let Some(ref combo) = self.input_source_combo {
             let code_str = source_code.to_string();
-            combo.set_active_id(Some(&code_str));
+            self.updating.suppress(|| {
+                if !combo.set_active_id(Some(&code_str)) {
+                    // Vendor-specific code: show it rather than hiding the
+                    // current input.
+                    combo.append(Some(&code_str), &format!("Unknown ({})", source_code));
+                    combo.set_active_id(Some(&code_str));
+                }
+            });
+        }
+    }
+
+    /// Shows `value` in the custom control for `code`. A dropdown gets an
+    /// entry for a value the settings don't list.
+    pub fn set_custom_control(&self, code: u8, value: u16) {
+        for (_, widget) in self.custom_controls.iter().filter(|(c, _)| *c == code) {
+            self.updating.suppress(|| match widget {
+                CustomWidget::Dropdown(combo) => {
+                    let value_str = value.to_string();
+                    if !combo.set_active_id(Some(&value_str)) {
+                        combo.append(Some(&value_str), &format!("Unknown ({})", value));
+                        combo.set_active_id(Some(&value_str));
+                    }
+                }
+                CustomWidget::Slider(scale) => scale.set_value(value as f64),
+            });
+        }
+    }
+
+    /// Calls `callback` with the VCP code and raw value when a custom
+    /// control is changed by hand.
+    pub fn connect_custom_control_changed<F>(&self, callback: F)
+    where
+        F: Fn(u8, u16) + Clone + 'static,
+    {
+        for (code, widget) in &self.custom_controls {
+            let code = *code;
+            let callback = callback.clone();
+            let updating = self.updating.clone();
+            match widget {
+                CustomWidget::Dropdown(combo) => {
+                    combo.connect_changed(move |combo| {
+                        if updating.is_updating() {
+                            return;
+                        }
+                        if let Some(value) = combo.active_id().and_then(|id| id.parse().ok()) {
+                            callback(code, value);
+                        }
+                    });
+                }
+                CustomWidget::Slider(scale) => {
+                    scale.adjustment().connect_value_changed(move |adj| {
+                        if !updating.is_updating() {
+                            callback(code, adj.value() as u16);
+                        }
+                    });
+                }
+            }
+        }
+    }
+
+    pub fn set_vendor_toggle(&self, code: u8, on: bool) {
+        for (_, switch) in self.vendor_switches.iter().filter(|(c, _)| *c == code) {
+            self.updating.suppress(|| switch.set_active(on));
+        }
+    }
+
+    /// Calls `callback` with the VCP code and new state when a manufacturer
+    /// toggle is switched by hand.
+    pub fn connect_vendor_toggle_changed<F>(&self, callback: F)
+    where
+        F: Fn(u8, bool) + Clone + 'static,
+    {
+        for (code, switch) in &self.vendor_switches {
+            let code = *code;
+            let callback = callback.clone();
+            let updating = self.updating.clone();
+            switch.connect_state_set(move |_, state| {
+                if !updating.is_updating() {
+                    callback(code, state);
+                }
+                Propagation::Proceed
+            });
+        }
+    }
+
+    pub fn has_osd_lock(&self) -> bool {
+        self.osd_lock_switch.is_some()
+    }
+
+    pub fn set_osd_lock(&self, locked: bool) {
+        if let Some(ref switch) = self.osd_lock_switch {
+            self.updating.suppress(|| switch.set_active(locked));
+        }
+    }
+
+    /// `callback` returns whether the monitor took the change; the switch
+    /// flips back when it didn't.
+    pub fn connect_osd_lock_changed<F>(&self, callback: F)
+    where
+        F: Fn(bool) -> bool + 'static,
+    {
+        if let Some(ref switch) = self.osd_lock_switch {
+            connect_reverting_switch(switch, &self.updating, callback);
+        }
+    }
+
+    pub fn has_invert(&self) -> bool {
+        self.invert_switch.is_some()
+    }
+
+    pub fn set_invert(&self, inverted: bool) {
+        if let Some(ref switch) = self.invert_switch {
+            self.updating.suppress(|| switch.set_active(inverted));
+        }
+    }
+
+    /// Like `connect_osd_lock_changed`, the switch flips back when
+    /// `callback` returns false.
+    pub fn connect_invert_changed<F>(&self, callback: F)
+    where
+        F: Fn(bool) -> bool + 'static,
+    {
+        if let Some(ref switch) = self.invert_switch {
+            connect_reverting_switch(switch, &self.updating, callback);
         }
     }
 
     pub fn set_power_mode(&self, mode_code: u8) {
         if let Some(ref combo) = self.power_mode_combo {
             let code_str = mode_code.to_string();
-            combo.set_active_id(Some(&code_str));
+            self.updating.suppress(|| {
+                combo.set_active_id(Some(&code_str));
+            });
         }
     }
 
@@ -450,23 +1359,212 @@ impl MonitorRow {
             (&self.volume_scale, &self.volume_label_inner)
         {
             let label_inner = label_inner.clone();
-            let callback_clone = callback.clone();
             let adjustment = scale.adjustment();
             adjustment.connect_value_changed(move |adj| {
-                let val = adj.value() as u8;
-                callback_clone(val);
-                label_inner.borrow().set_text(&format!("{}%", val));
+                label_inner
+                    .borrow()
+                    .set_text(&format!("{}%", adj.value() as u8));
+            });
+            connect_user_value(scale, &self.updating, &self.live_update, move |value| {
+                callback(value as u8)
             });
         }
     }
 
+    pub fn rgb_bias_channels(&self) -> Vec<ColorChannel> {
+        self.rgb_bias_controls.iter().map(|(c, _, _)| *c).collect()
+    }
+
+    pub fn set_rgb_bias(&self, channel: ColorChannel, percentage: u8) {
+        self.updating.suppress(|| {
+            for (c, scale, label) in &self.rgb_bias_controls {
+                if *c == channel {
+                    scale.set_value(percentage as f64);
+                    label.set_text(&format!("{}%", percentage));
+                }
+            }
+        });
+    }
+
+    pub fn connect_rgb_bias_changed<F>(&self, callback: F)
+    where
+        F: Fn(ColorChannel, u8) + Clone + 'static,
+    {
+        for (channel, scale, label) in &self.rgb_bias_controls {
+            let channel = *channel;
+            let label = label.clone();
+            let callback_clone = callback.clone();
+            scale.adjustment().connect_value_changed(move |adj| {
+                label.set_text(&format!("{}%", adj.value() as u8));
+            });
+            connect_user_value(scale, &self.updating, &self.live_update, move |val| {
+                callback_clone(channel, val as u8)
+            });
+        }
+    }
+
+    pub fn has_color_temperature(&self) -> bool {
+        self.color_temperature_scale.is_some()
+    }
+
+    pub fn set_color_temperature(&self, kelvin: u32) {
+        if let (Some(scale), Some(label)) =
+            (&self.color_temperature_scale, &self.color_temperature_label)
+        {
+            self.updating
+                .suppress(|| scale.set_value((kelvin / 100) as f64));
+            label.set_text(&format!("{} K", kelvin));
+        }
+    }
+
+    pub fn connect_color_temperature_changed<F>(&self, callback: F)
+    where
+        F: Fn(u32) + 'static,
+    {
+        if let Some(ref scale) = self.color_temperature_scale {
+            connect_user_value(scale, &self.updating, &self.live_update, move |hundreds| {
+                callback(hundreds as u32 * 100)
+            });
+        }
+    }
+
+    pub fn connect_identify<F>(&self, callback: F)
+    where
+        F: Fn() + 'static,
+    {
+        self.identify_button.connect_clicked(move |_| callback());
+    }
+
+    /// Calls `callback` once the pointer has rested on the row for
+    /// `HOVER_DELAY_MS`; leaving earlier cancels it.
+    pub fn connect_hover<F>(&self, callback: F)
+    where
+        F: Fn() + 'static,
+    {
+        let callback = Rc::new(callback);
+        let pending: Rc<RefCell<Option<glib::SourceId>>> = Rc::default();
+        let controller = EventControllerMotion::new();
+
+        let pending_enter = pending.clone();
+        controller.connect_enter(move |_, _, _| {
+            let callback = callback.clone();
+            let pending_fire = pending_enter.clone();
+            let source = glib::timeout_add_local_once(
+                std::time::Duration::from_millis(HOVER_DELAY_MS),
+                move || {
+                    pending_fire.borrow_mut().take();
+                    callback();
+                },
+            );
+            if let Some(previous) = pending_enter.borrow_mut().replace(source) {
+                previous.remove();
+            }
+        });
+        controller.connect_leave(move |_| {
+            if let Some(source) = pending.borrow_mut().take() {
+                source.remove();
+            }
+        });
+        self.container.add_controller(controller);
+    }
+
+    /// Marks the monitor as being in standby: its sliders are disabled
+    /// until it wakes, as the values they show can't be read.
+    pub fn set_asleep(&self, asleep: bool) {
+        self.asleep.set(asleep);
+        self.asleep_label.set_visible(asleep);
+        self.update_sensitivity();
+    }
+
+    /// Turns every control into a display of the current value, for
+    /// monitors that reject writes.
+    pub fn set_read_only(&self, read_only: bool) {
+        self.read_only.set(read_only);
+        self.read_only_label.set_visible(read_only);
+        self.update_sensitivity();
+    }
+
+    /// Locks every control so the values can be seen but not changed.
+    pub fn set_locked(&self, locked: bool) {
+        self.locked.set(locked);
+        self.favorite_button.set_sensitive(!locked);
+        self.update_sensitivity();
+    }
+
+    fn update_sensitivity(&self) {
+        let read_only = self.read_only.get() || self.locked.get();
+        let scales = [
+            Some(&self.brightness_scale),
+            self.contrast_scale.as_ref(),
+            self.volume_scale.as_ref(),
+            self.dynamic_contrast_scale.as_ref(),
+        ];
+        for scale in scales.into_iter().flatten() {
+            scale.set_sensitive(!self.asleep.get() && !read_only);
+        }
+        for (_, scale, _) in &self.rgb_bias_controls {
+            scale.set_sensitive(!read_only);
+        }
+        if let Some(ref scale) = self.color_temperature_scale {
+            scale.set_sensitive(!read_only);
+        }
+        for combo in [&self.input_source_combo, &self.power_mode_combo]
+            .into_iter()
+            .flatten()
+        {
+            combo.set_sensitive(!read_only);
+        }
+        for (_, widget) in &self.custom_controls {
+            match widget {
+                CustomWidget::Dropdown(combo) => combo.set_sensitive(!read_only),
+                CustomWidget::Slider(scale) => scale.set_sensitive(!read_only),
+            }
+        }
+        for switch in [&self.dynamic_contrast_toggle, &self.invert_switch]
+            .into_iter()
+            .flatten()
+            .chain(self.vendor_switches.iter().map(|(_, switch)| switch))
+        {
+            switch.set_sensitive(!read_only);
+        }
+        if let Some(ref switch) = self.osd_lock_switch {
+            switch.set_sensitive(self.osd_lock_writable && !read_only);
+        }
+        self.identify_button.set_sensitive(!read_only);
+    }
+
+    pub fn is_asleep(&self) -> bool {
+        self.asleep.get()
+    }
+
+    pub fn set_favorite(&self, favorite: bool) {
+        self.updating
+            .suppress(|| self.favorite_button.set_active(favorite));
+    }
+
+    pub fn connect_favorite_toggled<F>(&self, callback: F)
+    where
+        F: Fn(bool) + 'static,
+    {
+        let updating = self.updating.clone();
+        self.favorite_button.connect_toggled(move |button| {
+            if !updating.is_updating() {
+                callback(button.is_active());
+            }
+        });
+    }
+
     pub fn connect_input_source_changed<F>(&self, callback: F)
     where
         F: Fn(u8) + Clone + 'static,
     {
         if let Some(ref combo) = self.input_source_combo {
             let callback_clone = callback.clone();
+            let updating = self.updating.clone();
             combo.connect_changed(move |combo| {
+                if updating.is_updating() {
+                    return;
+                }
                 if let Some(id) = combo.active_id() {
                     if let Ok(code) = id.parse::<u8>() {
                         callback_clone(code);
@@ -482,7 +1580,11 @@ impl MonitorRow {
     {
         if let Some(ref combo) = self.power_mode_combo {
             let callback_clone = callback.clone();
+            let updating = self.updating.clone();
             combo.connect_changed(move |combo| {
+                if updating.is_updating() {
+                    return;
+                }
                 if let Some(id) = combo.active_id() {
                     if let Ok(code) = id.parse::<u8>() {
                         callback_clone(code);
@@ -503,28 +1605,36 @@ impl MonitorRow {
     }
 
     pub fn set_dynamic_contrast(&self, percentage: u8) {
-        if let Some(ref scale) = self.dynamic_contrast_scale {
-            scale.set_value(percentage as f64);
-        }
-        if let Some(ref label) = self.dynamic_contrast_label {
-            label.set_text(&format!("{}%", percentage));
-        }
+        self.updating.suppress(|| {
+            if let Some(ref scale) = self.dynamic_contrast_scale {
+                scale.set_value(self.brightness_range.position(percentage));
+            }
+            if let Some(ref label) = self.dynamic_contrast_label {
+                label.set_text(&format!("{}%", percentage));
+            }
+        });
     }
 
+    /// Calls `callback` with the raw brightness and its percentage when
+    /// the user moves the dynamic contrast slider.
     pub fn connect_dynamic_contrast_changed<F>(&self, callback: F)
     where
-        F: Fn(u8) + Clone + 'static,
+        F: Fn(u16, u8) + Clone + 'static,
     {
         if let (Some(ref scale), Some(ref label_inner)) =
             (&self.dynamic_contrast_scale, &self.dynamic_contrast_label_inner)
         {
             let label_inner = label_inner.clone();
-            let callback_clone = callback.clone();
+            let range = self.brightness_range.clone();
             let adjustment = scale.adjustment();
             adjustment.connect_value_changed(move |adj| {
-                let val = adj.value() as u8;
-                callback_clone(val);
-                label_inner.borrow().set_text(&format!("{}%", val));
+                label_inner
+                    .borrow()
+                    .set_text(&format!("{}%", range.percentage(adj.value())));
+            });
+            let range = self.brightness_range.clone();
+            connect_user_value(scale, &self.updating, &self.live_update, move |position| {
+                callback(position as u16, range.percentage(position))
             });
         }
     }
@@ -561,7 +1671,208 @@ impl MonitorRow {
         }
     }
 
+    pub fn brightness_value(&self) -> u8 {
+        self.brightness_range
+            .percentage(self.brightness_scale.value())
+    }
+
+    pub fn contrast_value(&self) -> Option<u8> {
+        self.contrast_scale
+            .as_ref()
+            .map(|scale| scale.value() as u8)
+    }
+
+    pub fn volume_value(&self) -> Option<u8> {
+        self.volume_scale.as_ref().map(|scale| scale.value() as u8)
+    }
+
+    pub fn dynamic_contrast_value(&self) -> u8 {
+        self.dynamic_contrast_scale
+            .as_ref()
+            .map(|scale| self.brightness_range.percentage(scale.value()))
+            .unwrap_or(0)
+    }
+
+    pub fn is_dynamic_contrast_active(&self) -> bool {
+        self.dynamic_contrast_row
+            .as_ref()
+            .map(|row| row.is_visible())
+            .unwrap_or(false)
+    }
+
     pub fn has_dynamic_contrast(&self) -> bool {
         self.dynamic_contrast_scale.is_some()
     }
+
+    /// Draws the value on every slider, adds marks at quarter steps and
+    /// enlarges the percentage labels, for users who find the plain sliders
+    /// hard to read.
+    pub fn set_high_contrast(&self, enabled: bool) {
+        self.high_contrast.set(enabled);
+        let pairs = [
+            (Some(&self.brightness_scale), Some(&self.brightness_label)),
+            (self.contrast_scale.as_ref(), self.contrast_label.as_ref()),
+            (self.volume_scale.as_ref(), self.volume_label.as_ref()),
+            (
+                self.dynamic_contrast_scale.as_ref(),
+                self.dynamic_contrast_label.as_ref(),
+            ),
+        ];
+        let bias_pairs = self
+            .rgb_bias_controls
+            .iter()
+            .map(|(_, scale, label)| (Some(scale), Some(label)));
+        for (scale, label) in pairs.into_iter().chain(bias_pairs) {
+            if let Some(scale) = scale {
+                scale.set_draw_value(enabled);
+            }
+            if let Some(label) = label {
+                if enabled {
+                    label.add_css_class("title-4");
+                } else {
+                    label.remove_css_class("title-4");
+                }
+            }
+        }
+        self.draw_marks();
+    }
+
+    /// Redraws the quarter-step marks of high contrast mode and, on the
+    /// brightness sliders, the saved brightness percentages, whose
+    /// positions depend on the brightness curve.
+    fn draw_marks(&self) {
+        let range = &self.brightness_range;
+        let brightness_scales = std::iter::once(&self.brightness_scale)
+            .chain(&self.dynamic_contrast_scale)
+            .map(|scale| (scale, true));
+        let other_scales = [&self.contrast_scale, &self.volume_scale]
+            .into_iter()
+            .flatten()
+            .chain(self.rgb_bias_controls.iter().map(|(_, scale, _)| scale))
+            .map(|scale| (scale, false));
+        for (scale, brightness) in brightness_scales.chain(other_scales) {
+            scale.clear_marks();
+            if self.high_contrast.get() {
+                for quarter in [0, 25, 50, 75, 100] {
+                    let position = if brightness {
+                        range.position(quarter)
+                    } else {
+                        quarter as f64
+                    };
+                    scale.add_mark(position, gtk::PositionType::Bottom, None);
+                }
+            }
+            if brightness {
+                add_brightness_marks(scale, &self.brightness_marks, range);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn programmatic_updates_do_not_reach_the_write_callback() {
+        let guard = UpdateGuard::default();
+        // Stands in for the monitor, recording each value written to it.
+        let written = Rc::new(RefCell::new(Vec::new()));
+
+        let handler_guard = guard.clone();
+        let handler_written = written.clone();
+        let on_value_changed = move |value: u8| {
+            if !handler_guard.is_updating() {
+                handler_written.borrow_mut().push(value);
+            }
+        };
+
+        // A value read from the monitor is shown without being written back.
+        guard.suppress(|| on_value_changed(60));
+        assert!(written.borrow().is_empty());
+
+        on_value_changed(45);
+        assert_eq!(*written.borrow(), [45]);
+    }
+
+    #[test]
+    fn only_clicks_and_drags_snap_to_marks() {
+        assert_eq!(snap_target(ScrollType::Jump, 49.0, &[25, 50]), Some(50));
+        assert_eq!(snap_target(ScrollType::Jump, 40.0, &[25, 50]), None);
+        assert_eq!(snap_target(ScrollType::StepForward, 51.0, &[50]), None);
+        assert_eq!(snap_target(ScrollType::PageBackward, 49.0, &[50]), None);
+    }
+
+    #[test]
+    fn combo_ids_match_the_enum_codes() {
+        let inputs = input_source_choices();
+        assert!(inputs.contains(&("15".to_string(), "DisplayPort 1".to_string())));
+        assert!(inputs.contains(&("16".to_string(), "DisplayPort 2".to_string())));
+        for (id, label) in inputs {
+            let source = InputSource::from_code(id.parse().unwrap());
+            assert_eq!(source.name(), label);
+            assert!(!matches!(source, InputSource::Unknown(_)));
+        }
+        for (id, label) in power_mode_choices() {
+            let mode = PowerMode::from_code(id.parse().unwrap());
+            assert_eq!(mode.name(), label);
+            assert!(!matches!(mode, PowerMode::Unknown(_)));
+        }
+    }
+
+    #[test]
+    fn nested_suppression_restores_outer_state() {
+        let guard = UpdateGuard::default();
+        guard.suppress(|| {
+            guard.suppress(|| {});
+            assert!(guard.is_updating());
+        });
+        assert!(!guard.is_updating());
+    }
+
+    #[test]
+    fn scroll_step_changes_apply_to_existing_accumulators() {
+        let step = Rc::new(Cell::new(5));
+        let accumulator = ScrollAccumulator::new(step.clone());
+        assert_eq!(accumulator.consume(-1.0), 5.0);
+
+        step.set(2);
+        assert_eq!(accumulator.consume(-1.0), 2.0);
+        assert_eq!(accumulator.consume(1.0), -2.0);
+    }
+
+    #[test]
+    fn values_snap_to_the_closest_nearby_mark() {
+        let marks = [30, 33, 80];
+        assert_eq!(nearest_mark(31.0, &marks), Some(30));
+        assert_eq!(nearest_mark(32.0, &marks), Some(33));
+        assert_eq!(nearest_mark(77.5, &marks), None);
+        assert_eq!(nearest_mark(50.0, &[]), None);
+    }
+
+    #[test]
+    fn brightness_steps_always_reach_another_raw_value() {
+        let range = BrightnessRange::new(0, 64);
+        assert_eq!(range.step(10.0, 1.0), 11.0);
+        assert_eq!(range.step(10.0, -1.0), 9.0);
+        assert_eq!(range.step(32.0, 10.0), 39.0);
+        assert_eq!(range.step(64.0, 5.0), 64.0);
+        assert_eq!(range.step(0.0, -5.0), 0.0);
+        for raw in 0..=64 {
+            assert_eq!(range.position(range.percentage(raw as f64)), raw as f64);
+        }
+
+        let unsupported = BrightnessRange::new(0, 0);
+        assert_eq!((unsupported.min, unsupported.max), (0, 100));
+    }
+
+    #[test]
+    fn smooth_scrolling_carries_the_remainder() {
+        let accumulator = ScrollAccumulator::new(Rc::new(Cell::new(5)));
+        assert_eq!(accumulator.consume(-0.1), 0.0);
+        assert_eq!(accumulator.consume(-0.1), 1.0);
+
+        accumulator.reset();
+        assert_eq!(accumulator.consume(-0.1), 0.0);
+    }
 }
@@ -0,0 +1,141 @@
+use crate::ddc_manager::DdcManager;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Saved values for one monitor; `None` leaves that feature untouched.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MonitorProfile {
+    /// The monitor's name when the profile was saved, to report it by when
+    /// it is not connected.
+    pub name: String,
+    pub brightness: Option<u8>,
+    pub contrast: Option<u8>,
+    pub volume: Option<u8>,
+}
+
+/// A named set of monitor values, keyed by `Monitor::settings_key`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Profile {
+    pub monitors: HashMap<String, MonitorProfile>,
+}
+
+/// What `Profile::apply` did, by monitor name.
+#[derive(Debug, Default)]
+pub struct ApplySummary {
+    pub applied: Vec<String>,
+    pub failed: Vec<String>,
+    /// Monitors in the profile that are not currently connected.
+    pub missing: Vec<String>,
+}
+
+impl Profile {
+    /// Captures the current values of every connected monitor.
+    pub fn capture(ddc: &mut DdcManager) -> Self {
+        let mut monitors = HashMap::new();
+        for i in 0..ddc.monitors.len() {
+            let entry = MonitorProfile {
+                name: ddc.monitors[i].name.clone(),
+                brightness: ddc.get_brightness_percentage(i).ok(),
+                contrast: ddc
+                    .supports_contrast(i)
+                    .then(|| ddc.get_contrast_percentage(i).ok())
+                    .flatten(),
+                volume: ddc
+                    .supports_volume(i)
+                    .then(|| ddc.get_volume_percentage(i).ok())
+                    .flatten(),
+            };
+            monitors.insert(ddc.monitors[i].settings_key().to_string(), entry);
+        }
+        Self { monitors }
+    }
+
+    pub fn apply(&self, ddc: &mut DdcManager) -> ApplySummary {
+        let mut summary = ApplySummary::default();
+
+        for (key, values) in &self.monitors {
+            let Some(i) = ddc.monitors.iter().position(|m| m.settings_key() == key) else {
+                let name = if values.name.is_empty() {
+                    key
+                } else {
+                    &values.name
+                };
+                summary.missing.push(name.clone());
+                continue;
+            };
+
+            let mut ok = true;
+            if let Some(brightness) = values.brightness {
+                ok &= ddc.set_brightness_percentage(i, brightness).is_ok();
+            }
+            if let Some(contrast) = values.contrast.filter(|_| ddc.supports_contrast(i)) {
+                ok &= ddc.set_contrast_percentage(i, contrast).is_ok();
+            }
+            if let Some(volume) = values.volume.filter(|_| ddc.supports_volume(i)) {
+                ok &= ddc.set_volume_percentage(i, volume).is_ok();
+            }
+
+            let name = ddc.monitors[i].name.clone();
+            if ok {
+                summary.applied.push(name);
+            } else {
+                summary.failed.push(name);
+            }
+        }
+
+        summary
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ddc_manager::{DdcOptions, SysPaths};
+
+    #[test]
+    fn profiles_leave_out_features_they_do_not_save() {
+        let profile: Profile =
+            serde_json::from_str(r#"{"monitors": {"Left": {"brightness": 40}}}"#).unwrap();
+        assert_eq!(
+            profile.monitors["Left"],
+            MonitorProfile {
+                name: String::new(),
+                brightness: Some(40),
+                contrast: None,
+                volume: None,
+            }
+        );
+    }
+
+    #[test]
+    fn monitors_that_are_not_connected_are_reported_missing() {
+        let root = std::env::temp_dir().join(format!("brightless-profiles-{}", std::process::id()));
+        std::fs::create_dir_all(&root).unwrap();
+        let (mut ddc, _) = DdcManager::with_options_and_report(&DdcOptions {
+            paths: SysPaths {
+                drm: root.clone(),
+                dev: root.clone(),
+            },
+            ..DdcOptions::default()
+        });
+        let named = MonitorProfile {
+            name: "DELL U2720Q (DP-1)".to_string(),
+            ..MonitorProfile::default()
+        };
+        let profile = Profile {
+            monitors: HashMap::from([
+                ("ABC123".to_string(), named),
+                ("Left".to_string(), MonitorProfile::default()),
+            ]),
+        };
+
+        let mut summary = profile.apply(&mut ddc);
+
+        summary.missing.sort();
+        assert_eq!(summary.missing, ["DELL U2720Q (DP-1)", "Left"]);
+        assert!(summary.applied.is_empty() && summary.failed.is_empty());
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,178 @@
+use serde::{Deserialize, Serialize};
+
+const MINUTES_PER_DAY: u16 = 24 * 60;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Breakpoint {
+    /// Minutes since local midnight.
+    pub minute: u16,
+    pub brightness: u8,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BrightnessSchedule {
+    pub enabled: bool,
+    pub breakpoints: Vec<Breakpoint>,
+}
+
+impl BrightnessSchedule {
+    fn sorted(&self) -> Vec<Breakpoint> {
+        let mut points: Vec<Breakpoint> = self
+            .breakpoints
+            .iter()
+            .filter(|b| b.minute < MINUTES_PER_DAY)
+            .copied()
+            .collect();
+        points.sort_by_key(|b| b.minute);
+        points
+    }
+
+    /// Brightness for the given minute of the day, interpolated linearly
+    /// between the surrounding breakpoints and wrapping around midnight.
+    pub fn brightness_at(&self, minute: u16) -> Option<u8> {
+        let points = self.sorted();
+        let first = *points.first()?;
+        let last = *points.last()?;
+        if points.len() == 1 {
+            return Some(first.brightness.min(100));
+        }
+
+        let prev = points
+            .iter()
+            .rev()
+            .find(|b| b.minute <= minute)
+            .copied()
+            .unwrap_or(last);
+        let next = points
+            .iter()
+            .find(|b| b.minute > minute)
+            .copied()
+            .unwrap_or(first);
+
+        let span = (next.minute + MINUTES_PER_DAY - prev.minute) % MINUTES_PER_DAY;
+        if span == 0 {
+            return Some(prev.brightness.min(100));
+        }
+        let elapsed = (minute + MINUTES_PER_DAY - prev.minute) % MINUTES_PER_DAY;
+        let t = elapsed as f32 / span as f32;
+        let value = prev.brightness as f32 + (next.brightness as f32 - prev.brightness as f32) * t;
+        Some((value.round() as u8).min(100))
+    }
+
+    /// Minutes from `minute` until the next breakpoint is reached.
+    pub fn minutes_until_next_breakpoint(&self, minute: u16) -> Option<u16> {
+        self.sorted()
+            .iter()
+            .map(|b| (b.minute + MINUTES_PER_DAY - minute - 1) % MINUTES_PER_DAY + 1)
+            .min()
+    }
+}
+
+/// A ceiling on brightness between two times of day, which holds however
+/// the brightness is set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NightCap {
+    pub enabled: bool,
+    /// Minutes since local midnight the cap starts; when later than `end`,
+    /// the cap runs past midnight.
+    pub start: u16,
+    /// Minutes since local midnight the cap ends.
+    pub end: u16,
+    /// Highest brightness percentage allowed meanwhile.
+    pub max_brightness: u8,
+}
+
+impl Default for NightCap {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            start: 22 * 60,
+            end: 7 * 60,
+            max_brightness: 30,
+        }
+    }
+}
+
+impl NightCap {
+    /// The ceiling in force at the given minute of the day, if any.
+    pub fn ceiling_at(&self, minute: u16) -> Option<u8> {
+        let inside = if self.start <= self.end {
+            (self.start..self.end).contains(&minute)
+        } else {
+            minute >= self.start || minute < self.end
+        };
+        (self.enabled && inside).then_some(self.max_brightness.min(100))
+    }
+}
+
+pub fn local_minute_of_day() -> Option<u16> {
+    let now = glib::DateTime::now_local().ok()?;
+    Some((now.hour() * 60 + now.minute()) as u16)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn schedule(points: &[(u16, u8)]) -> BrightnessSchedule {
+        BrightnessSchedule {
+            enabled: true,
+            breakpoints: points
+                .iter()
+                .map(|&(minute, brightness)| Breakpoint { minute, brightness })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn schedules_interpolate_between_breakpoints() {
+        // Out of order, with a minute past the end of the day that is ignored.
+        let day = schedule(&[(19 * 60, 40), (7 * 60, 80), (MINUTES_PER_DAY, 0)]);
+        assert_eq!(day.brightness_at(7 * 60), Some(80));
+        assert_eq!(day.brightness_at(13 * 60), Some(60));
+        assert_eq!(day.brightness_at(19 * 60), Some(40));
+
+        assert_eq!(schedule(&[(12 * 60, 120)]).brightness_at(0), Some(100));
+        assert_eq!(schedule(&[]).brightness_at(0), None);
+    }
+
+    #[test]
+    fn schedules_wrap_around_midnight() {
+        let day = schedule(&[(7 * 60, 80), (19 * 60, 40)]);
+        assert_eq!(day.brightness_at(60), Some(60));
+        assert_eq!(day.brightness_at(23 * 60), Some(53));
+        assert_eq!(day.brightness_at(6 * 60 + 59), Some(80));
+    }
+
+    #[test]
+    fn next_breakpoint_is_never_the_current_minute() {
+        let day = schedule(&[(7 * 60, 80), (19 * 60, 40)]);
+        assert_eq!(day.minutes_until_next_breakpoint(6 * 60), Some(60));
+        assert_eq!(day.minutes_until_next_breakpoint(7 * 60), Some(12 * 60));
+        assert_eq!(day.minutes_until_next_breakpoint(20 * 60), Some(11 * 60));
+        assert_eq!(schedule(&[]).minutes_until_next_breakpoint(0), None);
+    }
+
+    #[test]
+    fn night_cap_wraps_around_midnight() {
+        let cap = NightCap {
+            enabled: true,
+            ..NightCap::default()
+        };
+        assert_eq!(cap.ceiling_at(21 * 60 + 59), None);
+        assert_eq!(cap.ceiling_at(22 * 60), Some(30));
+        assert_eq!(cap.ceiling_at(3 * 60), Some(30));
+        assert_eq!(cap.ceiling_at(7 * 60), None);
+
+        let evening = NightCap {
+            start: 18 * 60,
+            end: 23 * 60,
+            ..cap
+        };
+        assert_eq!(evening.ceiling_at(20 * 60), Some(30));
+        assert_eq!(evening.ceiling_at(60), None);
+        assert_eq!(NightCap::default().ceiling_at(23 * 60), None);
+    }
+}
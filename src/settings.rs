@@ -1,56 +1,675 @@
+use crate::ddc_manager::{
+    migrate_serial_keys, migrated_serial_key, BrightnessCurve, DdcManager, DdcOptions, Feature,
+    MonitorState, SysPaths, DEFAULT_PROBE_TIMEOUT_MS,
+};
+use crate::profiles::Profile;
+use crate::schedule::{BrightnessSchedule, NightCap};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fs;
-use std::path::PathBuf;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorScheme {
+    #[default]
+    System,
+    Light,
+    Dark,
+}
+
+impl ColorScheme {
+    pub const ALL: [ColorScheme; 3] = [ColorScheme::System, ColorScheme::Light, ColorScheme::Dark];
+
+    pub fn name(&self) -> &str {
+        match self {
+            ColorScheme::System => "System",
+            ColorScheme::Light => "Light",
+            ColorScheme::Dark => "Dark",
+        }
+    }
+
+    pub fn to_adw(self) -> adw::ColorScheme {
+        match self {
+            ColorScheme::System => adw::ColorScheme::Default,
+            ColorScheme::Light => adw::ColorScheme::ForceLight,
+            ColorScheme::Dark => adw::ColorScheme::ForceDark,
+        }
+    }
+}
+
+/// Monitors whose sliders move together, and which of the sliders do.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LinkGroup {
+    /// Keyed by `Monitor::settings_key`, like the per-monitor maps.
+    pub monitors: Vec<String>,
+    pub brightness: bool,
+    pub contrast: bool,
+    pub volume: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CustomControlKind {
+    /// One of the listed `values`.
+    #[default]
+    Dropdown,
+    /// Any raw value up to the maximum the monitor reports.
+    Slider,
+}
+
+/// A control for a VCP feature Brightless has no control for, such as a
+/// vendor's audio input select.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CustomControl {
+    /// Hex byte, e.g. `"0xE9"` or `"E9"`. A plain JSON number, e.g. `233`,
+    /// is read as the code in decimal.
+    #[serde(deserialize_with = "deserialize_vcp_code")]
+    pub vcp_code: String,
+    pub name: String,
+    pub kind: CustomControlKind,
+    /// Choices of a dropdown; unused by sliders.
+    pub values: Vec<CustomValue>,
+    /// Monitors that show the control, by `Monitor::settings_key`; empty
+    /// means every monitor that answers `vcp_code`.
+    pub monitors: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CustomValue {
+    pub code: u16,
+    pub label: String,
+}
+
+impl CustomControl {
+    /// The VCP code, or `None` if `vcp_code` is not a hex byte.
+    pub fn code(&self) -> Option<u8> {
+        parse_vcp_code(&self.vcp_code)
+    }
+}
+
+/// Reads `CustomControl::vcp_code` from a string or a number, the latter
+/// written back as hex.
+fn deserialize_vcp_code<'de, D>(deserializer: D) -> Result<String, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Code {
+        Text(String),
+        Number(u64),
+    }
+    Ok(match Code::deserialize(deserializer)? {
+        Code::Text(text) => text,
+        Code::Number(number) => format!("0x{:02X}", number),
+    })
+}
+
+/// Parses a VCP code written in hex, with or without a `0x` prefix or an
+/// `h` suffix.
+pub fn parse_vcp_code(text: &str) -> Option<u8> {
+    let text = text.trim();
+    let text = text
+        .strip_prefix("0x")
+        .or_else(|| text.strip_prefix("0X"))
+        .or_else(|| text.strip_suffix('h'))
+        .unwrap_or(text);
+    u8::from_str_radix(text, 16).ok()
+}
+
+impl Default for LinkGroup {
+    fn default() -> Self {
+        Self {
+            monitors: Vec::new(),
+            brightness: true,
+            contrast: false,
+            volume: false,
+        }
+    }
+}
+
+impl LinkGroup {
+    pub fn links(&self, feature: Feature) -> bool {
+        match feature {
+            Feature::Brightness => self.brightness,
+            Feature::Contrast => self.contrast,
+            Feature::Volume => self.volume,
+        }
+    }
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(default)]
 pub struct AppSettings {
     pub scroll_step: u8,
+    /// Monitors pinned to the top of the list, in discovery order among
+    /// themselves.
+    pub favorite_monitors: HashSet<String>,
+    pub link_groups: Vec<LinkGroup>,
+    /// Extra dropdowns for vendor VCP features, read and written as raw
+    /// values.
+    pub custom_controls: Vec<CustomControl>,
+    /// Per-monitor scroll steps; monitors without one use `scroll_step`.
+    /// These maps, and the favorites, are keyed by `Monitor::settings_key`.
+    pub monitor_scroll_steps: HashMap<String, u8>,
+    /// Scrolling over a slider changes its value; when off, it scrolls the
+    /// list instead.
+    pub scroll_adjust: bool,
+    /// Per-monitor overrides of `scroll_adjust`.
+    pub monitor_scroll_adjust: HashMap<String, bool>,
+    pub color_scheme: ColorScheme,
+    /// Collapses each monitor to its brightness slider, with the other
+    /// controls behind an expander.
+    pub compact_view: bool,
+    /// Window size when it was last closed.
+    pub window_width: i32,
+    pub window_height: i32,
+    pub window_maximized: bool,
+    /// Window position when it was last closed unmaximized. Only known and
+    /// restored on X11; on Wayland the compositor places the window.
+    pub window_x: Option<i32>,
+    pub window_y: Option<i32>,
+    /// Shows each monitor's port and native resolution under its name.
+    pub show_connector_details: bool,
+    /// Draws values and quarter marks on sliders and enlarges their labels.
+    pub high_contrast_sliders: bool,
+    /// Whether the "Advanced" part of the settings popover is expanded.
+    pub advanced_settings_expanded: bool,
     pub dynamic_contrast_enabled: bool,
     pub dynamic_contrast_global: bool,
     pub dynamic_contrast_ratio: f32,
     pub dynamic_contrast_per_monitor_ratio: bool,
     pub monitor_dynamic_contrast: HashMap<String, bool>,
     pub monitor_ratios: HashMap<String, f32>,
+    pub monitor_brightness_min: HashMap<String, u16>,
+    /// Manufacturer VCP code that inverts each monitor's image, by settings
+    /// key.
+    pub monitor_invert_codes: HashMap<String, u8>,
+    /// Settings key of the monitor that `nudge` adjusts when no `--monitor`
+    /// is given; all monitors when unset or not connected.
+    pub default_monitor: Option<String>,
+    pub monitor_schedules: HashMap<String, BrightnessSchedule>,
+    /// Brightness ceiling between two times of day, on every monitor and
+    /// whatever sets the brightness.
+    pub night_cap: NightCap,
+    /// Set once the night cap has been suggested for an OLED monitor, so
+    /// the suggestion isn't repeated on every start.
+    pub night_cap_suggested: bool,
+    /// Values each monitor had when the window last closed, for
+    /// `--apply-saved-state`, by `Monitor::settings_key`.
+    pub monitor_states: HashMap<String, MonitorState>,
+    /// How long a manual change suspends the schedule; 0 means until the
+    /// next breakpoint.
+    pub schedule_override_minutes: u16,
+    pub profiles: HashMap<String, Profile>,
+    /// Profile applied when the window opens; ignored if it no longer exists.
+    pub default_profile: Option<String>,
+    /// Profile last applied from the header bar, cleared once a slider is
+    /// moved by hand.
+    pub active_profile: Option<String>,
+    /// Profiles applied when the desktop switches to dark or light mode;
+    /// `None` leaves the monitors alone.
+    pub dark_mode_profile: Option<String>,
+    pub light_mode_profile: Option<String>,
+    /// Connector (e.g. `card0-DP-1`) to I2C device (`/dev/i2c-5`) pins that
+    /// discovery tries before scanning.
+    pub i2c_device_pins: HashMap<String, String>,
+    /// Name monitors after their model from the EDID; when off they are
+    /// "Monitor 1", "Monitor 2"... and per-monitor settings of monitors
+    /// without an EDID serial follow those names.
+    pub use_edid_names: bool,
+    /// How long discovery waits for a monitor to answer on each I2C device
+    /// before moving on; 0 waits as long as the bus takes.
+    pub probe_timeout_ms: u32,
+    /// Extra milliseconds to wait after every DDC command. Helps monitors
+    /// that return garbage when addressed too quickly, at the cost of every
+    /// change taking that much longer; 0 keeps the library's own timing.
+    pub ddc_delay_ms: u32,
+    /// How many times a failed DDC command is sent again.
+    pub ddc_retries: u32,
+    /// Milliseconds slider changes are held so only the latest value per
+    /// control is written, all at once; 0 writes every change immediately.
+    pub write_coalesce_ms: u32,
+    /// Write slider values while dragging; when off, a drag is written once
+    /// on release.
+    pub live_update: bool,
+    /// Flash a monitor's brightness when the pointer rests on its row.
+    pub identify_on_hover: bool,
+    /// Read values back after each write and flag sliders whose value the
+    /// monitor clamped or rounded. Costs one extra DDC read per change.
+    pub verify_writes: bool,
+    /// Map brightness sliders through a gamma curve so equal steps look
+    /// equally large, instead of linearly onto the monitor's range.
+    pub perceptual_brightness: bool,
+    /// Seconds between re-reading monitor values to pick up changes made
+    /// on the monitor's own OSD; 0 disables polling.
+    pub poll_interval_secs: u32,
+    /// Seconds between checks that re-apply the last value set from
+    /// Brightless to monitors that changed it on their own; 0 disables the
+    /// watchdog.
+    pub watchdog_interval_secs: u32,
+    /// How many percent a monitor may drift before the watchdog corrects it.
+    pub watchdog_threshold: u8,
+    /// Restore already-changed monitors when applying to all of them fails
+    /// part way through.
+    pub batch_rollback: bool,
+    /// Shows the raw VCP panel in the settings popover.
+    pub developer_mode: bool,
+    /// Run without showing the window at launch, as with `--hidden`;
+    /// launching again shows it.
+    pub start_hidden: bool,
+    /// Remember each monitor's supported features between launches instead
+    /// of probing them every time.
+    pub cache_capabilities: bool,
+    /// Port the HTTP control endpoint listens on, on localhost only, when
+    /// built with the `http` feature; 0, the default, disables it.
+    pub http_port: u16,
+    /// Keep every control insensitive so values can be seen but not
+    /// changed, e.g. on a shared screen.
+    pub controls_locked: bool,
+    /// PIN asked for before unlocking the controls; `None` unlocks
+    /// without one. Only guards the window, not the settings file.
+    pub lock_pin: Option<String>,
 }
 
 impl Default for AppSettings {
     fn default() -> Self {
         Self {
             scroll_step: 2,
+            link_groups: Vec::new(),
+            custom_controls: Vec::new(),
+            monitor_scroll_steps: HashMap::new(),
+            scroll_adjust: true,
+            monitor_scroll_adjust: HashMap::new(),
+            favorite_monitors: HashSet::new(),
+            color_scheme: ColorScheme::System,
+            compact_view: false,
+            show_connector_details: true,
+            window_width: 400,
+            window_height: 300,
+            window_maximized: false,
+            window_x: None,
+            window_y: None,
+            high_contrast_sliders: false,
+            advanced_settings_expanded: false,
             dynamic_contrast_enabled: false,
             dynamic_contrast_global: true,
             dynamic_contrast_ratio: 0.7,
             dynamic_contrast_per_monitor_ratio: false,
             monitor_dynamic_contrast: HashMap::new(),
             monitor_ratios: HashMap::new(),
+            monitor_brightness_min: HashMap::new(),
+            monitor_invert_codes: HashMap::new(),
+            default_monitor: None,
+            monitor_schedules: HashMap::new(),
+            night_cap: NightCap::default(),
+            night_cap_suggested: false,
+            monitor_states: HashMap::new(),
+            schedule_override_minutes: 0,
+            profiles: HashMap::new(),
+            default_profile: None,
+            active_profile: None,
+            dark_mode_profile: None,
+            light_mode_profile: None,
+            i2c_device_pins: HashMap::new(),
+            use_edid_names: true,
+            probe_timeout_ms: DEFAULT_PROBE_TIMEOUT_MS,
+            ddc_delay_ms: 0,
+            ddc_retries: 0,
+            write_coalesce_ms: 0,
+            live_update: true,
+            identify_on_hover: false,
+            verify_writes: false,
+            perceptual_brightness: false,
+            poll_interval_secs: 0,
+            watchdog_interval_secs: 0,
+            watchdog_threshold: 2,
+            batch_rollback: true,
+            developer_mode: false,
+            start_hidden: false,
+            cache_capabilities: true,
+            http_port: 0,
+            controls_locked: false,
+            lock_pin: None,
         }
     }
 }
 
+static CONFIG_OVERRIDE: OnceLock<PathBuf> = OnceLock::new();
+static CONFIG_PROFILE: OnceLock<String> = OnceLock::new();
+static REFRESH_CAPABILITIES: OnceLock<()> = OnceLock::new();
+
+/// Points settings at a specific file, as given by `--config`. Takes
+/// precedence over `BRIGHTLESS_CONFIG`.
+pub fn set_config_override(path: PathBuf) {
+    let _ = CONFIG_OVERRIDE.set(path);
+}
+
+/// Selects `brightless/<name>.json` instead of `settings.json`, as given by
+/// `--profile`. Takes precedence over `BRIGHTLESS_PROFILE`.
+pub fn set_config_profile(name: String) {
+    let _ = CONFIG_PROFILE.set(name);
+}
+
+/// Makes discovery probe every monitor again instead of using the
+/// capability cache, rewriting its entries, as asked by
+/// `--refresh-capabilities`.
+pub fn set_refresh_capabilities() {
+    let _ = REFRESH_CAPABILITIES.set(());
+}
+
+fn resolve_config_path(
+    cli_override: Option<PathBuf>,
+    env_override: Option<PathBuf>,
+    profile: Option<String>,
+    config_dir: Option<PathBuf>,
+) -> io::Result<PathBuf> {
+    if let Some(path) = cli_override.or(env_override) {
+        return Ok(path);
+    }
+    let file_name = match profile {
+        Some(name) if name.is_empty() || name.contains('/') || name.starts_with('.') => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("invalid config profile name \"{}\"", name),
+            ));
+        }
+        Some(name) => format!("{}.json", name),
+        None => "settings.json".to_string(),
+    };
+    // A relative directory (e.g. a relative XDG_CONFIG_HOME) would resolve
+    // against wherever the app was launched from, so it counts as missing.
+    config_dir
+        .filter(|dir| dir.is_absolute())
+        .map(|dir| dir.join("brightless").join(file_name))
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                "no config directory found; set BRIGHTLESS_CONFIG or pass --config",
+            )
+        })
+}
+
 impl AppSettings {
-    fn config_path() -> PathBuf {
-        let config_dir = dirs::config_dir()
-            .unwrap_or_else(|| PathBuf::from("."))
-            .join("brightless");
-        config_dir.join("settings.json")
+    fn config_path() -> io::Result<PathBuf> {
+        resolve_config_path(
+            CONFIG_OVERRIDE.get().cloned(),
+            std::env::var_os("BRIGHTLESS_CONFIG")
+                .filter(|v| !v.is_empty())
+                .map(PathBuf::from),
+            CONFIG_PROFILE
+                .get()
+                .cloned()
+                .or_else(|| std::env::var("BRIGHTLESS_PROFILE").ok())
+                .filter(|v| !v.is_empty()),
+            dirs::config_dir(),
+        )
+    }
+
+    pub fn scroll_step_for(&self, monitor: &str) -> u8 {
+        *self
+            .monitor_scroll_steps
+            .get(monitor)
+            .unwrap_or(&self.scroll_step)
+    }
+
+    pub fn scroll_adjust_for(&self, monitor: &str) -> bool {
+        *self
+            .monitor_scroll_adjust
+            .get(monitor)
+            .unwrap_or(&self.scroll_adjust)
+    }
+
+    /// Other monitors that share a link group with `monitor` for `feature`.
+    pub fn linked_monitors(&self, monitor: &str, feature: Feature) -> HashSet<&str> {
+        self.link_groups
+            .iter()
+            .filter(|g| g.links(feature) && g.monitors.iter().any(|m| m == monitor))
+            .flat_map(|g| g.monitors.iter().map(String::as_str))
+            .filter(|m| *m != monitor)
+            .collect()
+    }
+
+    /// Custom controls that apply to `monitor`.
+    pub fn custom_controls_for(&self, monitor: &str) -> Vec<&CustomControl> {
+        self.custom_controls
+            .iter()
+            .filter(|c| c.monitors.is_empty() || c.monitors.iter().any(|m| m == monitor))
+            .collect()
+    }
+
+    pub fn brightness_curve(&self) -> BrightnessCurve {
+        if self.perceptual_brightness {
+            BrightnessCurve::Perceptual
+        } else {
+            BrightnessCurve::Linear
+        }
+    }
+
+    /// Raises each monitor's lowest brightness to its `monitor_brightness_min`,
+    /// so 0% maps to that floor.
+    pub fn apply_brightness_floors(&self, ddc: &mut DdcManager) {
+        for monitor in &mut ddc.monitors {
+            if let Some(&floor) = self.monitor_brightness_min.get(monitor.settings_key()) {
+                monitor.min_brightness = floor.min(monitor.max_brightness);
+            }
+        }
+    }
+
+    /// Moves entries saved under a monitor's name to its settings key, for
+    /// settings from before monitors with an EDID serial were keyed by it.
+    /// `monitors` holds each monitor's `(name, settings key)`; returns
+    /// whether anything moved.
+    pub fn adopt_monitor_keys(&mut self, monitors: &[(&str, &str)]) -> bool {
+        let mut moved = rekey(&mut self.monitor_scroll_steps, monitors);
+        moved |= rekey(&mut self.monitor_scroll_adjust, monitors);
+        moved |= rekey(&mut self.monitor_dynamic_contrast, monitors);
+        moved |= rekey(&mut self.monitor_ratios, monitors);
+        moved |= rekey(&mut self.monitor_brightness_min, monitors);
+        moved |= rekey(&mut self.monitor_states, monitors);
+        moved |= rekey(&mut self.monitor_invert_codes, monitors);
+        moved |= rekey(&mut self.monitor_schedules, monitors);
+        for profile in self.profiles.values_mut() {
+            moved |= rekey(&mut profile.monitors, monitors);
+        }
+        let mut favorites: HashMap<String, ()> =
+            self.favorite_monitors.drain().map(|m| (m, ())).collect();
+        moved |= rekey(&mut favorites, monitors);
+        self.favorite_monitors = favorites.into_keys().collect();
+        for group in &mut self.link_groups {
+            moved |= rekey_list(&mut group.monitors, monitors);
+        }
+        for control in &mut self.custom_controls {
+            moved |= rekey_list(&mut control.monitors, monitors);
+        }
+        if let Some(default) = &mut self.default_monitor {
+            let mut list = vec![default.clone()];
+            if rekey_list(&mut list, monitors) {
+                *default = list.swap_remove(0);
+                moved = true;
+            }
+        }
+        moved
+    }
+
+    /// Moves entries keyed by EDID serials in their old form, from before
+    /// the serial text was cleaned, to the form monitors have now.
+    fn migrate_serial_keys(&mut self) {
+        migrate_serial_keys(&mut self.monitor_scroll_steps);
+        migrate_serial_keys(&mut self.monitor_scroll_adjust);
+        migrate_serial_keys(&mut self.monitor_dynamic_contrast);
+        migrate_serial_keys(&mut self.monitor_ratios);
+        migrate_serial_keys(&mut self.monitor_brightness_min);
+        migrate_serial_keys(&mut self.monitor_states);
+        migrate_serial_keys(&mut self.monitor_invert_codes);
+        migrate_serial_keys(&mut self.monitor_schedules);
+        for profile in self.profiles.values_mut() {
+            migrate_serial_keys(&mut profile.monitors);
+        }
+        let mut favorites: HashMap<String, ()> =
+            self.favorite_monitors.drain().map(|m| (m, ())).collect();
+        migrate_serial_keys(&mut favorites);
+        self.favorite_monitors = favorites.into_keys().collect();
+        let lists = self
+            .link_groups
+            .iter_mut()
+            .map(|g| &mut g.monitors)
+            .chain(self.custom_controls.iter_mut().map(|c| &mut c.monitors));
+        for key in lists.flatten().chain(&mut self.default_monitor) {
+            if let Some(migrated) = migrated_serial_key(key) {
+                *key = migrated;
+            }
+        }
+    }
+
+    pub fn ddc_options(&self) -> DdcOptions {
+        DdcOptions {
+            pinned_devices: self.i2c_device_pins.clone(),
+            probe_timeout: (self.probe_timeout_ms > 0)
+                .then(|| Duration::from_millis(self.probe_timeout_ms as u64)),
+            extra_delay: Duration::from_millis(self.ddc_delay_ms as u64),
+            verify_writes: self.verify_writes,
+            brightness_curve: self.brightness_curve(),
+            capability_cache: self
+                .cache_capabilities
+                .then(Self::capability_cache_path)
+                .flatten(),
+            refresh_capabilities: REFRESH_CAPABILITIES.get().is_some(),
+            invert_codes: self.monitor_invert_codes.clone(),
+            retries: self.ddc_retries,
+            edid_names: self.use_edid_names,
+            paths: SysPaths::default(),
+        }
+    }
+
+    /// Where discovery caches monitor capabilities, next to the settings.
+    pub fn capability_cache_path() -> Option<PathBuf> {
+        Self::config_path()
+            .ok()
+            .map(|path| path.with_file_name("capabilities.json"))
+    }
+
+    /// Whether settings have somewhere to be saved. Without a config
+    /// location they live in memory for this session only.
+    pub fn is_persistent() -> bool {
+        Self::config_path().is_ok()
     }
 
     pub fn load() -> Self {
-        let path = Self::config_path();
-        if path.exists() {
-            if let Ok(contents) = fs::read_to_string(&path) {
-                if let Ok(settings) = serde_json::from_str(&contents) {
-                    return settings;
+        let path = match Self::config_path() {
+            Ok(path) => path,
+            Err(e) => {
+                eprintln!("Warning: {}; settings will not be saved this session", e);
+                return Self::default();
+            }
+        };
+        Self::load_from(&path)
+    }
+
+    /// Loads settings from `path`. A file that fails to parse is moved aside
+    /// to `<path>.bak`, so the next save does not destroy it.
+    fn load_from(path: &Path) -> Self {
+        let Ok(contents) = fs::read_to_string(path) else {
+            return Self::default();
+        };
+        match serde_json::from_str::<Self>(&contents) {
+            Ok(mut settings) => {
+                settings.migrate_serial_keys();
+                settings
+            }
+            Err(e) => {
+                let mut backup = path.as_os_str().to_owned();
+                backup.push(".bak");
+                let backup = PathBuf::from(backup);
+                match fs::rename(path, &backup) {
+                    Ok(()) => eprintln!(
+                        "Settings file {} is invalid ({}); moved it to {} and using defaults",
+                        path.display(),
+                        e,
+                        backup.display()
+                    ),
+                    Err(rename_error) => eprintln!(
+                        "Settings file {} is invalid ({}) and could not be backed up: {}",
+                        path.display(),
+                        e,
+                        rename_error
+                    ),
+                }
+                Self::default()
+            }
+        }
+    }
+
+    /// Settings with `contents`, an exported settings file, folded in. Only
+    /// keys present in the file change: per-monitor maps and profiles gain
+    /// or replace entries by name, favorites are added, and other values are
+    /// replaced. I2C pins name this machine's connectors and devices, so
+    /// they are never imported.
+    pub fn merge_import(&self, contents: &str) -> Result<Self, String> {
+        let imported: serde_json::Value =
+            serde_json::from_str(contents).map_err(|e| format!("not valid JSON: {}", e))?;
+        let serde_json::Value::Object(imported) = imported else {
+            return Err("expected a JSON object of settings".to_string());
+        };
+        let serde_json::Value::Object(mut merged) =
+            serde_json::to_value(self).map_err(|e| e.to_string())?
+        else {
+            unreachable!("settings serialize to an object");
+        };
+
+        let mut unknown: Vec<&str> = imported
+            .keys()
+            .filter(|key| !merged.contains_key(*key))
+            .map(String::as_str)
+            .collect();
+        if !unknown.is_empty() {
+            unknown.sort();
+            return Err(format!("unknown settings: {}", unknown.join(", ")));
+        }
+
+        for (key, value) in imported {
+            if key == "i2c_device_pins" {
+                continue;
+            }
+            match (merged.get_mut(&key), value) {
+                (Some(serde_json::Value::Object(current)), serde_json::Value::Object(entries)) => {
+                    current.extend(entries);
+                }
+                (Some(serde_json::Value::Array(current)), serde_json::Value::Array(entries))
+                    if key == "favorite_monitors" =>
+                {
+                    for entry in entries {
+                        if !current.contains(&entry) {
+                            current.push(entry);
+                        }
+                    }
+                }
+                (_, value) => {
+                    merged.insert(key, value);
                 }
             }
         }
-        Self::default()
+
+        let settings: Self = serde_json::from_value(serde_json::Value::Object(merged))
+            .map_err(|e| format!("invalid settings: {}", e))?;
+        if !(1..=10).contains(&settings.scroll_step) {
+            return Err(format!(
+                "scroll_step must be between 1 and 10, not {}",
+                settings.scroll_step
+            ));
+        }
+        Ok(settings)
     }
 
-    pub fn save(&self) -> std::io::Result<()> {
-        let path = Self::config_path();
+    pub fn save(&self) -> io::Result<()> {
+        let path = Self::config_path()?;
         if let Some(parent) = path.parent() {
             fs::create_dir_all(parent)?;
         }
@@ -58,3 +677,259 @@ impl AppSettings {
         fs::write(path, contents)
     }
 }
+
+/// Copies the entry under each monitor's name to its key, when the key has
+/// none yet, then drops the names no monitor is keyed by. Monitors of the
+/// same model share a name, so each of them gets the entry.
+fn rekey<V: Clone>(map: &mut HashMap<String, V>, monitors: &[(&str, &str)]) -> bool {
+    let mut moved = false;
+    for &(name, key) in monitors {
+        if name != key && !map.contains_key(key) {
+            if let Some(value) = map.get(name).cloned() {
+                map.insert(key.to_string(), value);
+                moved = true;
+            }
+        }
+    }
+    for &(name, _) in monitors {
+        if !monitors.iter().any(|&(_, key)| key == name) {
+            moved |= map.remove(name).is_some();
+        }
+    }
+    moved
+}
+
+/// `rekey` for a list of monitors: each name becomes the keys of the
+/// monitors with that name, and is kept only if a monitor is keyed by it.
+fn rekey_list(list: &mut Vec<String>, monitors: &[(&str, &str)]) -> bool {
+    let mut rekeyed: Vec<String> = Vec::new();
+    for entry in list.iter() {
+        let named: Vec<&str> = monitors
+            .iter()
+            .filter(|&&(name, key)| name == entry && name != key)
+            .map(|&(_, key)| key)
+            .collect();
+        let keyed = monitors.iter().any(|&(_, key)| key == entry);
+        let entries = (keyed || named.is_empty())
+            .then_some(entry.as_str())
+            .into_iter()
+            .chain(named);
+        for key in entries {
+            if !rekeyed.iter().any(|k| k == key) {
+                rekeyed.push(key.to_string());
+            }
+        }
+    }
+    let moved = rekeyed != *list;
+    *list = rekeyed;
+    moved
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn corrupt_settings_are_kept_as_a_backup() {
+        let dir = std::env::temp_dir().join(format!("brightless-settings-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("settings.json");
+        fs::write(&path, "{ \"scroll_step\": ").unwrap();
+
+        let settings = AppSettings::load_from(&path);
+
+        assert_eq!(settings.scroll_step, AppSettings::default().scroll_step);
+        assert!(!path.exists());
+        assert_eq!(
+            fs::read_to_string(dir.join("settings.json.bak")).unwrap(),
+            "{ \"scroll_step\": "
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn import_merges_per_monitor_entries_and_keeps_pins() {
+        let mut settings = AppSettings::default();
+        settings.monitor_scroll_steps.insert("Left".into(), 3);
+        settings
+            .i2c_device_pins
+            .insert("card0-DP-1".into(), "/dev/i2c-5".into());
+
+        let merged = settings
+            .merge_import(
+                r#"{
+                    "compact_view": true,
+                    "monitor_scroll_steps": { "Right": 5 },
+                    "i2c_device_pins": { "card1-HDMI-A-1": "/dev/i2c-9" }
+                }"#,
+            )
+            .unwrap();
+
+        assert!(merged.compact_view);
+        assert_eq!(merged.scroll_step, settings.scroll_step);
+        assert_eq!(merged.monitor_scroll_steps.get("Left"), Some(&3));
+        assert_eq!(merged.monitor_scroll_steps.get("Right"), Some(&5));
+        assert_eq!(merged.i2c_device_pins, settings.i2c_device_pins);
+
+        assert!(settings.merge_import(r#"{ "scroll_stpe": 4 }"#).is_err());
+        assert!(settings
+            .merge_import(r#"{ "scroll_step": "big" }"#)
+            .is_err());
+        assert!(settings.merge_import("[]").is_err());
+    }
+
+    #[test]
+    fn linked_monitors_follow_the_group_feature_mask() {
+        let settings = AppSettings {
+            link_groups: vec![
+                LinkGroup {
+                    monitors: vec!["Left".into(), "Right".into()],
+                    volume: true,
+                    ..LinkGroup::default()
+                },
+                LinkGroup {
+                    monitors: vec!["Right".into(), "TV".into()],
+                    ..LinkGroup::default()
+                },
+            ],
+            ..AppSettings::default()
+        };
+
+        let brightness = settings.linked_monitors("Right", Feature::Brightness);
+        assert_eq!(brightness, HashSet::from(["Left", "TV"]));
+        let volume = settings.linked_monitors("Right", Feature::Volume);
+        assert_eq!(volume, HashSet::from(["Left"]));
+        assert!(settings
+            .linked_monitors("Left", Feature::Contrast)
+            .is_empty());
+    }
+
+    #[test]
+    fn entries_saved_by_name_move_to_the_serial() {
+        let mut settings = AppSettings::default();
+        settings.monitor_ratios.insert("DELL U2720Q".into(), 0.5);
+        settings
+            .monitor_brightness_min
+            .insert("Monitor 1".into(), 20);
+        settings.favorite_monitors.insert("DELL U2720Q".into());
+        settings.link_groups.push(LinkGroup {
+            monitors: vec!["DELL U2720Q".into(), "Monitor 1".into()],
+            ..LinkGroup::default()
+        });
+        settings.default_monitor = Some("DELL U2720Q".into());
+        let monitors = [
+            ("DELL U2720Q", "ABC123"),
+            ("DELL U2720Q", "DEF456"),
+            ("Monitor 1", "Monitor 1"),
+        ];
+
+        assert!(settings.adopt_monitor_keys(&monitors));
+
+        assert_eq!(settings.monitor_ratios.get("ABC123"), Some(&0.5));
+        assert_eq!(settings.monitor_ratios.get("DEF456"), Some(&0.5));
+        assert!(!settings.monitor_ratios.contains_key("DELL U2720Q"));
+        assert_eq!(settings.monitor_brightness_min.get("Monitor 1"), Some(&20));
+        assert_eq!(
+            settings.favorite_monitors,
+            HashSet::from(["ABC123".to_string(), "DEF456".to_string()])
+        );
+        assert_eq!(
+            settings.link_groups[0].monitors,
+            ["ABC123", "DEF456", "Monitor 1"]
+        );
+        assert_eq!(settings.default_monitor.as_deref(), Some("ABC123"));
+        assert!(!settings.adopt_monitor_keys(&monitors));
+    }
+
+    #[test]
+    fn old_serial_keys_are_migrated_on_load() {
+        let dir = std::env::temp_dir().join(format!("brightless-serials-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("settings.json");
+        fs::write(
+            &path,
+            r#"{
+                "monitor_ratios": { "10ac-3412-00000000-AB12  ": 0.5, "DELL  U2720Q": 0.8 },
+                "favorite_monitors": ["10ac-3412-00000000-CN 0  42"],
+                "link_groups": [{ "monitors": ["10ac-3412-00000000-AB12  ", "TV"] }]
+            }"#,
+        )
+        .unwrap();
+
+        let settings = AppSettings::load_from(&path);
+
+        assert_eq!(
+            settings.monitor_ratios.get("10ac-3412-00000000-AB12"),
+            Some(&0.5)
+        );
+        assert_eq!(settings.monitor_ratios.get("DELL  U2720Q"), Some(&0.8));
+        assert_eq!(
+            settings.favorite_monitors,
+            HashSet::from(["10ac-3412-00000000-CN 0 42".to_string()])
+        );
+        assert_eq!(
+            settings.link_groups[0].monitors,
+            ["10ac-3412-00000000-AB12", "TV"]
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn custom_controls_parse_hex_codes_and_kinds() {
+        let settings: AppSettings = serde_json::from_str(
+            r#"{"custom_controls": [
+                {"vcp_code": "0xE9", "name": "Audio In", "monitors": ["Left"]},
+                {"vcp_code": "8Ah", "name": "Saturation", "kind": "slider"},
+                {"vcp_code": "1FF", "name": "Broken"},
+                {"vcp_code": 233, "name": "Decimal"}
+            ]}"#,
+        )
+        .unwrap();
+        let controls = &settings.custom_controls;
+        assert_eq!(controls[0].code(), Some(0xe9));
+        assert_eq!(controls[0].kind, CustomControlKind::Dropdown);
+        assert_eq!(controls[1].code(), Some(0x8a));
+        assert_eq!(controls[1].kind, CustomControlKind::Slider);
+        assert_eq!(controls[2].code(), None);
+        assert_eq!(controls[3].code(), Some(0xe9));
+        assert_eq!(settings.custom_controls_for("Right").len(), 3);
+        assert_eq!(settings.custom_controls_for("Left").len(), 4);
+    }
+
+    #[test]
+    fn config_path_prefers_overrides() {
+        let cli = Some(PathBuf::from("/tmp/cli.json"));
+        let env = Some(PathBuf::from("/tmp/env.json"));
+        let dir = Some(PathBuf::from("/home/user/.config"));
+
+        assert_eq!(
+            resolve_config_path(cli, env.clone(), None, dir.clone()).unwrap(),
+            PathBuf::from("/tmp/cli.json")
+        );
+        assert_eq!(
+            resolve_config_path(None, env, None, dir.clone()).unwrap(),
+            PathBuf::from("/tmp/env.json")
+        );
+        assert_eq!(
+            resolve_config_path(None, None, None, dir).unwrap(),
+            PathBuf::from("/home/user/.config/brightless/settings.json")
+        );
+    }
+
+    #[test]
+    fn config_path_follows_profile() {
+        let dir = Some(PathBuf::from("/home/user/.config"));
+        assert_eq!(
+            resolve_config_path(None, None, Some("laptop".to_string()), dir.clone()).unwrap(),
+            PathBuf::from("/home/user/.config/brightless/laptop.json")
+        );
+        assert!(resolve_config_path(None, None, Some("../x".to_string()), dir).is_err());
+    }
+
+    #[test]
+    fn config_path_errors_without_any_location() {
+        assert!(resolve_config_path(None, None, None, None).is_err());
+        let relative = Some(PathBuf::from("."));
+        assert!(resolve_config_path(None, None, None, relative).is_err());
+    }
+}
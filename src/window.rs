@@ -1,38 +1,330 @@
-use crate::ddc_manager::{DdcError, DdcManager};
+use crate::ddc_manager::{
+    ColorChannel, DdcError, DdcManager, DiscoveryReport, DisplayTechnology, Feature, MonitorState,
+    PowerMode, Rediscovery, WriteOutcome, IDENTIFY_PULSE_MS,
+};
 use crate::monitor_row::MonitorRow;
-use crate::settings::AppSettings;
+use crate::profiles::Profile;
+use crate::schedule;
+use crate::settings::{parse_vcp_code, AppSettings, ColorScheme, CustomControl};
+use crate::window_position;
 use adw::prelude::*;
-use adw::{Application, ApplicationWindow, HeaderBar, ToolbarView, ViewStack, ViewSwitcher};
+use adw::{
+    Application, ApplicationWindow, ExpanderRow, HeaderBar, Toast, ToastOverlay, ToolbarView,
+    ViewStack, ViewSwitcher,
+};
+#[cfg(feature = "http")]
+use brightless::http;
 use glib::Propagation;
+use gtk::gio;
 use gtk::{
-    Box, Button, EventControllerScroll, EventControllerScrollFlags, Label, ListBox, Orientation,
-    Popover, Scale, ScrolledWindow, SelectionMode, Switch,
+    Box, Button, CheckButton, DropDown, Entry, EventControllerScroll, EventControllerScrollFlags,
+    Label, ListBox, Orientation, Popover, Scale, ScrolledWindow, SelectionMode, SpinButton,
+    StringList, Switch,
 };
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::rc::Rc;
+#[cfg(feature = "http")]
+use std::sync::mpsc::Receiver;
+use std::time::Duration;
+
+/// How often monitors found asleep at startup are checked for waking up
+/// when polling is off.
+const WAKE_CHECK_INTERVAL_SECS: u32 = 5;
+
+/// How long "Wake All" waits for monitors to come back before re-reading
+/// their values.
+const WAKE_SETTLE_MS: u64 = 2000;
+
+/// Length of one step of the "Identify Monitors" sequence.
+const IDENTIFY_STEP_MS: u64 = 400;
+
+/// How many times "Identify Monitors" tries to restore a pulsed monitor's
+/// brightness, one step apart, before reporting it as left pulsed.
+const IDENTIFY_RESTORE_ATTEMPTS: u32 = 3;
+
+/// How often queued HTTP requests are answered.
+#[cfg(feature = "http")]
+const HTTP_POLL_MS: u64 = 100;
+
+/// Each row's scroll step as `(name, settings key, step)`, shared with the
+/// settings popover.
+type RowScrollSteps = Rc<RefCell<Vec<(String, String, Rc<Cell<u8>>)>>>;
+
+/// Whether each row's sliders follow scrolling, by settings key.
+type RowScrollAdjust = Rc<RefCell<Vec<(String, Rc<Cell<bool>>)>>>;
+
+/// A warning button whose popover lists what discovery made of every
+/// connected display, shown when some of them could not be used.
+fn discovery_report_button(report: &DiscoveryReport) -> gtk::MenuButton {
+    let label = Label::new(Some(&report.to_string()));
+    label.set_wrap(true);
+    label.set_max_width_chars(60);
+    label.set_selectable(true);
+    label.set_margin_top(8);
+    label.set_margin_bottom(8);
+    label.set_margin_start(8);
+    label.set_margin_end(8);
+    let popover = Popover::new();
+    popover.set_child(Some(&label));
+    gtk::MenuButton::builder()
+        .icon_name("dialog-warning-symbolic")
+        .tooltip_text("Some monitors could not be used")
+        .popover(&popover)
+        .build()
+}
+
+/// Asks for the PIN that unlocks the controls and passes what was entered
+/// to `callback`, unless the dialog is cancelled.
+fn ask_unlock_pin<F>(window: &ApplicationWindow, callback: F)
+where
+    F: Fn(String) + 'static,
+{
+    let entry = gtk::PasswordEntry::new();
+    entry.set_activates_default(true);
+    let dialog = adw::AlertDialog::new(Some("Unlock Controls"), Some("Enter the PIN"));
+    dialog.set_extra_child(Some(&entry));
+    dialog.add_responses(&[("cancel", "Cancel"), ("unlock", "Unlock")]);
+    dialog.set_response_appearance("unlock", adw::ResponseAppearance::Suggested);
+    dialog.set_default_response(Some("unlock"));
+    dialog.set_close_response("cancel");
+    dialog.connect_response(Some("unlock"), move |_, _| {
+        callback(entry.text().to_string())
+    });
+    dialog.present(Some(window));
+}
+
+/// Steps of the "Identify Monitors" sequence, one every `IDENTIFY_STEP_MS`:
+/// the n-th monitor in `order` pulses n times, each pulse a step at the far
+/// end of its brightness and a step back, with a longer pause before the
+/// next monitor. `None` is a pause.
+fn identify_sequence(order: impl IntoIterator<Item = usize>) -> Vec<Option<(usize, bool)>> {
+    let mut steps = Vec::new();
+    for (position, index) in order.into_iter().enumerate() {
+        if position > 0 {
+            steps.extend([None, None]);
+        }
+        for pulse in 0..=position {
+            if pulse > 0 {
+                steps.push(None);
+            }
+            steps.push(Some((index, true)));
+            steps.push(Some((index, false)));
+        }
+    }
+    steps
+}
+
+/// Pulses monitor `index` like `DdcManager::identify`, but restores it from
+/// a timeout instead of sleeping on the main loop. `identifying` is set
+/// while the pulse lasts, so no other identify reads the pulsed brightness
+/// as the one to restore; nothing happens if it already is.
+fn identify_monitor(
+    ddc: &Rc<RefCell<DdcManager>>,
+    index: usize,
+    identifying: &Rc<Cell<bool>>,
+    on_error: impl Fn(DdcError) + 'static,
+) {
+    if identifying.get() {
+        return;
+    }
+    let Ok(mut manager) = ddc.try_borrow_mut() else {
+        return;
+    };
+    let raw = match manager.start_identify_pulse(index) {
+        Ok(raw) => raw,
+        Err(e) => return on_error(e),
+    };
+    identifying.set(true);
+    let ddc = ddc.clone();
+    let identifying = identifying.clone();
+    glib::timeout_add_local_once(Duration::from_millis(IDENTIFY_PULSE_MS), move || {
+        if let Err(e) = ddc.borrow_mut().end_identify_pulse(index, raw) {
+            on_error(e);
+        }
+        identifying.set(false);
+    });
+}
+
+/// Toast text for what "Detect Monitors" found, e.g. "Added DELL U2720Q;
+/// removed LG HDR 4K".
+fn describe_rediscovery(changes: &Rediscovery) -> String {
+    let added = changes.added.join(", ");
+    let removed = changes.removed.join(", ");
+    match (added.is_empty(), removed.is_empty()) {
+        (false, true) => format!("Added {}", added),
+        (true, false) => format!("Removed {}", removed),
+        _ => format!("Added {}; removed {}", added, removed),
+    }
+}
+
+/// Builds the primary menu and registers its actions on the application.
+fn app_menu_button(
+    app: &Application,
+    window: &ApplicationWindow,
+    monitor_count: usize,
+) -> gtk::MenuButton {
+    let about = gio::SimpleAction::new("about", None);
+    let parent = window.clone();
+    about.connect_activate(move |_, _| {
+        let comments = match monitor_count {
+            1 => "1 monitor detected".to_string(),
+            n => format!("{} monitors detected", n),
+        };
+        adw::AboutDialog::builder()
+            .application_name("Brightless")
+            .application_icon("com.brightless.app")
+            .version(env!("CARGO_PKG_VERSION"))
+            .comments(comments)
+            .license_type(gtk::License::Gpl30Only)
+            .build()
+            .present(Some(&parent));
+    });
+    app.add_action(&about);
+
+    let shortcuts = gio::SimpleAction::new("shortcuts", None);
+    let parent = window.clone();
+    shortcuts.connect_activate(move |_, _| {
+        let section = adw::ShortcutsSection::new(Some("General"));
+        section.add(adw::ShortcutsItem::from_action(
+            "Keyboard Shortcuts",
+            "app.shortcuts",
+        ));
+        section.add(adw::ShortcutsItem::from_action(
+            "Reset Brightness to 50%",
+            "app.reset-brightness",
+        ));
+        section.add(adw::ShortcutsItem::from_action(
+            "Identify Monitors",
+            "app.identify-monitors",
+        ));
+        section.add(adw::ShortcutsItem::from_action(
+            "Detect Monitors",
+            "app.detect-monitors",
+        ));
+        section.add(adw::ShortcutsItem::from_action(
+            "Lock Controls",
+            "app.lock-controls",
+        ));
+        section.add(adw::ShortcutsItem::from_action("Quit", "app.quit"));
+        let dialog = adw::ShortcutsDialog::new();
+        dialog.add(section);
+        dialog.present(Some(&parent));
+    });
+    app.add_action(&shortcuts);
+    app.set_accels_for_action("app.shortcuts", &["<Control>question"]);
+
+    let quit = gio::SimpleAction::new("quit", None);
+    let parent = window.clone();
+    // Closing rather than quitting runs the window's save-on-close handler;
+    // a window started hidden would otherwise only hide.
+    quit.connect_activate(move |_, _| {
+        parent.set_hide_on_close(false);
+        parent.close();
+    });
+    app.add_action(&quit);
+    app.set_accels_for_action("app.quit", &["<Control>q"]);
+
+    let menu = gio::Menu::new();
+    menu.append(
+        Some("Reset Brightness to 50%"),
+        Some("app.reset-brightness"),
+    );
+    menu.append(Some("Identify Monitors"), Some("app.identify-monitors"));
+    menu.append(Some("Detect Monitors"), Some("app.detect-monitors"));
+    menu.append(Some("Lock Controls"), Some("app.lock-controls"));
+    menu.append(Some("Keyboard Shortcuts"), Some("app.shortcuts"));
+    menu.append(Some("About Brightless"), Some("app.about"));
+    menu.append(Some("Quit"), Some("app.quit"));
+    gtk::MenuButton::builder()
+        .icon_name("open-menu-symbolic")
+        .tooltip_text("Main Menu")
+        .primary(true)
+        .menu_model(&menu)
+        .build()
+}
 
 pub struct MainWindow {
     pub window: ApplicationWindow,
     pub stack: ViewStack,
     pub monitor_rows: Rc<RefCell<Vec<MonitorRow>>>,
+    toast_overlay: ToastOverlay,
+    profile_dropdown: DropDown,
+    /// Set while the header dropdown is changed to reflect state rather
+    /// than to apply a profile.
+    profile_quiet: Rc<Cell<bool>>,
     ddc: Rc<RefCell<DdcManager>>,
     settings: Rc<RefCell<AppSettings>>,
+    /// Set once "Detect Monitors" has replaced this window, so its timers
+    /// stop.
+    retired: Rc<Cell<bool>>,
+}
+
+/// What a window rebuilt by "Detect Monitors" takes over from the one it
+/// replaces.
+#[derive(Default)]
+struct Handover {
+    #[cfg(feature = "http")]
+    http_requests: Option<Rc<Receiver<http::Pending>>>,
 }
 
 impl MainWindow {
-    pub fn new(app: &Application) -> Result<Self, DdcError> {
-        let ddc = DdcManager::new()?;
-        let monitor_count = ddc.monitors.len();
+    /// Builds the window around monitors already discovered, as with
+    /// `DdcManager::discover`.
+    pub fn new(app: &Application, ddc: DdcManager) -> Self {
+        Self::build(app, Rc::new(RefCell::new(ddc)), Handover::default())
+    }
+
+    #[cfg_attr(not(feature = "http"), allow(unused_variables))]
+    fn build(app: &Application, ddc_ref: Rc<RefCell<DdcManager>>, handover: Handover) -> Self {
         let settings = Rc::new(RefCell::new(AppSettings::load()));
-        let scroll_step = settings.borrow().scroll_step;
+        {
+            let ddc = ddc_ref.borrow();
+            let keys: Vec<(&str, &str)> = ddc
+                .monitors
+                .iter()
+                .map(|m| (m.name.as_str(), m.settings_key()))
+                .collect();
+            if settings.borrow_mut().adopt_monitor_keys(&keys) {
+                let _ = settings.borrow().save();
+            }
+        }
+        for failure in &ddc_ref.borrow().failures {
+            eprintln!("Skipping {}: {}", failure.connector, failure.error);
+        }
+        let monitor_count = ddc_ref.borrow().monitors.len();
+        let scroll_step = Rc::new(Cell::new(settings.borrow().scroll_step));
+        // Each row's step, kept in sync with the global one unless the
+        // monitor has its own.
+        let monitor_scroll_steps: RowScrollSteps = Rc::new(RefCell::new(Vec::new()));
+        let monitor_scroll_adjust: RowScrollAdjust = Rc::new(RefCell::new(Vec::new()));
+        // Set while "Identify Monitors" or a single monitor's pulse runs, so
+        // no other identify reads a pulsed brightness as the one to restore.
+        let identifying = Rc::new(Cell::new(false));
+        let retired = Rc::new(Cell::new(false));
 
         let window = ApplicationWindow::builder()
             .application(app)
             .title("Brightless")
-            .default_width(400)
-            .default_height(300)
+            .default_width(settings.borrow().window_width)
+            .default_height(settings.borrow().window_height)
+            .maximized(settings.borrow().window_maximized)
             .build();
+        // A window can only be moved once it is mapped, which happens again
+        // each time it is shown from the background.
+        let settings_map = settings.clone();
+        window.connect_map(move |window| {
+            let settings = settings_map.borrow();
+            if let (Some(x), Some(y), false) = (
+                settings.window_x,
+                settings.window_y,
+                settings.window_maximized,
+            ) {
+                window_position::move_to(window, x, y);
+            }
+        });
 
+        let toast_overlay = ToastOverlay::new();
         let toolbar_view = ToolbarView::new();
         let header_bar = HeaderBar::new();
         toolbar_view.add_top_bar(&header_bar);
@@ -63,11 +355,19 @@ impl MainWindow {
         popover_box.set_margin_bottom(12);
         popover_box.set_margin_start(12);
 
+        // Rarely changed options, shown under the "Advanced" expander at the
+        // end of the popover.
+        let advanced_box = Box::new(Orientation::Vertical, 12);
+        advanced_box.set_margin_top(12);
+        advanced_box.set_margin_end(12);
+        advanced_box.set_margin_bottom(12);
+        advanced_box.set_margin_start(12);
+
         let scroll_step_label = Label::new(Some("Scroll Step:"));
         scroll_step_label.set_halign(gtk::Align::Start);
         popover_box.append(&scroll_step_label);
 
-        let scroll_step_value_label = Label::new(Some(&format!("{}%", scroll_step)));
+        let scroll_step_value_label = Label::new(Some(&format!("{}%", scroll_step.get())));
         scroll_step_value_label.set_halign(gtk::Align::End);
         scroll_step_value_label.set_hexpand(true);
         popover_box.append(&scroll_step_value_label);
@@ -79,14 +379,20 @@ impl MainWindow {
         scroll_step_scale.set_range(1.0, 10.0);
         scroll_step_scale.set_digits(0);
         scroll_step_scale.set_draw_value(false);
-        scroll_step_scale.set_value(scroll_step as f64);
+        scroll_step_scale.set_value(scroll_step.get() as f64);
 
         let scroll_step_value_label_inner = scroll_step_value_label.clone();
+        let scroll_step_inner = scroll_step.clone();
+        let monitor_scroll_steps_inner = monitor_scroll_steps.clone();
         let adjustment = scroll_step_scale.adjustment();
         adjustment.connect_value_changed(move |adj| {
             let val = adj.value() as u8;
             scroll_step_value_label_inner.set_text(&format!("{}%", val));
+            scroll_step_inner.set(val);
             settings_inner.borrow_mut().scroll_step = val;
+            for (_, key, step) in monitor_scroll_steps_inner.borrow().iter() {
+                step.set(settings_inner.borrow().scroll_step_for(key));
+            }
             let _ = settings_inner.borrow().save();
         });
 
@@ -113,6 +419,204 @@ impl MainWindow {
 
         popover_box.append(&scroll_step_scale);
 
+        let scroll_adjust_row = Box::new(Orientation::Horizontal, 8);
+        let scroll_adjust_label = Label::new(Some("Scroll to Adjust"));
+        scroll_adjust_label.set_hexpand(true);
+        scroll_adjust_label.set_halign(gtk::Align::Start);
+        let scroll_adjust_switch = Switch::new();
+        scroll_adjust_switch.set_active(settings.borrow().scroll_adjust);
+        let settings_scroll_adjust = settings.clone();
+        let monitor_scroll_adjust_inner = monitor_scroll_adjust.clone();
+        scroll_adjust_switch.connect_state_set(move |_, state| {
+            settings_scroll_adjust.borrow_mut().scroll_adjust = state;
+            for (key, enabled) in monitor_scroll_adjust_inner.borrow().iter() {
+                enabled.set(settings_scroll_adjust.borrow().scroll_adjust_for(key));
+            }
+            let _ = settings_scroll_adjust.borrow().save();
+            Propagation::Proceed
+        });
+        scroll_adjust_row.append(&scroll_adjust_label);
+        scroll_adjust_row.append(&scroll_adjust_switch);
+        scroll_adjust_row.set_tooltip_text(Some(
+            "When off, scrolling over a slider scrolls the list instead of changing the value",
+        ));
+        popover_box.append(&scroll_adjust_row);
+
+        let scroll_step_per_monitor_box = Box::new(Orientation::Vertical, 8);
+        popover_box.append(&scroll_step_per_monitor_box);
+
+        // --- Appearance Section ---
+        let appearance_section_label = Label::new(Some("Appearance"));
+        appearance_section_label.set_halign(gtk::Align::Start);
+        appearance_section_label.add_css_class("heading");
+        popover_box.append(&appearance_section_label);
+
+        let color_scheme_row = Box::new(Orientation::Horizontal, 8);
+        let color_scheme_label = Label::new(Some("Color Scheme:"));
+        color_scheme_label.set_hexpand(true);
+        color_scheme_label.set_halign(gtk::Align::Start);
+        let color_scheme_names: Vec<&str> = ColorScheme::ALL.iter().map(|c| c.name()).collect();
+        let color_scheme_dropdown = DropDown::from_strings(&color_scheme_names);
+        let current_scheme = settings.borrow().color_scheme;
+        if let Some(pos) = ColorScheme::ALL.iter().position(|c| *c == current_scheme) {
+            color_scheme_dropdown.set_selected(pos as u32);
+        }
+        let settings_scheme = settings.clone();
+        color_scheme_dropdown.connect_selected_notify(move |dropdown| {
+            if let Some(&scheme) = ColorScheme::ALL.get(dropdown.selected() as usize) {
+                adw::StyleManager::default().set_color_scheme(scheme.to_adw());
+                settings_scheme.borrow_mut().color_scheme = scheme;
+                let _ = settings_scheme.borrow().save();
+            }
+        });
+        color_scheme_row.append(&color_scheme_label);
+        color_scheme_row.append(&color_scheme_dropdown);
+        popover_box.append(&color_scheme_row);
+
+        let compact_row = Box::new(Orientation::Horizontal, 8);
+        let compact_label = Label::new(Some("Compact View"));
+        compact_label.set_hexpand(true);
+        compact_label.set_halign(gtk::Align::Start);
+        let compact_switch = Switch::new();
+        compact_switch.set_active(settings.borrow().compact_view);
+        let settings_compact = settings.clone();
+        compact_switch.connect_state_set(move |_, state| {
+            settings_compact.borrow_mut().compact_view = state;
+            let _ = settings_compact.borrow().save();
+            Propagation::Proceed
+        });
+        compact_row.append(&compact_label);
+        compact_row.append(&compact_switch);
+        compact_row.set_tooltip_text(Some("Applies after restart"));
+        popover_box.append(&compact_row);
+
+        let perceptual_row = Box::new(Orientation::Horizontal, 8);
+        let perceptual_label = Label::new(Some("Perceptual Brightness"));
+        perceptual_label.set_hexpand(true);
+        perceptual_label.set_halign(gtk::Align::Start);
+        let perceptual_switch = Switch::new();
+        perceptual_switch.set_active(settings.borrow().perceptual_brightness);
+        perceptual_row.append(&perceptual_label);
+        perceptual_row.append(&perceptual_switch);
+        perceptual_row.set_tooltip_text(Some(
+            "Equal slider steps look like equal brightness changes",
+        ));
+        advanced_box.append(&perceptual_row);
+
+        let start_hidden_row = Box::new(Orientation::Horizontal, 8);
+        let start_hidden_label = Label::new(Some("Start Hidden"));
+        start_hidden_label.set_hexpand(true);
+        start_hidden_label.set_halign(gtk::Align::Start);
+        let start_hidden_switch = Switch::new();
+        start_hidden_switch.set_active(settings.borrow().start_hidden);
+        let settings_start_hidden = settings.clone();
+        start_hidden_switch.connect_state_set(move |_, state| {
+            settings_start_hidden.borrow_mut().start_hidden = state;
+            let _ = settings_start_hidden.borrow().save();
+            Propagation::Proceed
+        });
+        start_hidden_row.append(&start_hidden_label);
+        start_hidden_row.append(&start_hidden_switch);
+        start_hidden_row.set_tooltip_text(Some(
+            "Keep running in the background at launch; open Brightless again to show the window",
+        ));
+        advanced_box.append(&start_hidden_row);
+
+        let connector_details_row = Box::new(Orientation::Horizontal, 8);
+        let connector_details_label = Label::new(Some("Show Port and Resolution"));
+        connector_details_label.set_hexpand(true);
+        connector_details_label.set_halign(gtk::Align::Start);
+        let connector_details_switch = Switch::new();
+        connector_details_switch.set_active(settings.borrow().show_connector_details);
+        let settings_connector_details = settings.clone();
+        connector_details_switch.connect_state_set(move |_, state| {
+            settings_connector_details
+                .borrow_mut()
+                .show_connector_details = state;
+            let _ = settings_connector_details.borrow().save();
+            Propagation::Proceed
+        });
+        connector_details_row.append(&connector_details_label);
+        connector_details_row.append(&connector_details_switch);
+        connector_details_row.set_tooltip_text(Some("Applies after restart"));
+        advanced_box.append(&connector_details_row);
+
+        let forget_capabilities_button = Button::with_label("Probe Monitors Again");
+        forget_capabilities_button.set_tooltip_text(Some(
+            "Forget the cached monitor capabilities; applies after restart",
+        ));
+        let toast_forget = toast_overlay.clone();
+        forget_capabilities_button.connect_clicked(move |_| {
+            let Some(path) = AppSettings::capability_cache_path() else {
+                return;
+            };
+            match std::fs::remove_file(&path) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => toast_forget.add_toast(
+                    Toast::new(&format!("Couldn't clear the capability cache: {}", e)),
+                ),
+                _ => toast_forget.add_toast(Toast::new(
+                    "Monitors will be probed again on the next launch",
+                )),
+            }
+        });
+        advanced_box.append(&forget_capabilities_button);
+
+        // Link groups come from the settings file; here only which sliders
+        // each group moves together can be changed.
+        for (group_index, group) in settings.borrow().link_groups.iter().enumerate() {
+            let names: Vec<String> = {
+                let ddc = ddc_ref.borrow();
+                group
+                    .monitors
+                    .iter()
+                    .map(|key| {
+                        ddc.monitors
+                            .iter()
+                            .find(|m| m.settings_key() == key)
+                            .map_or_else(|| key.clone(), |m| m.name.clone())
+                    })
+                    .collect()
+            };
+            let group_label = Label::new(Some(&format!("Linked: {}", names.join(" + "))));
+            group_label.set_halign(gtk::Align::Start);
+            group_label.set_wrap(true);
+            advanced_box.append(&group_label);
+
+            let features_row = Box::new(Orientation::Horizontal, 8);
+            for (feature, title) in [
+                (Feature::Brightness, "Brightness"),
+                (Feature::Contrast, "Contrast"),
+                (Feature::Volume, "Volume"),
+            ] {
+                let check = CheckButton::with_label(title);
+                check.set_active(group.links(feature));
+                let settings_link = settings.clone();
+                check.connect_toggled(move |check| {
+                    if let Some(group) = settings_link.borrow_mut().link_groups.get_mut(group_index)
+                    {
+                        match feature {
+                            Feature::Brightness => group.brightness = check.is_active(),
+                            Feature::Contrast => group.contrast = check.is_active(),
+                            Feature::Volume => group.volume = check.is_active(),
+                        }
+                    }
+                    let _ = settings_link.borrow().save();
+                });
+                features_row.append(&check);
+            }
+            advanced_box.append(&features_row);
+        }
+
+        let high_contrast_row = Box::new(Orientation::Horizontal, 8);
+        let high_contrast_label = Label::new(Some("High Contrast Sliders"));
+        high_contrast_label.set_hexpand(true);
+        high_contrast_label.set_halign(gtk::Align::Start);
+        let high_contrast_switch = Switch::new();
+        high_contrast_switch.set_active(settings.borrow().high_contrast_sliders);
+        high_contrast_row.append(&high_contrast_label);
+        high_contrast_row.append(&high_contrast_switch);
+        popover_box.append(&high_contrast_row);
+
         // --- Dynamic Contrast Section ---
         let dc_section_label = Label::new(Some("Dynamic Contrast"));
         dc_section_label.set_halign(gtk::Align::Start);
@@ -182,20 +686,34 @@ impl MainWindow {
         dc_per_monitor_box.set_visible(settings.borrow().dynamic_contrast_per_monitor_ratio);
         dc_sub_box.append(&dc_per_monitor_box);
 
-        popover.set_child(Some(&popover_box));
+        let popover_scroll = ScrolledWindow::builder()
+            .hscrollbar_policy(gtk::PolicyType::Never)
+            .propagate_natural_height(true)
+            .max_content_height(560)
+            .child(&popover_box)
+            .build();
+        popover.set_child(Some(&popover_scroll));
 
         settings_button.connect_clicked(move |_| {
             popover.popup();
         });
 
+        header_bar.pack_end(&app_menu_button(app, &window, monitor_count));
+        if ddc_ref.borrow().report.has_failures() {
+            header_bar.pack_end(&discovery_report_button(&ddc_ref.borrow().report));
+        }
         header_bar.pack_end(&settings_button);
 
         let mut monitor_rows_vec = Vec::new();
-        let ddc_ref = Rc::new(RefCell::new(ddc));
+        let writer = SliderWriter::new(ddc_ref.clone(), settings.borrow().write_coalesce_ms);
 
+        settings
+            .borrow()
+            .apply_brightness_floors(&mut ddc_ref.borrow_mut());
         for i in 0..monitor_count {
             let (
                 name,
+                key,
                 min_brightness,
                 max_brightness,
                 min_contrast,
@@ -204,10 +722,16 @@ impl MainWindow {
                 max_volume,
                 supports_input_source,
                 supports_power_mode,
+                supports_osd_lock,
+                osd_lock_writable,
+                supports_invert,
+                rgb_bias_channels,
+                color_temperature,
             ) = {
                 let ddc = ddc_ref.borrow();
                 (
                     ddc.monitors[i].name.clone(),
+                    ddc.monitors[i].settings_key().to_string(),
                     ddc.monitors[i].min_brightness,
                     ddc.monitors[i].max_brightness,
                     ddc.monitors[i].min_contrast,
@@ -216,26 +740,81 @@ impl MainWindow {
                     ddc.monitors[i].max_volume,
                     ddc.monitors[i].supports_input_source,
                     ddc.monitors[i].supports_power_mode,
+                    ddc.monitors[i].supports_osd_lock,
+                    ddc.monitors[i].osd_lock_writable,
+                    ddc.monitors[i].supports_invert,
+                    ColorChannel::ALL
+                        .into_iter()
+                        .filter(|&c| ddc.supports_rgb_bias(i, c))
+                        .collect::<Vec<_>>(),
+                    ddc.color_temperature_control(i),
                 )
             };
 
+            // Custom controls only show on monitors that answer their code,
+            // as `(control, code, value, maximum)`.
+            let custom_values: Vec<(CustomControl, u8, u16, u16)> = settings
+                .borrow()
+                .custom_controls_for(&key)
+                .into_iter()
+                .filter_map(|control| {
+                    let Some(code) = control.code() else {
+                        eprintln!(
+                            "Custom control \"{}\": \"{}\" is not a VCP code in hex, ignoring",
+                            control.name, control.vcp_code
+                        );
+                        return None;
+                    };
+                    let (value, max) = ddc_ref.borrow_mut().get_raw_vcp(i, code).ok()?;
+                    Some((control.clone(), code, value, max))
+                })
+                .collect();
+
+            // A custom control for the same code takes the place of its
+            // generic toggle.
+            let vendor_toggles: Vec<(u8, bool)> = {
+                let mut ddc = ddc_ref.borrow_mut();
+                let codes: Vec<u8> = ddc
+                    .vendor_toggles(i)
+                    .iter()
+                    .map(|t| t.code)
+                    .filter(|code| !custom_values.iter().any(|(_, c, _, _)| c == code))
+                    .collect();
+                codes
+                    .into_iter()
+                    .filter_map(|code| Some((code, ddc.get_vendor_toggle(i, code).ok()?)))
+                    .collect()
+            };
+
+            let row_scroll_step = Rc::new(Cell::new(settings.borrow().scroll_step_for(&key)));
+            monitor_scroll_steps.borrow_mut().push((
+                name.clone(),
+                key.clone(),
+                row_scroll_step.clone(),
+            ));
+            let row_scroll_adjust = Rc::new(Cell::new(settings.borrow().scroll_adjust_for(&key)));
+            monitor_scroll_adjust
+                .borrow_mut()
+                .push((key.clone(), row_scroll_adjust.clone()));
+
             let dc_enabled_for_monitor = if settings.borrow().dynamic_contrast_enabled {
                 if settings.borrow().dynamic_contrast_global {
                     true
                 } else {
-                    *settings.borrow().monitor_dynamic_contrast.get(&name).unwrap_or(&true)
+                    *settings.borrow().monitor_dynamic_contrast.get(&key).unwrap_or(&true)
                 }
             } else {
                 false
             };
             let ratio = if settings.borrow().dynamic_contrast_per_monitor_ratio {
-                *settings.borrow().monitor_ratios.get(&name).unwrap_or(&settings.borrow().dynamic_contrast_ratio)
+                *settings.borrow().monitor_ratios.get(&key).unwrap_or(&settings.borrow().dynamic_contrast_ratio)
             } else {
                 settings.borrow().dynamic_contrast_ratio
             };
 
             let row = MonitorRow::new(
                 name.clone(),
+                key.clone(),
                 min_brightness,
                 max_brightness,
                 min_contrast,
@@ -244,40 +823,128 @@ impl MainWindow {
                 max_volume,
                 supports_input_source,
                 supports_power_mode,
-                scroll_step,
+                supports_osd_lock,
+                osd_lock_writable,
+                supports_invert,
+                row_scroll_step.clone(),
+                row_scroll_adjust,
                 dc_enabled_for_monitor,
                 settings.borrow().dynamic_contrast_global,
                 ratio,
+                settings.borrow().compact_view,
+                &rgb_bias_channels,
+                &color_temperature,
+                &profile_brightness_marks(&settings.borrow(), &key),
+                &custom_values
+                    .iter()
+                    .map(|(control, _, _, max)| (control, *max))
+                    .collect::<Vec<_>>(),
+                &vendor_toggles
+                    .iter()
+                    .map(|(code, _)| *code)
+                    .collect::<Vec<_>>(),
+            );
+            for (_, code, value, _) in &custom_values {
+                row.set_custom_control(*code, *value);
+            }
+            for (code, on) in &vendor_toggles {
+                row.set_vendor_toggle(*code, *on);
+            }
+
+            if settings.borrow().high_contrast_sliders {
+                row.set_high_contrast(true);
+            }
+
+            let subtitle = monitor_subtitle(
+                &ddc_ref.borrow(),
+                i,
+                settings.borrow().show_connector_details,
             );
+            if !subtitle.is_empty() {
+                row.set_subtitle(&subtitle);
+            }
+            row.set_feature_summary(
+                ddc_ref.borrow().display_technology(i).name(),
+                &ddc_ref.borrow().feature_summary(i),
+            );
+            row.set_live_update(settings.borrow().live_update);
+            row.set_brightness_curve(ddc_ref.borrow().brightness_curve());
+            if !ddc_ref.borrow().is_writable(i) {
+                row.set_read_only(true);
+            }
 
-            let ddc_clone = ddc_ref.clone();
+            let writer_clone = writer.clone();
             let idx = i;
-            row.connect_brightness_changed(move |value| {
-                if let Ok(mut ddc) = ddc_clone.try_borrow_mut() {
-                    let _ = ddc.set_brightness_percentage(idx, value);
+            let settings_clone = settings.clone();
+            let key_clone = key.clone();
+            let ddc_clone_hold = ddc_ref.clone();
+            let brightness_scale = row.brightness_scale.clone();
+            row.connect_brightness_raw_changed(move |raw, _| {
+                if writer_clone.write_brightness_raw(idx, raw, Some(&brightness_scale)) {
+                    note_schedule_override(
+                        &ddc_clone_hold,
+                        idx,
+                        &settings_clone.borrow(),
+                        &key_clone,
+                    );
                 }
             });
 
-            let ddc_clone2 = ddc_ref.clone();
+            let writer_clone2 = writer.clone();
             let idx2 = i;
-            if row.has_contrast() {
+            if let Some(contrast_scale) = row.contrast_scale.clone() {
                 row.connect_contrast_changed(move |value| {
-                    if let Ok(mut ddc) = ddc_clone2.try_borrow_mut() {
-                        let _ = ddc.set_contrast_percentage(idx2, value);
-                    }
+                    writer_clone2.write(idx2, Feature::Contrast, value, Some(&contrast_scale));
                 });
             }
 
-            let ddc_clone3 = ddc_ref.clone();
+            let writer_clone3 = writer.clone();
             let idx3 = i;
-            if row.has_volume() {
+            if let Some(volume_scale) = row.volume_scale.clone() {
                 row.connect_volume_changed(move |value| {
-                    if let Ok(mut ddc) = ddc_clone3.try_borrow_mut() {
-                        let _ = ddc.set_volume_percentage(idx3, value);
-                    }
+                    writer_clone3.write(idx3, Feature::Volume, value, Some(&volume_scale));
                 });
             }
 
+            let ddc_identify = ddc_ref.clone();
+            let toast_identify = toast_overlay.clone();
+            let name_identify = name.clone();
+            let identifying_row = identifying.clone();
+            row.connect_identify(move || {
+                let toast = toast_identify.clone();
+                let name = name_identify.clone();
+                identify_monitor(&ddc_identify, i, &identifying_row, move |e| {
+                    toast.add_toast(Toast::new(&format!("Couldn't identify {}: {}", name, e)));
+                });
+            });
+
+            let ddc_hover = ddc_ref.clone();
+            let settings_hover = settings.clone();
+            let identifying_hover = identifying.clone();
+            row.connect_hover(move || {
+                {
+                    let settings = settings_hover.borrow();
+                    if !settings.identify_on_hover || settings.controls_locked {
+                        return;
+                    }
+                }
+                identify_monitor(&ddc_hover, i, &identifying_hover, |_| {});
+            });
+
+            let ddc_bias = ddc_ref.clone();
+            row.connect_rgb_bias_changed(move |channel, value| {
+                if let Ok(mut ddc) = ddc_bias.try_borrow_mut() {
+                    let _ = ddc.set_rgb_bias(i, channel, value);
+                }
+            });
+
+            let ddc_temperature = ddc_ref.clone();
+            row.connect_color_temperature_changed(move |kelvin| {
+                if let Ok(mut ddc) = ddc_temperature.try_borrow_mut() {
+                    let _ = ddc.set_color_temperature(i, kelvin);
+                }
+            });
+
             let ddc_clone4 = ddc_ref.clone();
             let idx4 = i;
             if row.has_input_source() {
@@ -293,38 +960,100 @@ impl MainWindow {
             let idx5 = i;
             if row.has_power_mode() {
                 row.connect_power_mode_changed(move |value| {
-                    use crate::ddc_manager::PowerMode;
                     if let Ok(mut ddc) = ddc_clone5.try_borrow_mut() {
                         let _ = ddc.set_power_mode(idx5, PowerMode::from_code(value));
                     }
                 });
             }
 
-            let ddc_clone_dc = ddc_ref.clone();
+            let ddc_osd = ddc_ref.clone();
+            let toast_osd = toast_overlay.clone();
+            let name_osd = name.clone();
+            row.connect_osd_lock_changed(move |locked| {
+                let Ok(mut ddc) = ddc_osd.try_borrow_mut() else {
+                    return false;
+                };
+                if let Err(e) = ddc.set_osd_lock(i, locked) {
+                    toast_osd.add_toast(Toast::new(&format!(
+                        "Couldn't change the OSD lock of {}: {}",
+                        name_osd, e
+                    )));
+                    return false;
+                }
+                true
+            });
+
+            let ddc_invert = ddc_ref.clone();
+            let toast_invert = toast_overlay.clone();
+            let name_invert = name.clone();
+            row.connect_invert_changed(move |inverted| {
+                let Ok(mut ddc) = ddc_invert.try_borrow_mut() else {
+                    return false;
+                };
+                if let Err(e) = ddc.set_invert(i, inverted) {
+                    toast_invert.add_toast(Toast::new(&format!(
+                        "Couldn't invert the colors of {}: {}",
+                        name_invert, e
+                    )));
+                    return false;
+                }
+                true
+            });
+
+            let ddc_custom = ddc_ref.clone();
+            let toast_custom = toast_overlay.clone();
+            let name_custom = name.clone();
+            row.connect_custom_control_changed(move |code, value| {
+                if let Ok(mut ddc) = ddc_custom.try_borrow_mut() {
+                    if let Err(e) = ddc.set_raw_vcp(i, code, value) {
+                        toast_custom.add_toast(Toast::new(&format!(
+                            "Couldn't change {}: {}",
+                            name_custom, e
+                        )));
+                    }
+                }
+            });
+
+            let ddc_vendor = ddc_ref.clone();
+            let toast_vendor = toast_overlay.clone();
+            let name_vendor = name.clone();
+            row.connect_vendor_toggle_changed(move |code, on| {
+                if let Ok(mut ddc) = ddc_vendor.try_borrow_mut() {
+                    if let Err(e) = ddc.set_vendor_toggle(i, code, on) {
+                        toast_vendor.add_toast(Toast::new(&format!(
+                            "Couldn't change VCP 0x{:02X} on {}: {}",
+                            code, name_vendor, e
+                        )));
+                    }
+                }
+            });
+
+            let writer_dc = writer.clone();
             let idx_dc = i;
             let settings_clone_dc = settings.clone();
-            let name_clone_dc = name.clone();
-            if row.has_dynamic_contrast() {
-                row.connect_dynamic_contrast_changed(move |brightness| {
+            let key_clone_dc = key.clone();
+            let ddc_clone_dc = ddc_ref.clone();
+            if let Some(dc_scale) = row.dynamic_contrast_scale.clone() {
+                row.connect_dynamic_contrast_changed(move |raw, brightness| {
                     let settings = settings_clone_dc.borrow();
-                    let ratio = if settings.dynamic_contrast_per_monitor_ratio {
-                        *settings.monitor_ratios.get(&name_clone_dc).unwrap_or(&settings.dynamic_contrast_ratio)
-                    } else {
-                        settings.dynamic_contrast_ratio
-                    };
-                    let contrast = ((brightness as f32 * ratio).round() as u8).min(100);
-                    if let Ok(mut ddc) = ddc_clone_dc.try_borrow_mut() {
-                        let _ = ddc.set_brightness_percentage(idx_dc, brightness);
-                        let _ = ddc.set_contrast_percentage(idx_dc, contrast);
+                    let brightness = ddc_clone_dc
+                        .try_borrow()
+                        .ok()
+                        .and_then(|ddc| ddc.brightness_ceiling())
+                        .map_or(brightness, |ceiling| brightness.min(ceiling));
+                    let contrast = dynamic_contrast_for(&settings, &key_clone_dc, brightness);
+                    if writer_dc.write_brightness_raw(idx_dc, raw, Some(&dc_scale)) {
+                        writer_dc.write(idx_dc, Feature::Contrast, contrast, None);
+                        note_schedule_override(&ddc_clone_dc, idx_dc, &settings, &key_clone_dc);
                     }
                 });
             }
 
             let settings_clone_toggle = settings.clone();
-            let name_clone_toggle = name.clone();
+            let key_clone_toggle = key.clone();
             if row.has_dynamic_contrast() {
                 row.connect_dynamic_contrast_toggle_changed(move |enabled| {
-                    settings_clone_toggle.borrow_mut().monitor_dynamic_contrast.insert(name_clone_toggle.clone(), enabled);
+                    settings_clone_toggle.borrow_mut().monitor_dynamic_contrast.insert(key_clone_toggle.clone(), enabled);
                     let _ = settings_clone_toggle.borrow().save();
                 });
             }
@@ -338,7 +1067,7 @@ impl MainWindow {
                 continue;
             }
             let name = row.name.clone();
-            let ratio = *settings.borrow().monitor_ratios.get(&name).unwrap_or(&settings.borrow().dynamic_contrast_ratio);
+            let ratio = *settings.borrow().monitor_ratios.get(&row.settings_key).unwrap_or(&settings.borrow().dynamic_contrast_ratio);
             let pm_label = Label::new(Some(&format!("{} Ratio:", name)));
             pm_label.set_halign(gtk::Align::Start);
             pm_label.set_hexpand(true);
@@ -360,20 +1089,545 @@ impl MainWindow {
             dc_per_monitor_box.append(&pm_scale);
 
             let settings_pm = settings.clone();
-            let name_pm = name.clone();
+            let key_pm = row.settings_key.clone();
             let pm_value_inner = pm_value.clone();
             let pm_adj = pm_scale.adjustment();
             pm_adj.connect_value_changed(move |adj| {
                 let val = adj.value() as f32;
                 pm_value_inner.set_text(&format!("{:.1}", val));
-                settings_pm.borrow_mut().monitor_ratios.insert(name_pm.clone(), val);
+                settings_pm.borrow_mut().monitor_ratios.insert(key_pm.clone(), val);
                 let _ = settings_pm.borrow().save();
             });
         }
 
-        // Wire settings signals after monitor_rows_vec is built
+        // Per-monitor scroll step overrides; 0 follows the global step.
+        scroll_step_per_monitor_box.set_visible(monitor_count > 1);
+        let per_monitor_label = Label::new(Some("Per-Monitor Scroll Step:"));
+        per_monitor_label.set_halign(gtk::Align::Start);
+        per_monitor_label.set_tooltip_text(Some("0 uses the scroll step above"));
+        scroll_step_per_monitor_box.append(&per_monitor_label);
+        for (name, key, step) in monitor_scroll_steps.borrow().iter() {
+            let step_row = Box::new(Orientation::Horizontal, 8);
+            let step_label = Label::new(Some(name));
+            step_label.set_hexpand(true);
+            step_label.set_halign(gtk::Align::Start);
+            let step_spin = SpinButton::with_range(0.0, 10.0, 1.0);
+            step_spin.set_value(
+                settings
+                    .borrow()
+                    .monitor_scroll_steps
+                    .get(key)
+                    .copied()
+                    .unwrap_or(0) as f64,
+            );
+            step_row.append(&step_label);
+            step_row.append(&step_spin);
+            scroll_step_per_monitor_box.append(&step_row);
+
+            let settings_step = settings.clone();
+            let key_step = key.clone();
+            let step_inner = step.clone();
+            step_spin.connect_value_changed(move |spin| {
+                let val = spin.value() as u8;
+                {
+                    let mut settings = settings_step.borrow_mut();
+                    if val == 0 {
+                        settings.monitor_scroll_steps.remove(&key_step);
+                    } else {
+                        settings.monitor_scroll_steps.insert(key_step.clone(), val);
+                    }
+                    step_inner.set(settings.scroll_step_for(&key_step));
+                }
+                let _ = settings_step.borrow().save();
+            });
+        }
+
         let monitor_rows_ref: Rc<RefCell<Vec<MonitorRow>>> = Rc::new(RefCell::new(monitor_rows_vec));
 
+        let live_update_row = Box::new(Orientation::Horizontal, 8);
+        let live_update_label = Label::new(Some("Live Slider Updates"));
+        live_update_label.set_hexpand(true);
+        live_update_label.set_halign(gtk::Align::Start);
+        let live_update_switch = Switch::new();
+        live_update_switch.set_active(settings.borrow().live_update);
+        let settings_live = settings.clone();
+        let rows_live = monitor_rows_ref.clone();
+        live_update_switch.connect_state_set(move |_, state| {
+            for row in rows_live.borrow().iter() {
+                row.set_live_update(state);
+            }
+            settings_live.borrow_mut().live_update = state;
+            let _ = settings_live.borrow().save();
+            Propagation::Proceed
+        });
+        live_update_row.append(&live_update_label);
+        live_update_row.append(&live_update_switch);
+        live_update_row.set_tooltip_text(Some(
+            "Turn off for slow monitors: a dragged slider is written once, when released",
+        ));
+        advanced_box.append(&live_update_row);
+
+        let identify_hover_row = Box::new(Orientation::Horizontal, 8);
+        let identify_hover_label = Label::new(Some("Identify on Hover"));
+        identify_hover_label.set_hexpand(true);
+        identify_hover_label.set_halign(gtk::Align::Start);
+        let identify_hover_switch = Switch::new();
+        identify_hover_switch.set_active(settings.borrow().identify_on_hover);
+        let settings_identify_hover = settings.clone();
+        identify_hover_switch.connect_state_set(move |_, state| {
+            settings_identify_hover.borrow_mut().identify_on_hover = state;
+            let _ = settings_identify_hover.borrow().save();
+            Propagation::Proceed
+        });
+        identify_hover_row.append(&identify_hover_label);
+        identify_hover_row.append(&identify_hover_switch);
+        identify_hover_row.set_tooltip_text(Some(
+            "Flash a monitor's brightness when the pointer rests on its row",
+        ));
+        advanced_box.append(&identify_hover_row);
+
+        // Mirror changes to linked monitors. Their sliders are set with
+        // callbacks suppressed, so a mirrored change does not echo back.
+        for (i, row) in monitor_rows_ref.borrow().iter().enumerate() {
+            for feature in [Feature::Brightness, Feature::Contrast, Feature::Volume] {
+                let rows_link = monitor_rows_ref.clone();
+                let ddc_link = ddc_ref.clone();
+                let settings_link = settings.clone();
+                let mirror = move |value: u8| {
+                    if let (Ok(rows), Ok(mut ddc)) =
+                        (rows_link.try_borrow(), ddc_link.try_borrow_mut())
+                    {
+                        mirror_to_linked(
+                            &rows,
+                            &mut ddc,
+                            &settings_link.borrow(),
+                            i,
+                            feature,
+                            value,
+                        );
+                    }
+                };
+                match feature {
+                    Feature::Brightness => row.connect_brightness_changed(mirror),
+                    Feature::Contrast => row.connect_contrast_changed(mirror),
+                    Feature::Volume => row.connect_volume_changed(mirror),
+                }
+            }
+        }
+
+        let monitor_names: Vec<String> = monitor_rows_ref
+            .borrow()
+            .iter()
+            .map(|r| r.name.clone())
+            .collect();
+        let monitor_name_refs: Vec<&str> = monitor_names.iter().map(|n| n.as_str()).collect();
+
+        // --- Profiles ---
+        let mut profile_names: Vec<String> = settings.borrow().profiles.keys().cloned().collect();
+        profile_names.sort();
+        let profile_list = StringList::new(&["Custom"]);
+        let startup_profile_list = StringList::new(&["None"]);
+        for name in &profile_names {
+            profile_list.append(name);
+            startup_profile_list.append(name);
+        }
+
+        let profile_dropdown = DropDown::new(Some(profile_list.clone()), None::<gtk::Expression>);
+        profile_dropdown.set_tooltip_text(Some("Apply profile"));
+        header_bar.pack_start(&profile_dropdown);
+
+        let profile_quiet = Rc::new(Cell::new(false));
+        let ddc_profile = ddc_ref.clone();
+        let rows_profile = monitor_rows_ref.clone();
+        let settings_profile = settings.clone();
+        let toast_profile = toast_overlay.clone();
+        let quiet_profile = profile_quiet.clone();
+        profile_dropdown.connect_selected_notify(move |dropdown| {
+            if quiet_profile.get() {
+                return;
+            }
+            let Some(name) = selected_string(dropdown).filter(|_| dropdown.selected() > 0) else {
+                let mut settings = settings_profile.borrow_mut();
+                if settings.active_profile.take().is_some() {
+                    let _ = settings.save();
+                }
+                return;
+            };
+            apply_profile(
+                &ddc_profile,
+                &rows_profile.borrow(),
+                &settings_profile,
+                &toast_profile,
+                &name,
+            );
+        });
+
+        // Moving a slider by hand means the monitors no longer match the
+        // applied profile.
+        for row in monitor_rows_ref.borrow().iter() {
+            let dropdown = profile_dropdown.clone();
+            let mark_custom = move |_: u8| {
+                if dropdown.selected() != 0 {
+                    dropdown.set_selected(0);
+                }
+            };
+            row.connect_brightness_changed(mark_custom.clone());
+            row.connect_contrast_changed(mark_custom.clone());
+            row.connect_volume_changed(mark_custom);
+        }
+
+        let profiles_section_label = Label::new(Some("Profiles"));
+        profiles_section_label.set_halign(gtk::Align::Start);
+        profiles_section_label.add_css_class("heading");
+        popover_box.append(&profiles_section_label);
+
+        let profile_save_row = Box::new(Orientation::Horizontal, 8);
+        let profile_name_entry = Entry::builder()
+            .placeholder_text("Profile name")
+            .hexpand(true)
+            .build();
+        let profile_save_button = Button::with_label("Save Current");
+        profile_save_row.append(&profile_name_entry);
+        profile_save_row.append(&profile_save_button);
+        popover_box.append(&profile_save_row);
+
+        let (startup_profile_row, startup_profile_dropdown) = profile_choice_row(
+            "Apply on launch:",
+            &startup_profile_list,
+            settings.borrow().default_profile.as_deref(),
+        );
+        popover_box.append(&startup_profile_row);
+
+        let settings_startup = settings.clone();
+        startup_profile_dropdown.connect_selected_notify(move |dropdown| {
+            let name = selected_string(dropdown).filter(|_| dropdown.selected() > 0);
+            settings_startup.borrow_mut().default_profile = name;
+            let _ = settings_startup.borrow().save();
+        });
+
+        let (dark_profile_row, dark_profile_dropdown) = profile_choice_row(
+            "Apply in dark mode:",
+            &startup_profile_list,
+            settings.borrow().dark_mode_profile.as_deref(),
+        );
+        dark_profile_row.set_tooltip_text(Some(
+            "Applied when the desktop switches to dark mode, e.g. at sunset",
+        ));
+        popover_box.append(&dark_profile_row);
+
+        let settings_dark_choice = settings.clone();
+        dark_profile_dropdown.connect_selected_notify(move |dropdown| {
+            let name = selected_string(dropdown).filter(|_| dropdown.selected() > 0);
+            settings_dark_choice.borrow_mut().dark_mode_profile = name;
+            let _ = settings_dark_choice.borrow().save();
+        });
+
+        let (light_profile_row, light_profile_dropdown) = profile_choice_row(
+            "Apply in light mode:",
+            &startup_profile_list,
+            settings.borrow().light_mode_profile.as_deref(),
+        );
+        light_profile_row
+            .set_tooltip_text(Some("Applied when the desktop switches back to light mode"));
+        popover_box.append(&light_profile_row);
+
+        let settings_light_choice = settings.clone();
+        light_profile_dropdown.connect_selected_notify(move |dropdown| {
+            let name = selected_string(dropdown).filter(|_| dropdown.selected() > 0);
+            settings_light_choice.borrow_mut().light_mode_profile = name;
+            let _ = settings_light_choice.borrow().save();
+        });
+
+        // Applied the same way as picking it by hand, toast included, even
+        // when it is the profile already shown. This only follows the
+        // desktop while the color scheme is left at System.
+        let settings_dark = settings.clone();
+        let dropdown_dark = profile_dropdown.clone();
+        let quiet_dark = profile_quiet.clone();
+        let ddc_dark = ddc_ref.clone();
+        let rows_dark = monitor_rows_ref.clone();
+        let toast_dark = toast_overlay.clone();
+        let retired_dark = retired.clone();
+        adw::StyleManager::default().connect_dark_notify(move |manager| {
+            if retired_dark.get() || manager.color_scheme() != adw::ColorScheme::Default {
+                return;
+            }
+            let name = {
+                let settings = settings_dark.borrow();
+                if manager.is_dark() {
+                    settings.dark_mode_profile.clone()
+                } else {
+                    settings.light_mode_profile.clone()
+                }
+            };
+            let Some(name) = name else {
+                return;
+            };
+            if apply_profile(
+                &ddc_dark,
+                &rows_dark.borrow(),
+                &settings_dark,
+                &toast_dark,
+                &name,
+            ) {
+                show_profile(&dropdown_dark, &quiet_dark, &name);
+            }
+        });
+
+        let ddc_save = ddc_ref.clone();
+        let settings_save = settings.clone();
+        let toast_save = toast_overlay.clone();
+        profile_save_button.connect_clicked(move |_| {
+            let name = profile_name_entry.text().trim().to_string();
+            if name.is_empty() {
+                return;
+            }
+            let Ok(mut ddc) = ddc_save.try_borrow_mut() else {
+                return;
+            };
+            let profile = Profile::capture(&mut ddc);
+            let is_new = settings_save
+                .borrow_mut()
+                .profiles
+                .insert(name.clone(), profile)
+                .is_none();
+            let _ = settings_save.borrow().save();
+            if is_new {
+                profile_list.append(&name);
+                startup_profile_list.append(&name);
+            }
+            profile_name_entry.set_text("");
+            toast_save.add_toast(Toast::new(&format!("Saved profile \"{}\"", name)));
+        });
+
+        // --- Copy Settings Section ---
+        if monitor_names.len() > 1 {
+            let copy_section_label = Label::new(Some("Copy Settings"));
+            copy_section_label.set_halign(gtk::Align::Start);
+            copy_section_label.add_css_class("heading");
+            popover_box.append(&copy_section_label);
+
+            let copy_row = Box::new(Orientation::Horizontal, 8);
+            let copy_from_dropdown = DropDown::from_strings(&monitor_name_refs);
+            let copy_arrow_label = Label::new(Some("→"));
+            let copy_to_dropdown = DropDown::from_strings(&monitor_name_refs);
+            copy_to_dropdown.set_selected(1);
+            let copy_button = Button::with_label("Copy");
+            copy_row.append(&copy_from_dropdown);
+            copy_row.append(&copy_arrow_label);
+            copy_row.append(&copy_to_dropdown);
+            copy_row.append(&copy_button);
+            popover_box.append(&copy_row);
+
+            let copy_result_label = Label::new(None);
+            copy_result_label.set_halign(gtk::Align::Start);
+            copy_result_label.set_wrap(true);
+            popover_box.append(&copy_result_label);
+
+            let ddc_copy = ddc_ref.clone();
+            let rows_copy = monitor_rows_ref.clone();
+            copy_button.connect_clicked(move |_| {
+                let from = copy_from_dropdown.selected() as usize;
+                let to = copy_to_dropdown.selected() as usize;
+                if from == to {
+                    copy_result_label.set_text("Pick two different monitors");
+                    return;
+                }
+                let Ok(mut ddc) = ddc_copy.try_borrow_mut() else {
+                    return;
+                };
+                match ddc.copy_settings(from, to) {
+                    Ok(report) => {
+                        let mut text = if report.copied.is_empty() {
+                            "Nothing was copied".to_string()
+                        } else {
+                            format!("Copied: {}", report.copied.join(", "))
+                        };
+                        if !report.skipped.is_empty() {
+                            text.push_str(&format!("\nSkipped: {}", report.skipped.join(", ")));
+                        }
+                        copy_result_label.set_text(&text);
+                    }
+                    Err(e) => copy_result_label.set_text(&e.to_string()),
+                }
+                poll_monitor_values(&mut ddc, &rows_copy.borrow());
+            });
+        }
+
+        // --- I2C Device Section ---
+        let i2c_section_label = Label::new(Some("I2C Devices"));
+        i2c_section_label.set_halign(gtk::Align::Start);
+        i2c_section_label.add_css_class("heading");
+        advanced_box.append(&i2c_section_label);
+
+        for monitor in ddc_ref.borrow().monitors.iter() {
+            let i2c_row = Box::new(Orientation::Horizontal, 8);
+            let i2c_label = Label::new(Some(&format!("{} ({}):", monitor.name, monitor.connector)));
+            i2c_label.set_hexpand(true);
+            i2c_label.set_halign(gtk::Align::Start);
+
+            let mut choices: Vec<&str> = vec!["Automatic"];
+            choices.extend(monitor.i2c_candidates.iter().map(|c| c.as_str()));
+            let i2c_dropdown = DropDown::from_strings(&choices);
+            i2c_dropdown.set_tooltip_text(Some(&format!("In use: {}", monitor.i2c_path)));
+            if let Some(pinned) = settings.borrow().i2c_device_pins.get(&monitor.connector) {
+                if let Some(pos) = monitor.i2c_candidates.iter().position(|c| c == pinned) {
+                    i2c_dropdown.set_selected(pos as u32 + 1);
+                }
+            }
+
+            let settings_i2c = settings.clone();
+            let connector = monitor.connector.clone();
+            let candidates = monitor.i2c_candidates.clone();
+            i2c_dropdown.connect_selected_notify(move |dropdown| {
+                let mut settings = settings_i2c.borrow_mut();
+                match (dropdown.selected() as usize).checked_sub(1) {
+                    Some(pos) if pos < candidates.len() => {
+                        settings
+                            .i2c_device_pins
+                            .insert(connector.clone(), candidates[pos].clone());
+                    }
+                    _ => {
+                        settings.i2c_device_pins.remove(&connector);
+                    }
+                }
+                let _ = settings.save();
+            });
+
+            i2c_row.append(&i2c_label);
+            i2c_row.append(&i2c_dropdown);
+            advanced_box.append(&i2c_row);
+        }
+
+        let i2c_hint_label = Label::new(Some("Device changes apply after restart"));
+        i2c_hint_label.set_halign(gtk::Align::Start);
+        i2c_hint_label.add_css_class("dim-label");
+        advanced_box.append(&i2c_hint_label);
+
+        // --- Developer Section ---
+        if settings.borrow().developer_mode {
+            let dev_section_label = Label::new(Some("Raw VCP"));
+            dev_section_label.set_halign(gtk::Align::Start);
+            dev_section_label.add_css_class("heading");
+            advanced_box.append(&dev_section_label);
+
+            let dev_monitor_dropdown = DropDown::from_strings(&monitor_name_refs);
+            advanced_box.append(&dev_monitor_dropdown);
+
+            let dev_entry_row = Box::new(Orientation::Horizontal, 8);
+            let dev_code_entry = Entry::builder()
+                .placeholder_text("Code (hex)")
+                .width_chars(10)
+                .build();
+            let dev_value_entry = Entry::builder()
+                .placeholder_text("Value")
+                .width_chars(8)
+                .build();
+            dev_entry_row.append(&dev_code_entry);
+            dev_entry_row.append(&dev_value_entry);
+            advanced_box.append(&dev_entry_row);
+
+            let dev_button_row = Box::new(Orientation::Horizontal, 8);
+            let dev_read_button = Button::with_label("Read");
+            let dev_write_button = Button::with_label("Write");
+            dev_button_row.append(&dev_read_button);
+            dev_button_row.append(&dev_write_button);
+            advanced_box.append(&dev_button_row);
+
+            let dev_result_label = Label::new(None);
+            dev_result_label.set_halign(gtk::Align::Start);
+            dev_result_label.set_selectable(true);
+            advanced_box.append(&dev_result_label);
+
+            let ddc_read = ddc_ref.clone();
+            let dropdown_read = dev_monitor_dropdown.clone();
+            let code_read = dev_code_entry.clone();
+            let result_read = dev_result_label.clone();
+            dev_read_button.connect_clicked(move |_| {
+                let Some(code) = parse_vcp_code(&code_read.text()) else {
+                    result_read.set_text("Invalid code");
+                    return;
+                };
+                let index = dropdown_read.selected() as usize;
+                match ddc_read.borrow_mut().get_raw_vcp(index, code) {
+                    Ok((current, max)) => {
+                        result_read.set_text(&format!("0x{:02x}: {} (max {})", code, current, max))
+                    }
+                    Err(e) => result_read.set_text(&e.to_string()),
+                }
+            });
+
+            let ddc_write = ddc_ref.clone();
+            let dropdown_write = dev_monitor_dropdown.clone();
+            let code_write = dev_code_entry.clone();
+            let value_write = dev_value_entry.clone();
+            let result_write = dev_result_label.clone();
+            dev_write_button.connect_clicked(move |_| {
+                let Some(code) = parse_vcp_code(&code_write.text()) else {
+                    result_write.set_text("Invalid code");
+                    return;
+                };
+                let Ok(value) = value_write.text().trim().parse::<u16>() else {
+                    result_write.set_text("Invalid value");
+                    return;
+                };
+                let index = dropdown_write.selected() as usize;
+                match ddc_write.borrow_mut().set_raw_vcp(index, code, value) {
+                    Ok(()) => result_write.set_text(&format!("0x{:02x} set to {}", code, value)),
+                    Err(e) => result_write.set_text(&e.to_string()),
+                }
+            });
+        }
+
+        let advanced_expander = ExpanderRow::builder()
+            .title("Advanced")
+            .expanded(settings.borrow().advanced_settings_expanded)
+            .build();
+        advanced_expander.add_row(&advanced_box);
+        let settings_advanced = settings.clone();
+        advanced_expander.connect_expanded_notify(move |expander| {
+            settings_advanced.borrow_mut().advanced_settings_expanded = expander.is_expanded();
+            let _ = settings_advanced.borrow().save();
+        });
+        let advanced_list = ListBox::builder()
+            .selection_mode(SelectionMode::None)
+            .css_classes(vec![String::from("boxed-list")])
+            .build();
+        advanced_list.append(&advanced_expander);
+        popover_box.append(&advanced_list);
+
+        // Wire settings signals after monitor_rows_vec is built
+
+        let monitor_rows_high_contrast = monitor_rows_ref.clone();
+        let settings_high_contrast = settings.clone();
+        high_contrast_switch.connect_state_set(move |_, state| {
+            settings_high_contrast.borrow_mut().high_contrast_sliders = state;
+            let _ = settings_high_contrast.borrow().save();
+            for row in monitor_rows_high_contrast.borrow().iter() {
+                row.set_high_contrast(state);
+            }
+            Propagation::Proceed
+        });
+
+        let ddc_perceptual = ddc_ref.clone();
+        let monitor_rows_perceptual = monitor_rows_ref.clone();
+        let settings_perceptual = settings.clone();
+        perceptual_switch.connect_state_set(move |_, state| {
+            settings_perceptual.borrow_mut().perceptual_brightness = state;
+            let _ = settings_perceptual.borrow().save();
+            let curve = settings_perceptual.borrow().brightness_curve();
+            if let Ok(mut ddc) = ddc_perceptual.try_borrow_mut() {
+                ddc.set_brightness_curve(curve);
+                // Same hardware value and slider position, new percentage.
+                let rows = monitor_rows_perceptual.borrow();
+                for row in rows.iter() {
+                    row.set_brightness_curve(curve);
+                }
+                poll_monitor_values(&mut ddc, &rows);
+            }
+            Propagation::Proceed
+        });
+
         let dc_sub_box_inner = dc_sub_box.clone();
         let monitor_rows_enable = monitor_rows_ref.clone();
         let settings_enable = settings.clone();
@@ -433,6 +1687,455 @@ impl MainWindow {
             Propagation::Proceed
         });
 
+        let ddc_schedule = ddc_ref.clone();
+        let rows_schedule = monitor_rows_ref.clone();
+        let settings_schedule = settings.clone();
+        let retired_schedule = retired.clone();
+        glib::timeout_add_seconds_local(60, move || {
+            if retired_schedule.get() {
+                return glib::ControlFlow::Break;
+            }
+            if let Ok(mut ddc) = ddc_schedule.try_borrow_mut() {
+                apply_night_cap(
+                    &mut ddc,
+                    &rows_schedule.borrow(),
+                    &settings_schedule.borrow(),
+                );
+                apply_brightness_schedule(
+                    &mut ddc,
+                    &rows_schedule.borrow(),
+                    &settings_schedule.borrow(),
+                );
+            }
+            glib::ControlFlow::Continue
+        });
+
+        let poll_interval = settings.borrow().poll_interval_secs;
+        if poll_interval > 0 {
+            let ddc_poll = ddc_ref.clone();
+            let rows_poll = monitor_rows_ref.clone();
+            let retired_poll = retired.clone();
+            glib::timeout_add_seconds_local(poll_interval, move || {
+                if retired_poll.get() {
+                    return glib::ControlFlow::Break;
+                }
+                if let Ok(mut ddc) = ddc_poll.try_borrow_mut() {
+                    poll_monitor_values(&mut ddc, &rows_poll.borrow());
+                }
+                glib::ControlFlow::Continue
+            });
+        }
+
+        // Requests are answered here, where the manager lives, rather than
+        // on the server's thread. A rebuilt window keeps the server the
+        // first one started.
+        #[cfg(feature = "http")]
+        let http_requests = handover.http_requests.or_else(|| {
+            let port = settings.borrow().http_port;
+            if port == 0 {
+                return None;
+            }
+            match http::spawn(port) {
+                Ok(requests) => Some(Rc::new(requests)),
+                Err(e) => {
+                    eprintln!("Failed to start HTTP server on port {}: {}", port, e);
+                    None
+                }
+            }
+        });
+        #[cfg(feature = "http")]
+        if let Some(requests) = http_requests.clone() {
+            let ddc_http = ddc_ref.clone();
+            let rows_http = monitor_rows_ref.clone();
+            let retired_http = retired.clone();
+            glib::timeout_add_local(Duration::from_millis(HTTP_POLL_MS), move || {
+                if retired_http.get() {
+                    return glib::ControlFlow::Break;
+                }
+                let Ok(mut ddc) = ddc_http.try_borrow_mut() else {
+                    return glib::ControlFlow::Continue;
+                };
+                let mut changed = false;
+                while let Ok(pending) = requests.try_recv() {
+                    changed |= pending.request != http::Request::ListMonitors;
+                    let _ = pending.reply.send(http::handle(&mut ddc, &pending.request));
+                }
+                if changed {
+                    poll_monitor_values(&mut ddc, &rows_http.borrow());
+                }
+                glib::ControlFlow::Continue
+            });
+        }
+
+        // A way back from monitors left too dark to see anything, so it
+        // works without confirmation from the menu or a shortcut.
+        let reset_brightness = gio::SimpleAction::new("reset-brightness", None);
+        let ddc_reset = ddc_ref.clone();
+        let rows_reset = monitor_rows_ref.clone();
+        let settings_reset = settings.clone();
+        let toast_reset = toast_overlay.clone();
+        reset_brightness.connect_activate(move |_, _| {
+            let (Ok(mut ddc), Ok(rows)) = (ddc_reset.try_borrow_mut(), rows_reset.try_borrow())
+            else {
+                return;
+            };
+            let settings = settings_reset.borrow();
+            let results = ddc.set_brightness_all(50, settings.batch_rollback);
+            let mut failed = Vec::new();
+            for ((i, row), result) in rows.iter().enumerate().zip(results) {
+                let achieved = match result {
+                    Ok(WriteOutcome::Clamped { achieved, .. }) => achieved,
+                    Ok(_) => 50,
+                    Err(_) => {
+                        failed.push(row.name.clone());
+                        continue;
+                    }
+                };
+                if row.is_dynamic_contrast_active() {
+                    let contrast = dynamic_contrast_for(&settings, &row.settings_key, achieved);
+                    if ddc.set_contrast_percentage(i, contrast).is_err() {
+                        failed.push(row.name.clone());
+                    }
+                    row.set_dynamic_contrast(achieved);
+                } else {
+                    row.set_brightness(achieved);
+                }
+            }
+            let message = match (rows.len() - failed.len(), failed.is_empty()) {
+                (_, true) => "Brightness reset to 50% on all monitors".to_string(),
+                (0, false) => "Couldn't reset the brightness of any monitor".to_string(),
+                (_, false) => format!("Brightness reset to 50%, except on {}", failed.join(", ")),
+            };
+            toast_reset.add_toast(Toast::new(&message));
+        });
+        app.add_action(&reset_brightness);
+        app.set_accels_for_action("app.reset-brightness", &["<Control>0"]);
+
+        // Rows are built once per window, so when monitors were added or
+        // removed the window is replaced by one around the same manager.
+        let detect_monitors = gio::SimpleAction::new("detect-monitors", None);
+        let app_detect = app.clone();
+        let window_detect = window.clone();
+        let ddc_detect = ddc_ref.clone();
+        let settings_detect = settings.clone();
+        let writer_detect = writer.clone();
+        let toast_detect = toast_overlay.clone();
+        let retired_detect = retired.clone();
+        let identifying_detect = identifying.clone();
+        #[cfg(feature = "http")]
+        let http_detect = http_requests.clone();
+        detect_monitors.connect_activate(move |_, _| {
+            if identifying_detect.get() {
+                return;
+            }
+            writer_detect.flush();
+            let options = settings_detect.borrow().ddc_options();
+            let Ok(mut ddc) = ddc_detect.try_borrow_mut() else {
+                return;
+            };
+            let changes = match ddc.rediscover(&options) {
+                Ok(changes) => changes,
+                Err(e) => {
+                    toast_detect.add_toast(Toast::new(&format!("Couldn't detect monitors: {}", e)));
+                    return;
+                }
+            };
+            drop(ddc);
+            if changes.added.is_empty() && changes.removed.is_empty() {
+                toast_detect.add_toast(Toast::new("No monitors were added or removed"));
+                return;
+            }
+
+            retired_detect.set(true);
+            let _hold = app_detect.hold();
+            let hide_on_close = window_detect.hides_on_close();
+            let visible = window_detect.is_visible();
+            // Closing first saves this window's state for the new one.
+            window_detect.set_hide_on_close(false);
+            window_detect.close();
+            let replacement = MainWindow::build(
+                &app_detect,
+                ddc_detect.clone(),
+                Handover {
+                    #[cfg(feature = "http")]
+                    http_requests: http_detect.clone(),
+                },
+            );
+            replacement.init_brightness();
+            replacement.show_active_profile();
+            replacement.notify(&describe_rediscovery(&changes));
+            replacement.window.set_hide_on_close(hide_on_close);
+            if visible {
+                replacement.window.present();
+            }
+            std::mem::forget(replacement);
+        });
+        app.add_action(&detect_monitors);
+        app.set_accels_for_action("app.detect-monitors", &["F5"]);
+
+        // Pulses the monitors one after another in list order, so each can
+        // be matched to its row without anything shown on screen.
+        let identify_monitors = gio::SimpleAction::new("identify-monitors", None);
+        let ddc_identify_all = ddc_ref.clone();
+        let rows_identify_all = monitor_rows_ref.clone();
+        let toast_identify_all = toast_overlay.clone();
+        identify_monitors.connect_activate(move |_, _| {
+            if identifying.replace(true) {
+                return;
+            }
+            let mut order: Vec<(i32, usize)> = rows_identify_all
+                .borrow()
+                .iter()
+                .enumerate()
+                .map(|(i, row)| (row.container.index(), i))
+                .collect();
+            order.sort();
+            let mut steps: VecDeque<Option<(usize, bool)>> =
+                identify_sequence(order.into_iter().map(|(_, i)| i)).into();
+            let ddc = ddc_identify_all.clone();
+            let rows = rows_identify_all.clone();
+            let toast = toast_identify_all.clone();
+            let identifying = identifying.clone();
+            let mut originals: HashMap<usize, u16> = HashMap::new();
+            let mut restore_attempts: HashMap<usize, u32> = HashMap::new();
+            let mut failed: Vec<String> = Vec::new();
+            let mut stuck: Vec<String> = Vec::new();
+            glib::timeout_add_local(Duration::from_millis(IDENTIFY_STEP_MS), move || {
+                // Waits rather than skips, so a pulse is always undone.
+                let Ok(mut ddc) = ddc.try_borrow_mut() else {
+                    return glib::ControlFlow::Continue;
+                };
+                let Some(step) = steps.pop_front() else {
+                    identifying.set(false);
+                    if !failed.is_empty() {
+                        toast.add_toast(Toast::new(&format!(
+                            "Couldn't identify {}",
+                            failed.join(", ")
+                        )));
+                    }
+                    if !stuck.is_empty() {
+                        toast.add_toast(Toast::new(&format!(
+                            "Couldn't restore the brightness of {}; set it again from its row",
+                            stuck.join(", ")
+                        )));
+                    }
+                    return glib::ControlFlow::Break;
+                };
+                let Some((i, pulse)) = step else {
+                    return glib::ControlFlow::Continue;
+                };
+                if pulse {
+                    match ddc.start_identify_pulse(i) {
+                        Ok(raw) => {
+                            originals.entry(i).or_insert(raw);
+                        }
+                        Err(_) => {
+                            // The rest of this monitor's pulses are left out.
+                            steps.retain(|step| !matches!(step, Some((j, _)) if *j == i));
+                            failed.push(rows.borrow()[i].name.clone());
+                        }
+                    }
+                    return glib::ControlFlow::Continue;
+                }
+                let Some(&raw) = originals.get(&i) else {
+                    return glib::ControlFlow::Continue;
+                };
+                if ddc.end_identify_pulse(i, raw).is_err() {
+                    let attempts = restore_attempts.entry(i).or_insert(0);
+                    *attempts += 1;
+                    if *attempts < IDENTIFY_RESTORE_ATTEMPTS {
+                        // Tried again on the next step, before anything
+                        // else pulses.
+                        steps.push_front(Some((i, false)));
+                    } else {
+                        steps.retain(|step| !matches!(step, Some((j, _)) if *j == i));
+                        stuck.push(rows.borrow()[i].name.clone());
+                    }
+                }
+                glib::ControlFlow::Continue
+            });
+        });
+        app.add_action(&identify_monitors);
+        app.set_accels_for_action("app.identify-monitors", &["<Control>i"]);
+
+        let mut lockable: Vec<gtk::Widget> = vec![
+            settings_button.clone().upcast(),
+            profile_dropdown.clone().upcast(),
+        ];
+        if (0..monitor_count).any(|i| ddc_ref.borrow().supports_power_mode(i)) {
+            let wake_button = Button::from_icon_name("system-shutdown-symbolic");
+            wake_button.set_tooltip_text(Some("Wake All Monitors"));
+            let ddc_wake = ddc_ref.clone();
+            let rows_wake = monitor_rows_ref.clone();
+            let toast_wake = toast_overlay.clone();
+            wake_button.connect_clicked(move |_| {
+                let Ok(mut ddc) = ddc_wake.try_borrow_mut() else {
+                    return;
+                };
+                // A monitor that is fully off may ignore DDC/CI until it is
+                // woken with its own button.
+                let mut silent = Vec::new();
+                for i in 0..ddc.monitors.len() {
+                    if ddc.supports_power_mode(i) && ddc.set_power_mode(i, PowerMode::On).is_err() {
+                        silent.push(ddc.monitors[i].name.clone());
+                    }
+                }
+                if !silent.is_empty() {
+                    toast_wake.add_toast(Toast::new(&format!(
+                        "No response from {}",
+                        silent.join(", ")
+                    )));
+                }
+
+                // Values can only be read once the monitors are back on.
+                let ddc_reread = ddc_wake.clone();
+                let rows_reread = rows_wake.clone();
+                glib::timeout_add_local_once(Duration::from_millis(WAKE_SETTLE_MS), move || {
+                    if let Ok(mut ddc) = ddc_reread.try_borrow_mut() {
+                        poll_monitor_values(&mut ddc, &rows_reread.borrow());
+                    }
+                });
+            });
+            header_bar.pack_start(&wake_button);
+            lockable.push(wake_button.upcast());
+        }
+
+        // Locking makes every control insensitive, which also stops their
+        // scroll and keyboard handling; the actions that change monitors are
+        // turned off with them.
+        let locked = settings.borrow().controls_locked;
+        let lock_controls =
+            gio::SimpleAction::new_stateful("lock-controls", None, &locked.to_variant());
+        let rows_lock = monitor_rows_ref.clone();
+        let settings_lock = settings.clone();
+        let reset_lock = reset_brightness.clone();
+        let identify_lock = identify_monitors.clone();
+        let apply_lock = Rc::new(move |action: &gio::SimpleAction, locked: bool| {
+            action.set_state(&locked.to_variant());
+            for row in rows_lock.borrow().iter() {
+                row.set_locked(locked);
+            }
+            for widget in &lockable {
+                widget.set_sensitive(!locked);
+            }
+            reset_lock.set_enabled(!locked);
+            identify_lock.set_enabled(!locked);
+            let mut settings = settings_lock.borrow_mut();
+            if settings.controls_locked != locked {
+                settings.controls_locked = locked;
+                let _ = settings.save();
+            }
+        });
+        apply_lock(&lock_controls, locked);
+        let settings_unlock = settings.clone();
+        let window_unlock = window.clone();
+        let toast_unlock = toast_overlay.clone();
+        lock_controls.connect_activate(move |action, _| {
+            let locked = action
+                .state()
+                .and_then(|s| s.get::<bool>())
+                .unwrap_or(false);
+            let pin = settings_unlock
+                .borrow()
+                .lock_pin
+                .clone()
+                .filter(|pin| !pin.is_empty());
+            match pin {
+                Some(pin) if locked => {
+                    let apply_lock = apply_lock.clone();
+                    let action = action.clone();
+                    let toast = toast_unlock.clone();
+                    ask_unlock_pin(&window_unlock, move |entered| {
+                        if entered == pin {
+                            apply_lock(&action, false);
+                        } else {
+                            toast.add_toast(Toast::new("Wrong PIN, controls stay locked"));
+                        }
+                    });
+                }
+                _ => apply_lock(action, !locked),
+            }
+        });
+        app.add_action(&lock_controls);
+        app.set_accels_for_action("app.lock-controls", &["<Control>l"]);
+
+        let watchdog_interval = settings.borrow().watchdog_interval_secs;
+        if watchdog_interval > 0 {
+            let ddc_watchdog = ddc_ref.clone();
+            let rows_watchdog = monitor_rows_ref.clone();
+            let threshold = settings.borrow().watchdog_threshold;
+            let retired_watchdog = retired.clone();
+            glib::timeout_add_seconds_local(watchdog_interval, move || {
+                if retired_watchdog.get() {
+                    return glib::ControlFlow::Break;
+                }
+                if let (Ok(mut ddc), Ok(rows)) =
+                    (ddc_watchdog.try_borrow_mut(), rows_watchdog.try_borrow())
+                {
+                    for (i, row) in rows.iter().enumerate() {
+                        if row.is_asleep() {
+                            continue;
+                        }
+                        for change in ddc.correct_drift(i, threshold) {
+                            eprintln!(
+                                "Watchdog: {} {} drifted to {}%, restored {}%",
+                                row.name,
+                                change.feature.name(),
+                                change.from,
+                                change.to
+                            );
+                            match change.feature {
+                                Feature::Brightness => row.set_brightness(change.to),
+                                Feature::Contrast => row.set_contrast(change.to),
+                                Feature::Volume => row.set_volume(change.to),
+                            }
+                        }
+                    }
+                }
+                glib::ControlFlow::Continue
+            });
+        }
+
+        // Writes still waiting out the coalescing window go out before the
+        // window closes, and the final values are kept for
+        // `--apply-saved-state`.
+        let settings_close = settings.clone();
+        let writer_close = writer.clone();
+        let rows_close = monitor_rows_ref.clone();
+        window.connect_close_request(move |window| {
+            writer_close.flush();
+            {
+                let mut settings = settings_close.borrow_mut();
+                // The default size tracks the unmaximized size, so a
+                // maximized window still restores to its previous size.
+                let (width, height) = window.default_size();
+                settings.window_width = width;
+                settings.window_height = height;
+                settings.window_maximized = window.is_maximized();
+                if !window.is_maximized() {
+                    if let Some((x, y)) = window_position::position(window) {
+                        settings.window_x = Some(x);
+                        settings.window_y = Some(y);
+                    }
+                }
+            }
+            for row in rows_close.borrow().iter().filter(|r| !r.is_asleep()) {
+                let state = MonitorState {
+                    brightness: Some(row.brightness_value()),
+                    contrast: row.contrast_value(),
+                    volume: row.volume_value(),
+                };
+                settings_close
+                    .borrow_mut()
+                    .monitor_states
+                    .insert(row.settings_key.clone(), state);
+            }
+            if let Err(e) = settings_close.borrow().save() {
+                eprintln!("Failed to save settings: {}", e);
+            }
+            Propagation::Proceed
+        });
+
         let content = Box::new(Orientation::Vertical, 0);
         content.append(&toolbar_view);
 
@@ -445,11 +2148,38 @@ impl MainWindow {
             .css_classes(vec![String::from("boxed-list")])
             .build();
 
-        {
-            let rows = monitor_rows_ref.borrow();
-            for row in rows.iter() {
-                list.append(&row.container);
-            }
+        order_monitor_rows(
+            &list,
+            &monitor_rows_ref.borrow(),
+            &settings.borrow().favorite_monitors,
+        );
+        for row in monitor_rows_ref.borrow().iter() {
+            row.set_favorite(
+                settings
+                    .borrow()
+                    .favorite_monitors
+                    .contains(&row.settings_key),
+            );
+            let list_favorite = list.clone();
+            let rows_favorite = monitor_rows_ref.clone();
+            let settings_favorite = settings.clone();
+            let key_favorite = row.settings_key.clone();
+            row.connect_favorite_toggled(move |favorite| {
+                {
+                    let mut settings = settings_favorite.borrow_mut();
+                    if favorite {
+                        settings.favorite_monitors.insert(key_favorite.clone());
+                    } else {
+                        settings.favorite_monitors.remove(&key_favorite);
+                    }
+                }
+                let _ = settings_favorite.borrow().save();
+                order_monitor_rows(
+                    &list_favorite,
+                    &rows_favorite.borrow(),
+                    &settings_favorite.borrow().favorite_monitors,
+                );
+            });
         }
 
         let scrolled = ScrolledWindow::new();
@@ -458,21 +2188,109 @@ impl MainWindow {
 
         toolbar_view.set_content(Some(&scrolled));
 
-        window.set_content(Some(&content));
+        toast_overlay.set_child(Some(&content));
+        window.set_content(Some(&toast_overlay));
 
-        Ok(Self {
+        Self {
             window,
             stack,
             monitor_rows: monitor_rows_ref,
+            toast_overlay,
+            profile_dropdown,
+            profile_quiet,
             ddc: ddc_ref,
             settings,
-        })
+            retired,
+        }
+    }
+
+    /// Shows a transient message at the bottom of the window.
+    pub fn notify(&self, message: &str) {
+        self.toast_overlay.add_toast(Toast::new(message));
+    }
+
+    /// Offers, once, to turn on the night cap when an OLED monitor is
+    /// connected, since a lower brightness at night eases burn-in.
+    pub fn suggest_night_cap(&self) {
+        {
+            let settings = self.settings.borrow();
+            if settings.night_cap.enabled || settings.night_cap_suggested {
+                return;
+            }
+        }
+        let Some(oled) = self
+            .ddc
+            .borrow()
+            .monitors
+            .iter()
+            .find(|m| m.tech_type == DisplayTechnology::Oled)
+            .map(|m| m.name.clone())
+        else {
+            return;
+        };
+
+        self.settings.borrow_mut().night_cap_suggested = true;
+        let _ = self.settings.borrow().save();
+
+        let toast = Toast::new(&format!(
+            "{} is an OLED panel; a night cap lowers its brightness at night",
+            oled
+        ));
+        toast.set_button_label(Some("Turn On"));
+        toast.set_timeout(0);
+        let ddc = self.ddc.clone();
+        let rows = self.monitor_rows.clone();
+        let settings = self.settings.clone();
+        toast.connect_button_clicked(move |_| {
+            settings.borrow_mut().night_cap.enabled = true;
+            let _ = settings.borrow().save();
+            if let (Ok(mut ddc), Ok(rows)) = (ddc.try_borrow_mut(), rows.try_borrow()) {
+                apply_night_cap(&mut ddc, &rows, &settings.borrow());
+            }
+        });
+        self.toast_overlay.add_toast(toast);
+    }
+
+    /// Selects the last active profile in the header bar without applying
+    /// it again.
+    pub fn show_active_profile(&self) {
+        let Some(name) = self.settings.borrow().active_profile.clone() else {
+            return;
+        };
+        show_profile(&self.profile_dropdown, &self.profile_quiet, &name);
+    }
+
+    /// Applies `default_profile`, if set, and selects it in the header
+    /// bar. A profile that no longer exists is ignored with a warning.
+    pub fn apply_startup_profile(&self) {
+        let Some(name) = self.settings.borrow().default_profile.clone() else {
+            return;
+        };
+        if !self.settings.borrow().profiles.contains_key(&name) {
+            eprintln!("Startup profile \"{}\" no longer exists, ignoring", name);
+            return;
+        }
+        if apply_profile(
+            &self.ddc,
+            &self.monitor_rows.borrow(),
+            &self.settings,
+            &self.toast_overlay,
+            &name,
+        ) {
+            show_profile(&self.profile_dropdown, &self.profile_quiet, &name);
+        }
     }
 
     pub fn init_brightness(&self) {
         let mut ddc = self.ddc.borrow_mut();
         let rows = self.monitor_rows.borrow();
         for (i, row) in rows.iter().enumerate() {
+            // A monitor in standby fails every other read; leave its row
+            // alone until it wakes.
+            if refresh_power_state(&mut ddc, i, row) {
+                continue;
+            }
+
             match ddc.get_brightness_percentage(i) {
                 Ok(percentage) => {
                     row.set_brightness(percentage);
@@ -480,7 +2298,7 @@ impl MainWindow {
                         row.set_dynamic_contrast(percentage);
                     }
                 }
-                Err(_) => {}
+                Err(_) => self.notify(&format!("Couldn't read brightness for {}", row.name)),
             }
 
             if row.has_contrast() {
@@ -488,7 +2306,7 @@ impl MainWindow {
                     Ok(percentage) => {
                         row.set_contrast(percentage);
                     }
-                    Err(_) => {}
+                    Err(_) => self.notify(&format!("Couldn't read contrast for {}", row.name)),
                 }
             }
 
@@ -497,7 +2315,13 @@ impl MainWindow {
                     Ok(percentage) => {
                         row.set_volume(percentage);
                     }
-                    Err(_) => {}
+                    Err(_) => self.notify(&format!("Couldn't read volume for {}", row.name)),
+                }
+            }
+
+            for channel in row.rgb_bias_channels() {
+                if let Ok(percentage) = ddc.get_rgb_bias(i, channel) {
+                    row.set_rgb_bias(channel, percentage);
                 }
             }
 
@@ -510,14 +2334,516 @@ impl MainWindow {
                 }
             }
 
-            if row.has_power_mode() {
-                match ddc.get_power_mode(i) {
-                    Ok(mode) => {
-                        row.set_power_mode(mode.code());
+            if row.has_color_temperature() {
+                if let Ok(Some(kelvin)) = ddc.get_color_temperature(i) {
+                    row.set_color_temperature(kelvin);
+                }
+            }
+
+            if row.has_osd_lock() {
+                if let Ok(locked) = ddc.get_osd_lock(i) {
+                    row.set_osd_lock(locked);
+                }
+            }
+
+            if row.has_invert() {
+                if let Ok(inverted) = ddc.get_invert(i) {
+                    row.set_invert(inverted);
+                }
+            }
+        }
+
+        // Without polling nothing else notices a monitor waking up.
+        if rows.iter().any(|r| r.is_asleep()) && self.settings.borrow().poll_interval_secs == 0 {
+            let ddc_wake = self.ddc.clone();
+            let rows_wake = self.monitor_rows.clone();
+            let retired = self.retired.clone();
+            glib::timeout_add_seconds_local(WAKE_CHECK_INTERVAL_SECS, move || {
+                if retired.get() {
+                    return glib::ControlFlow::Break;
+                }
+                if let (Ok(mut ddc), Ok(rows)) = (ddc_wake.try_borrow_mut(), rows_wake.try_borrow())
+                {
+                    poll_monitor_values(&mut ddc, &rows);
+                    if !rows.iter().any(|r| r.is_asleep()) {
+                        return glib::ControlFlow::Break;
                     }
-                    Err(_) => {}
+                }
+                glib::ControlFlow::Continue
+            });
+        }
+
+        apply_night_cap(&mut ddc, &rows, &self.settings.borrow());
+        apply_brightness_schedule(&mut ddc, &rows, &self.settings.borrow());
+    }
+}
+
+/// Sends slider changes to the monitors, straight away or, with a coalescing
+/// window, batched so only the latest value per control is written once the
+/// window has passed.
+#[derive(Clone)]
+struct SliderWriter {
+    ddc: Rc<RefCell<DdcManager>>,
+    window: Duration,
+    flush_scheduled: Rc<Cell<bool>>,
+    /// Slider to report each queued write's outcome on. It is dimmed while
+    /// the write is pending.
+    scales: Rc<RefCell<HashMap<(usize, Feature), Scale>>>,
+}
+
+impl SliderWriter {
+    fn new(ddc: Rc<RefCell<DdcManager>>, window_ms: u32) -> Self {
+        Self {
+            ddc,
+            window: Duration::from_millis(window_ms as u64),
+            flush_scheduled: Rc::new(Cell::new(false)),
+            scales: Rc::new(RefCell::new(HashMap::new())),
+        }
+    }
+
+    /// Writes or queues `value`. Returns false if the DDC connection was
+    /// busy and the change was dropped.
+    fn write(&self, index: usize, feature: Feature, value: u8, scale: Option<&Scale>) -> bool {
+        self.write_queued(index, feature, scale, |ddc| {
+            if self.window.is_zero() {
+                Some(ddc.set_percentage(index, feature, value))
+            } else {
+                ddc.queue_percentage(index, feature, value);
+                None
+            }
+        })
+    }
+
+    /// Like `write`, for the raw brightness brightness sliders move in.
+    fn write_brightness_raw(&self, index: usize, value: u16, scale: Option<&Scale>) -> bool {
+        self.write_queued(index, Feature::Brightness, scale, |ddc| {
+            if self.window.is_zero() {
+                Some(ddc.write_brightness_raw(index, value))
+            } else {
+                ddc.queue_brightness_raw(index, value);
+                None
+            }
+        })
+    }
+
+    /// Runs `write`, which returns the outcome of a write it made straight
+    /// away or `None` after queueing one, and tracks `scale` for it.
+    fn write_queued(
+        &self,
+        index: usize,
+        feature: Feature,
+        scale: Option<&Scale>,
+        write: impl FnOnce(&mut DdcManager) -> Option<Result<WriteOutcome, DdcError>>,
+    ) -> bool {
+        let Ok(mut ddc) = self.ddc.try_borrow_mut() else {
+            return false;
+        };
+        if let Some(outcome) = write(&mut ddc) {
+            if let Some(scale) = scale {
+                show_write_outcome(scale, outcome);
+            }
+            return true;
+        }
+
+        let mut scales = self.scales.borrow_mut();
+        let previous = match scale {
+            Some(scale) => {
+                scale.add_css_class("dim-label");
+                scales.insert((index, feature), scale.clone())
+            }
+            None => scales.remove(&(index, feature)),
+        };
+        if let Some(previous) = previous.filter(|p| Some(p) != scale) {
+            previous.remove_css_class("dim-label");
+        }
+        if !self.flush_scheduled.replace(true) {
+            let writer = self.clone();
+            glib::timeout_add_local_once(self.window, move || writer.flush());
+        }
+        true
+    }
+
+    fn flush(&self) {
+        let Ok(mut ddc) = self.ddc.try_borrow_mut() else {
+            let writer = self.clone();
+            glib::timeout_add_local_once(self.window, move || writer.flush());
+            return;
+        };
+        self.flush_scheduled.set(false);
+        let mut scales = self.scales.borrow_mut();
+        for (index, feature, outcome) in ddc.flush_pending() {
+            if let Some(scale) = scales.remove(&(index, feature)) {
+                scale.remove_css_class("dim-label");
+                show_write_outcome(&scale, outcome);
+            }
+        }
+        // Writes made directly since, e.g. by a profile, cancelled these.
+        for (_, scale) in scales.drain() {
+            scale.remove_css_class("dim-label");
+        }
+    }
+}
+
+/// Applies profile `name` and remembers it as the active one, with a toast
+/// saying how it went. Returns false, doing nothing, if there is no such
+/// profile or the monitors are busy.
+fn apply_profile(
+    ddc: &RefCell<DdcManager>,
+    rows: &[MonitorRow],
+    settings: &RefCell<AppSettings>,
+    toast_overlay: &ToastOverlay,
+    name: &str,
+) -> bool {
+    let Some(profile) = settings.borrow().profiles.get(name).cloned() else {
+        return false;
+    };
+    let Ok(mut ddc) = ddc.try_borrow_mut() else {
+        return false;
+    };
+    let summary = profile.apply(&mut ddc);
+    poll_monitor_values(&mut ddc, rows);
+    settings.borrow_mut().active_profile = Some(name.to_string());
+    let _ = settings.borrow().save();
+    let message = if summary.failed.is_empty() {
+        format!("Applied profile \"{}\"", name)
+    } else {
+        format!(
+            "Applied profile \"{}\", failed on {}",
+            name,
+            summary.failed.join(", ")
+        )
+    };
+    toast_overlay.add_toast(Toast::new(&message));
+    true
+}
+
+/// Selects profile `name` in the header bar without applying it again.
+fn show_profile(dropdown: &DropDown, quiet: &Cell<bool>, name: &str) {
+    let position = dropdown
+        .model()
+        .and_downcast::<StringList>()
+        .and_then(|list| string_list_position(&list, name));
+    if let Some(pos) = position {
+        quiet.set(true);
+        dropdown.set_selected(pos);
+        quiet.set(false);
+    }
+}
+
+/// Brightness values saved for the monitor with settings key `key` across
+/// all profiles, for marking on its slider.
+fn profile_brightness_marks(settings: &AppSettings, key: &str) -> Vec<u8> {
+    let mut marks: Vec<u8> = settings
+        .profiles
+        .values()
+        .filter_map(|p| p.monitors.get(key)?.brightness)
+        .collect();
+    marks.sort_unstable();
+    marks.dedup();
+    marks
+}
+
+/// Applies `value` to the monitors linked to row `source` for `feature`,
+/// skipping those without that control.
+fn mirror_to_linked(
+    rows: &[MonitorRow],
+    ddc: &mut DdcManager,
+    settings: &AppSettings,
+    source: usize,
+    feature: Feature,
+    value: u8,
+) {
+    let linked = settings.linked_monitors(&rows[source].settings_key, feature);
+    for (i, row) in rows.iter().enumerate() {
+        if i == source || !linked.contains(row.settings_key.as_str()) {
+            continue;
+        }
+        match feature {
+            Feature::Brightness => {
+                let achieved = match ddc.set_brightness_percentage(i, value) {
+                    Ok(WriteOutcome::Clamped { achieved, .. }) => achieved,
+                    _ => value,
+                };
+                row.set_brightness(achieved);
+            }
+            Feature::Contrast if row.has_contrast() => {
+                row.set_contrast(value);
+                let _ = ddc.set_contrast_percentage(i, value);
+            }
+            Feature::Volume if row.has_volume() => {
+                row.set_volume(value);
+                let _ = ddc.set_volume_percentage(i, value);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Port, native resolution and MCCS version, as far as they are known, so
+/// rows can be matched to the screens in front of the user.
+fn monitor_subtitle(ddc: &DdcManager, index: usize, connector_details: bool) -> String {
+    let mut parts = Vec::new();
+    if connector_details {
+        let info = ddc.connector_info(index);
+        parts.push(info.port);
+        if let Some((width, height)) = info.native_mode {
+            parts.push(format!("{}×{}", width, height));
+        }
+        if info.enabled == Some(false) {
+            parts.push("disabled".to_string());
+        }
+    }
+    if let Some(version) = ddc.monitors[index].mccs_version {
+        parts.push(format!("MCCS {}", version));
+    }
+    parts.join(" · ")
+}
+
+/// Fills `list` with the rows, pinned monitors first and discovery order
+/// kept within each group.
+fn order_monitor_rows(list: &ListBox, rows: &[MonitorRow], favorites: &HashSet<String>) {
+    let mut ordered: Vec<&MonitorRow> = rows.iter().collect();
+    ordered.sort_by_key(|row| !favorites.contains(&row.settings_key));
+    for row in &ordered {
+        if row.container.parent().is_some() {
+            list.remove(&row.container);
+        }
+    }
+    for row in ordered {
+        list.append(&row.container);
+    }
+}
+
+/// Reads the power mode of a monitor that reports one and marks its row
+/// asleep or awake. Returns whether it is asleep, in which case its other
+/// values should not be read.
+fn refresh_power_state(ddc: &mut DdcManager, index: usize, row: &MonitorRow) -> bool {
+    if !row.has_power_mode() {
+        return false;
+    }
+    let Ok(mode) = ddc.get_power_mode(index) else {
+        return false;
+    };
+    row.set_power_mode(mode.code());
+    row.set_asleep(mode.is_asleep());
+    mode.is_asleep()
+}
+
+/// Re-reads brightness, contrast and volume and updates any row whose
+/// sliders no longer match, e.g. after a change on the monitor's OSD.
+/// Monitors in standby are skipped until they wake.
+fn poll_monitor_values(ddc: &mut DdcManager, rows: &[MonitorRow]) {
+    for (i, row) in rows.iter().enumerate() {
+        if refresh_power_state(ddc, i, row) {
+            continue;
+        }
+
+        if let Ok(percentage) = ddc.get_brightness_percentage(i) {
+            if row.brightness_value() != percentage {
+                row.set_brightness(percentage);
+                if row.has_dynamic_contrast() {
+                    row.set_dynamic_contrast(percentage);
+                }
+            }
+        }
+
+        if row.has_contrast() {
+            if let Ok(percentage) = ddc.get_contrast_percentage(i) {
+                if row.contrast_value() != Some(percentage) {
+                    row.set_contrast(percentage);
                 }
             }
         }
+
+        if row.has_volume() {
+            if let Ok(percentage) = ddc.get_volume_percentage(i) {
+                if row.volume_value() != Some(percentage) {
+                    row.set_volume(percentage);
+                }
+            }
+        }
+    }
+}
+
+/// A labelled dropdown choosing a profile from `list`, whose first entry
+/// stands for none, with `selected` preselected if it is still listed.
+fn profile_choice_row(label: &str, list: &StringList, selected: Option<&str>) -> (Box, DropDown) {
+    let row = Box::new(Orientation::Horizontal, 8);
+    let label = Label::new(Some(label));
+    label.set_hexpand(true);
+    label.set_halign(gtk::Align::Start);
+    let dropdown = DropDown::new(Some(list.clone()), None::<gtk::Expression>);
+    if let Some(pos) = selected.and_then(|name| string_list_position(list, name)) {
+        dropdown.set_selected(pos);
+    }
+    row.append(&label);
+    row.append(&dropdown);
+    (row, dropdown)
+}
+
+fn string_list_position(list: &StringList, value: &str) -> Option<u32> {
+    (0..list.n_items()).find(|&i| list.string(i).is_some_and(|s| s == value))
+}
+
+fn selected_string(dropdown: &DropDown) -> Option<String> {
+    dropdown
+        .selected_item()
+        .and_downcast::<gtk::StringObject>()
+        .map(|item| item.string().to_string())
+}
+
+/// Contrast that dynamic contrast pairs with `brightness` on the monitor
+/// whose settings key is `key`.
+fn dynamic_contrast_for(settings: &AppSettings, key: &str, brightness: u8) -> u8 {
+    let ratio = if settings.dynamic_contrast_per_monitor_ratio {
+        *settings
+            .monitor_ratios
+            .get(key)
+            .unwrap_or(&settings.dynamic_contrast_ratio)
+    } else {
+        settings.dynamic_contrast_ratio
+    };
+    ((brightness as f32 * ratio).round() as u8).min(100)
+}
+
+/// Notes on `scale`'s tooltip when the monitor settled on a different value
+/// than the one written, and clears the note once a write lands exactly.
+/// The calibration line the row keeps on the tooltip stays first.
+fn show_write_outcome(scale: &Scale, outcome: Result<WriteOutcome, DdcError>) {
+    const NOTE: &str = "Monitor set";
+    let calibration = scale.tooltip_text().and_then(|text| {
+        text.lines()
+            .find(|l| !l.starts_with(NOTE))
+            .map(str::to_string)
+    });
+    let note = match outcome {
+        Ok(WriteOutcome::Clamped {
+            requested,
+            achieved,
+        }) => format!("{} {}% instead of {}%", NOTE, achieved, requested),
+        Ok(WriteOutcome::Exact) => String::new(),
+        _ => return,
+    };
+    let text = [calibration.unwrap_or_default(), note]
+        .into_iter()
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n");
+    scale.set_tooltip_text((!text.is_empty()).then_some(text.as_str()));
+}
+
+/// Suspends the brightness schedule for monitor `index` after a manual
+/// change, either for the configured duration or until the next breakpoint.
+fn note_schedule_override(
+    ddc: &RefCell<DdcManager>,
+    index: usize,
+    settings: &AppSettings,
+    key: &str,
+) {
+    let Some(schedule) = settings.monitor_schedules.get(key).filter(|s| s.enabled) else {
+        return;
+    };
+    let minutes = if settings.schedule_override_minutes > 0 {
+        Some(settings.schedule_override_minutes)
+    } else {
+        schedule::local_minute_of_day().and_then(|m| schedule.minutes_until_next_breakpoint(m))
+    };
+    if let (Some(minutes), Ok(mut ddc)) = (minutes, ddc.try_borrow_mut()) {
+        ddc.hold(
+            index,
+            Feature::Brightness,
+            Duration::from_secs(minutes as u64 * 60),
+        );
+    }
+}
+
+/// Brightness `row` shows, on whichever slider is in use.
+fn row_brightness(row: &MonitorRow) -> u8 {
+    if row.is_dynamic_contrast_active() {
+        row.dynamic_contrast_value()
+    } else {
+        row.brightness_value()
+    }
+}
+
+/// Writes `percentage` to monitor `index`, with contrast to match in
+/// dynamic contrast mode, and moves the row to the brightness the monitor
+/// ended up at, which the night cap may hold lower.
+fn write_row_brightness(
+    ddc: &mut DdcManager,
+    settings: &AppSettings,
+    index: usize,
+    row: &MonitorRow,
+    percentage: u8,
+) -> Result<u8, DdcError> {
+    let achieved = match ddc.set_brightness_percentage(index, percentage)? {
+        WriteOutcome::Clamped { achieved, .. } => achieved,
+        _ => percentage,
+    };
+    if row.is_dynamic_contrast_active() {
+        let contrast = dynamic_contrast_for(settings, &row.settings_key, achieved);
+        ddc.set_contrast_percentage(index, contrast)?;
+        row.set_dynamic_contrast(achieved);
+    } else {
+        row.set_brightness(achieved);
+    }
+    Ok(achieved)
+}
+
+/// Puts the night cap in force or lifts it, as the time of day requires.
+/// When it starts, monitors brighter than it are dimmed to it.
+fn apply_night_cap(ddc: &mut DdcManager, rows: &[MonitorRow], settings: &AppSettings) {
+    let ceiling = schedule::local_minute_of_day().and_then(|m| settings.night_cap.ceiling_at(m));
+    // Read before the rows' ceilings move their sliders.
+    let shown: Vec<u8> = rows.iter().map(row_brightness).collect();
+    for row in rows {
+        row.set_brightness_ceiling(ceiling);
+    }
+    if ceiling == ddc.brightness_ceiling() {
+        return;
+    }
+    ddc.set_brightness_ceiling(ceiling);
+
+    for (i, row) in rows.iter().enumerate() {
+        let target = match ceiling {
+            Some(ceiling) if shown[i] > ceiling => shown[i],
+            Some(_) => continue,
+            None => match ddc.capped_brightness(i) {
+                Some(intended) => intended,
+                None => continue,
+            },
+        };
+        let _ = write_row_brightness(ddc, settings, i, row, target);
+    }
+}
+
+fn apply_brightness_schedule(
+    ddc: &mut DdcManager,
+    rows: &[MonitorRow],
+    settings: &AppSettings,
+) {
+    let Some(minute) = schedule::local_minute_of_day() else {
+        return;
+    };
+
+    for (i, row) in rows.iter().enumerate() {
+        if ddc.is_held(i, Feature::Brightness) {
+            continue;
+        }
+        let Some(target) = settings
+            .monitor_schedules
+            .get(&row.settings_key)
+            .filter(|s| s.enabled)
+            .and_then(|s| s.brightness_at(minute))
+        else {
+            continue;
+        };
+
+        let allowed = ddc
+            .brightness_ceiling()
+            .map_or(target, |ceiling| target.min(ceiling));
+        if row_brightness(row) != allowed {
+            let _ = write_row_brightness(ddc, settings, i, row, target);
+        }
     }
 }
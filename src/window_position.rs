@@ -0,0 +1,87 @@
+use gtk::gdk;
+use gtk::glib::translate::ToGlibPtr;
+use gtk::prelude::*;
+use std::ffi::{c_int, c_ulong, c_void, CStr};
+
+type XDisplay = c_void;
+type XWindow = c_ulong;
+type GetXDisplay = unsafe extern "C" fn(*mut gdk::ffi::GdkDisplay) -> *mut XDisplay;
+type GetXid = unsafe extern "C" fn(*mut gdk::ffi::GdkSurface) -> XWindow;
+type DefaultRootWindow = unsafe extern "C" fn(*mut XDisplay) -> XWindow;
+type TranslateCoordinates = unsafe extern "C" fn(
+    *mut XDisplay,
+    XWindow,
+    XWindow,
+    c_int,
+    c_int,
+    *mut c_int,
+    *mut c_int,
+    *mut XWindow,
+) -> c_int;
+type MoveWindow = unsafe extern "C" fn(*mut XDisplay, XWindow, c_int, c_int) -> c_int;
+
+/// Function `name` from the libraries already loaded, as type `F`.
+///
+/// # Safety
+///
+/// `F` must be a function pointer type matching the C declaration of `name`.
+unsafe fn symbol<F: Copy>(name: &CStr) -> Option<F> {
+    let pointer = libc::dlsym(libc::RTLD_DEFAULT, name.as_ptr());
+    (!pointer.is_null()).then(|| std::mem::transmute_copy::<*mut c_void, F>(&pointer))
+}
+
+/// The X display and window showing `window`, when it is shown on X11.
+///
+/// GTK 4 can neither read nor set a window's position, so on X11 both go
+/// through Xlib, whose functions GDK's X11 backend has already loaded and
+/// are looked up rather than linked against. On Wayland the compositor
+/// decides where windows open, and there is nothing to do.
+fn x11_window(window: &impl IsA<gtk::Native>) -> Option<(*mut XDisplay, XWindow)> {
+    let surface = window.surface()?;
+    let display = surface.display();
+    if display.type_().name() != "GdkX11Display" {
+        return None;
+    }
+    unsafe {
+        let get_xdisplay = symbol::<GetXDisplay>(c"gdk_x11_display_get_xdisplay")?;
+        let get_xid = symbol::<GetXid>(c"gdk_x11_surface_get_xid")?;
+        Some((
+            get_xdisplay(display.to_glib_none().0),
+            get_xid(surface.to_glib_none().0),
+        ))
+    }
+}
+
+/// Where `window`'s top-left corner is on the screen, if it can be told.
+pub fn position(window: &impl IsA<gtk::Native>) -> Option<(i32, i32)> {
+    let (display, xid) = x11_window(window)?;
+    unsafe {
+        let root = symbol::<DefaultRootWindow>(c"XDefaultRootWindow")?;
+        let translate = symbol::<TranslateCoordinates>(c"XTranslateCoordinates")?;
+        let (mut x, mut y, mut child) = (0, 0, 0);
+        let found = translate(
+            display,
+            xid,
+            root(display),
+            0,
+            0,
+            &mut x,
+            &mut y,
+            &mut child,
+        );
+        (found != 0).then_some((x, y))
+    }
+}
+
+/// Moves `window`'s top-left corner to `(x, y)` on the screen, where the
+/// windowing system allows it. The window has to be mapped.
+pub fn move_to(window: &impl IsA<gtk::Native>, x: i32, y: i32) {
+    let Some((display, xid)) = x11_window(window) else {
+        return;
+    };
+    unsafe {
+        if let Some(move_window) = symbol::<MoveWindow>(c"XMoveWindow") {
+            move_window(display, xid, x, y);
+        }
+    }
+}